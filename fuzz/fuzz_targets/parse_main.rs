@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use nginx_config::{parse_main, parse_main_recovering};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = parse_main(text);
+        let _ = parse_main_recovering(text);
+    }
+});
@@ -0,0 +1,19 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use nginx_config::parse_main;
+
+// Formatted config must parse again and format the same way
+fuzz_target!(|data: &[u8]| {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    if let Ok(ast) = parse_main(text) {
+        let formatted = ast.to_string();
+        let again = parse_main(&formatted).unwrap_or_else(|e| {
+            panic!("formatted config {:?} fails to parse: {}", formatted, e)
+        });
+        assert_eq!(again.to_string(), formatted);
+    }
+});
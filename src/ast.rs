@@ -5,19 +5,47 @@ use std::path::PathBuf;
 use std::net::{SocketAddr, IpAddr};
 
 pub use value::{Value};
-use position::Pos;
+pub use units::{Size, Duration};
+use position::{Pos, Span};
 use visitors::{DirectiveIter};
 
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Main {
     pub directives: Vec<Directive>,
+    /// Comments after the last directive of the file
+    pub end_comments: Vec<String>,
 }
 
+/// A directive with its position in the source
+///
+/// The `position` is the start of the directive name, the `span` covers
+/// everything from the name up to and including the final `;` or `}`.
+///
+/// Block directives also have `position: (Pos, Pos)` in their structure,
+/// which is the position of the opening brace and the position right
+/// after the closing brace.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Directive {
     pub position: Pos,
+    pub span: Span,
     pub item: Item,
+    pub comments: Comments,
+}
+
+/// Comments attached to a directive
+///
+/// Text of each comment is everything after the `#` sign (with trailing
+/// whitespace stripped). Comments between arguments of a directive are
+/// not preserved.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Comments {
+    /// Comments on the lines preceding the directive
+    pub leading: Vec<String>,
+    /// A comment on the same line after the directive
+    pub trailing: Option<String>,
+    /// Comments before the closing brace of the directive's block
+    pub block_end: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -26,12 +54,173 @@ pub enum WorkerProcesses {
     Exact(u32),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkerCpuAffinity {
+    /// `auto` with an optional mask limiting the CPUs to bind to
+    Auto(Option<String>),
+    /// One CPU set (bitmask) per worker process
+    Masks(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadPool {
+    pub name: String,
+    pub threads: u32,
+    pub max_queue: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Events {
+    pub position: (Pos, Pos),
+    pub directives: Vec<Directive>,
+}
+
+/// Connection processing method (the `use` directive)
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+pub enum ConnectionMethod {
+    Select,
+    Poll,
+    Kqueue,
+    Epoll,
+    DevPoll,
+    EventPort,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Http {
     pub position: (Pos, Pos),
     pub directives: Vec<Directive>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+pub enum SslProtocol {
+    SslV2,
+    SslV3,
+    TlsV1,
+    TlsV1_1,
+    TlsV1_2,
+    TlsV1_3,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SslSessionCache {
+    /// Session cache is strictly prohibited
+    Off,
+    /// Clients are told that sessions may be reused but they aren't stored
+    None,
+    /// Any combination of the built-in and shared caches
+    Caches(Vec<SslCache>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SslCache {
+    /// Built-in OpenSSL cache with optional size in sessions
+    Builtin(Option<u32>),
+    /// Cache shared between worker processes
+    Shared { name: String, size: Size },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+pub enum SslVerifyClient {
+    On,
+    Off,
+    Optional,
+    OptionalNoCa,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+pub enum GrpcScheme {
+    Grpc,
+    Grpcs,
+}
+
+/// The `grpc_pass` directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcPass {
+    /// Explicit `grpc://` or `grpcs://` scheme (nginx defaults to plaintext)
+    pub scheme: Option<GrpcScheme>,
+    /// Address or upstream name without the scheme
+    pub address: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateUnit {
+    PerSecond,
+    PerMinute,
+}
+
+/// Request rate, like `10r/s`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    pub requests: u32,
+    pub unit: RateUnit,
+}
+
+/// Argument which is parsed into a typed value unless it has variables
+///
+/// Nginx itself doesn't allow variables in most of such arguments, but they
+/// are kept as is, so that templates are parsed and can be formatted back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Typed<T> {
+    Parsed(T),
+    Variables(Value),
+}
+
+impl<T> Typed<T> {
+    /// Returns the parsed value if the argument has no variables
+    pub fn parsed(&self) -> Option<&T> {
+        match *self {
+            Typed::Parsed(ref v) => Some(v),
+            Typed::Variables(_) => None,
+        }
+    }
+    fn visit_value<F: FnMut(&mut Value)>(&mut self, f: &mut F) {
+        if let Typed::Variables(ref mut v) = *self {
+            f(v);
+        }
+    }
+}
+
+/// The `limit_req_zone` directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitReqZone {
+    pub key: Value,
+    pub zone: String,
+    pub size: Size,
+    pub rate: Rate,
+    pub sync: bool,
+}
+
+/// The `limit_conn_zone` directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitConnZone {
+    pub key: Value,
+    pub zone: String,
+    pub size: Size,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitReqDelay {
+    NoDelay,
+    /// Number of excessive requests to pass without delay
+    Delay(u32),
+}
+
+/// The `limit_req` directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitReq {
+    pub zone: String,
+    pub burst: Option<u32>,
+    pub delay: Option<LimitReqDelay>,
+}
+
+/// The `stream` block (TCP/UDP proxying)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stream {
+    pub position: (Pos, Pos),
+    pub directives: Vec<Directive>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Server {
     pub position: (Pos, Pos),
@@ -81,6 +270,8 @@ pub struct Listen {
     pub address: Address,
     pub default_server: bool,
     pub ssl: bool,
+    /// UDP socket (stream module only)
+    pub udp: bool,
     pub ext: Option<HttpExt>,
     pub proxy_protocol: bool,
     pub setfib: Option<i32>,
@@ -104,6 +295,7 @@ impl Listen {
             address,
             default_server: false,
             ssl: false,
+            udp: false,
             ext: None,
             proxy_protocol: false,
             setfib: None,
@@ -191,6 +383,81 @@ pub struct Map {
     pub volatile: bool,
     pub includes: Vec<String>,
     pub patterns: Vec<(MapPattern, Value)>,
+    // boxed to keep the size of `Item` small
+    pub comments: Box<MapComments>,
+}
+
+/// Comments attached to the entries of a `map` block
+///
+/// Entries are kept in the same order as `includes` and `patterns` of the
+/// `Map`. Comments before the closing brace of the block are stored in the
+/// `block_end` of the directive's `Comments`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MapComments {
+    pub volatile: Comments,
+    pub hostnames: Comments,
+    pub default: Comments,
+    pub includes: Vec<Comments>,
+    pub patterns: Vec<Comments>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upstream {
+    pub position: (Pos, Pos),
+    pub name: String,
+    pub directives: Vec<Directive>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpstreamAddress {
+    /// IP address with optional port
+    Ip(IpAddr, Option<u16>),
+    /// Domain name with optional port
+    Name(String, Option<u16>),
+    Unix(PathBuf),
+}
+
+/// The `server` directive in the `upstream` block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamServer {
+    pub address: UpstreamAddress,
+    pub weight: Option<u32>,
+    pub max_conns: Option<u32>,
+    pub max_fails: Option<u32>,
+    pub fail_timeout: Option<Duration>,
+    pub backup: bool,
+    pub down: bool,
+    pub resolve: bool,
+    pub service: Option<String>,
+    /// Parameters which are not parsed (like `slow_start=` or `route=`)
+    ///
+    /// Kept as is, in the original order, and written after all the known
+    /// parameters.
+    pub other: Vec<String>,
+}
+
+impl UpstreamServer {
+    pub fn new(address: UpstreamAddress) -> UpstreamServer {
+        UpstreamServer {
+            address,
+            weight: None,
+            max_conns: None,
+            max_fails: None,
+            fail_timeout: None,
+            backup: false,
+            down: false,
+            resolve: false,
+            service: None,
+            other: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+pub enum RandomMethod {
+    LeastConn,
+    LeastTimeHeader,
+    LeastTimeLastByte,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -289,6 +556,8 @@ pub enum ProxyNextUpstreamFlag {
     Off,
 }
 
+// boxing would make matching on the options much less convenient
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AccessLog {
     Off,
@@ -299,9 +568,9 @@ pub enum AccessLog {
 pub struct AccessLogOptions {
     pub path: Value,
     pub format: Option<String>,
-    pub buffer: Option<String>,
+    pub buffer: Option<Size>,
     pub gzip: Option<Option<u8>>,
-    pub flush: Option<String>,
+    pub flush: Option<Duration>,
     pub condition: Option<Value>,
 }
 
@@ -337,6 +606,23 @@ pub enum Item {
     Daemon(bool),
     MasterProcess(bool),
     WorkerProcesses(WorkerProcesses),
+    User { user: String, group: Option<String> },
+    Pid(Value),
+    WorkerRlimitNofile(u32),
+    WorkerCpuAffinity(WorkerCpuAffinity),
+    WorkerPriority(i32),
+    Env { name: String, value: Option<String> },
+    LoadModule(Value),
+    PcreJit(bool),
+    ThreadPool(ThreadPool),
+    TimerResolution(Typed<Duration>),
+    LockFile(Value),
+    // events module
+    Events(Events),
+    WorkerConnections(u32),
+    Use(ConnectionMethod),
+    MultiAccept(bool),
+    AcceptMutex(bool),
     Http(Http),
     Server(Server),
     Location(Location),
@@ -344,8 +630,8 @@ pub enum Item {
     ProxyPass(Value),
     ProxySetHeader { field: Value, value: Value },
     ProxyMethod(Value),
-    ProxyReadTimeout(Value),
-    ProxyConnectTimeout(Value),
+    ProxyReadTimeout(Typed<Duration>),
+    ProxyConnectTimeout(Typed<Duration>),
     ProxyHideHeader(Value),
     ProxyPassHeader(Value),
     ProxyPassRequestBody(bool),
@@ -358,7 +644,7 @@ pub enum Item {
     ProxyCacheKey(Value),
     ProxyCacheValid(ProxyCacheValid),
     ProxyNextUpstreamTries(Value),
-    ProxyNextUpstreamTimeout(Value),
+    ProxyNextUpstreamTimeout(Typed<Duration>),
     ProxyNextUpstream(Vec<ProxyNextUpstreamFlag>),
     Gzip(bool),
     GzipStatic(GzipStatic),
@@ -372,12 +658,13 @@ pub enum Item {
     ErrorLog { file: Value, level: Option<ErrorLevel>},
     Rewrite(Rewrite),
     Return(Return),
+    Break,
     If(If),
     TryFiles(TryFiles),
     ServerName(Vec<ServerName>),
     Set { variable: String, value: Value },
     Map(Map),
-    ClientMaxBodySize(Value),
+    ClientMaxBodySize(Typed<Size>),
     Include(Value),
     EmptyGif,
     Internal,
@@ -385,10 +672,29 @@ pub enum Item {
     Etag(bool),
     RecursiveErrorPages(bool),
     ChunkedTransferEncoding(bool),
-    KeepaliveTimeout(Value, Option<Value>),
+    KeepaliveTimeout(Typed<Duration>, Option<Typed<Duration>>),
     ServerTokens(Value),
+    // ssl module
     SslCertificate(Value),
     SslCertificateKey(Value),
+    SslProtocols(Vec<SslProtocol>),
+    SslCiphers(Value),
+    SslPreferServerCiphers(bool),
+    SslSessionCache(SslSessionCache),
+    SslSessionTimeout(Typed<Duration>),
+    SslSessionTickets(bool),
+    SslDhparam(Value),
+    SslEcdhCurve(Value),
+    SslStapling(bool),
+    SslStaplingVerify(bool),
+    SslStaplingFile(Value),
+    SslStaplingResponder(Value),
+    SslTrustedCertificate(Value),
+    SslClientCertificate(Value),
+    SslVerifyClient(SslVerifyClient),
+    SslVerifyDepth(u32),
+    SslEarlyData(bool),
+    SslConfCommand { name: Value, value: Value },
     // openresty
     RewriteByLuaFile(Value),
     BalancerByLuaFile(Value),
@@ -412,16 +718,142 @@ pub enum Item {
     SetRealIpFrom(RealIpFrom),
     // index module
     Index(Vec<Value>),
+    // fastcgi module
+    FastcgiPass(Value),
+    FastcgiParam { name: Value, value: Value, if_not_empty: bool },
+    FastcgiBuffering(bool),
+    FastcgiBufferSize(Typed<Size>),
+    FastcgiCache(Value),
+    FastcgiCacheKey(Value),
+    FastcgiCacheValid(ProxyCacheValid),
+    FastcgiConnectTimeout(Typed<Duration>),
+    FastcgiReadTimeout(Typed<Duration>),
+    FastcgiSendTimeout(Typed<Duration>),
+    FastcgiNextUpstream(Vec<ProxyNextUpstreamFlag>),
+    FastcgiNextUpstreamTries(Value),
+    FastcgiNextUpstreamTimeout(Typed<Duration>),
+    FastcgiInterceptErrors(bool),
+    FastcgiHideHeader(Value),
+    FastcgiPassHeader(Value),
+    FastcgiIndex(Value),
+    /// Regular expression capturing script name and path info
+    FastcgiSplitPathInfo(String),
+    // uwsgi module
+    UwsgiPass(Value),
+    UwsgiParam { name: Value, value: Value, if_not_empty: bool },
+    UwsgiBuffering(bool),
+    UwsgiBufferSize(Typed<Size>),
+    UwsgiCache(Value),
+    UwsgiCacheKey(Value),
+    UwsgiCacheValid(ProxyCacheValid),
+    UwsgiConnectTimeout(Typed<Duration>),
+    UwsgiReadTimeout(Typed<Duration>),
+    UwsgiSendTimeout(Typed<Duration>),
+    UwsgiNextUpstream(Vec<ProxyNextUpstreamFlag>),
+    UwsgiNextUpstreamTries(Value),
+    UwsgiNextUpstreamTimeout(Typed<Duration>),
+    UwsgiInterceptErrors(bool),
+    UwsgiHideHeader(Value),
+    UwsgiPassHeader(Value),
+    // scgi module
+    ScgiPass(Value),
+    ScgiParam { name: Value, value: Value, if_not_empty: bool },
+    ScgiBuffering(bool),
+    ScgiBufferSize(Typed<Size>),
+    ScgiCache(Value),
+    ScgiCacheKey(Value),
+    ScgiCacheValid(ProxyCacheValid),
+    ScgiConnectTimeout(Typed<Duration>),
+    ScgiReadTimeout(Typed<Duration>),
+    ScgiSendTimeout(Typed<Duration>),
+    ScgiNextUpstream(Vec<ProxyNextUpstreamFlag>),
+    ScgiNextUpstreamTries(Value),
+    ScgiNextUpstreamTimeout(Typed<Duration>),
+    ScgiInterceptErrors(bool),
+    ScgiHideHeader(Value),
+    ScgiPassHeader(Value),
+    // grpc module
+    GrpcPass(GrpcPass),
+    GrpcSetHeader { field: Value, value: Value },
+    GrpcReadTimeout(Typed<Duration>),
+    GrpcSendTimeout(Typed<Duration>),
+    GrpcConnectTimeout(Typed<Duration>),
+    GrpcSslCertificate(Value),
+    GrpcSslCertificateKey(Value),
+    GrpcSslCiphers(Value),
+    GrpcSslProtocols(Vec<SslProtocol>),
+    GrpcSslServerName(bool),
+    GrpcSslName(Value),
+    GrpcSslVerify(bool),
+    GrpcSslVerifyDepth(u32),
+    GrpcSslTrustedCertificate(Value),
+    GrpcSslSessionReuse(bool),
+    GrpcNextUpstream(Vec<ProxyNextUpstreamFlag>),
+    GrpcNextUpstreamTries(Value),
+    GrpcNextUpstreamTimeout(Typed<Duration>),
+    GrpcBufferSize(Typed<Size>),
+    GrpcInterceptErrors(bool),
+    GrpcHideHeader(Value),
+    // stream module
+    Stream(Stream),
+    ProxyTimeout(Typed<Duration>),
+    ProxyResponses(Value),
+    SslPreread(bool),
+    // limit_req and limit_conn modules
+    LimitReqZone(LimitReqZone),
+    LimitReq(LimitReq),
+    LimitReqStatus(u32),
+    LimitReqLogLevel(ErrorLevel),
+    LimitReqDryRun(bool),
+    LimitConnZone(LimitConnZone),
+    LimitConn { zone: String, limit: u32 },
+    LimitConnStatus(u32),
+    LimitRate(Typed<Size>),
+    LimitRateAfter(Typed<Size>),
+    // upstream module
+    Upstream(Upstream),
+    UpstreamServer(UpstreamServer),
+    LeastConn,
+    IpHash,
+    Hash { key: Value, consistent: bool },
+    Random { two: bool, method: Option<RandomMethod> },
+    Keepalive(u32),
+    KeepaliveRequests(Value),
+    Zone { name: String, size: Option<Size> },
+    /// Any directive or block which is not known by the parser
+    ///
+    /// Arguments which are not valid expressions are kept as literals.
+    Unknown {
+        name: String,
+        args: Vec<Value>,
+        block: Option<Vec<Directive>>,
+    },
 }
 
 impl Item {
 
-    pub fn directive_name(&self) -> &'static str {
+    pub fn directive_name(&self) -> &str {
         use self::Item::*;
         match *self {
             Daemon(..) => "daemon",
             MasterProcess(..) => "master_process",
             WorkerProcesses(..) => "worker_processes",
+            User { .. } => "user",
+            Pid(..) => "pid",
+            WorkerRlimitNofile(..) => "worker_rlimit_nofile",
+            WorkerCpuAffinity(..) => "worker_cpu_affinity",
+            WorkerPriority(..) => "worker_priority",
+            Env { .. } => "env",
+            LoadModule(..) => "load_module",
+            PcreJit(..) => "pcre_jit",
+            ThreadPool(..) => "thread_pool",
+            TimerResolution(..) => "timer_resolution",
+            LockFile(..) => "lock_file",
+            Events(..) => "events",
+            WorkerConnections(..) => "worker_connections",
+            Use(..) => "use",
+            MultiAccept(..) => "multi_accept",
+            AcceptMutex(..) => "accept_mutex",
             Http(..) => "http",
             Server(..) => "server",
             Location(..) => "location",
@@ -458,6 +890,7 @@ impl Item {
             ErrorLog {..} => "error_log",
             Rewrite(..) => "rewrite",
             Return(..) => "return",
+            Break => "break",
             If(..) => "if",
             TryFiles(..) => "try_files",
             ServerName(..) => "server_name",
@@ -474,6 +907,24 @@ impl Item {
             ServerTokens(..) => "server_tokens",
             SslCertificate(..) => "ssl_certificate",
             SslCertificateKey(..) => "ssl_certificate_key",
+            SslProtocols(..) => "ssl_protocols",
+            SslCiphers(..) => "ssl_ciphers",
+            SslPreferServerCiphers(..) => "ssl_prefer_server_ciphers",
+            SslSessionCache(..) => "ssl_session_cache",
+            SslSessionTimeout(..) => "ssl_session_timeout",
+            SslSessionTickets(..) => "ssl_session_tickets",
+            SslDhparam(..) => "ssl_dhparam",
+            SslEcdhCurve(..) => "ssl_ecdh_curve",
+            SslStapling(..) => "ssl_stapling",
+            SslStaplingVerify(..) => "ssl_stapling_verify",
+            SslStaplingFile(..) => "ssl_stapling_file",
+            SslStaplingResponder(..) => "ssl_stapling_responder",
+            SslTrustedCertificate(..) => "ssl_trusted_certificate",
+            SslClientCertificate(..) => "ssl_client_certificate",
+            SslVerifyClient(..) => "ssl_verify_client",
+            SslVerifyDepth(..) => "ssl_verify_depth",
+            SslEarlyData(..) => "ssl_early_data",
+            SslConfCommand { .. } => "ssl_conf_command",
             // openresty
             RewriteByLuaFile(..) => "rewrite_by_lua_file",
             BalancerByLuaFile(..) => "balancer_by_lua_file",
@@ -497,6 +948,108 @@ impl Item {
             SetRealIpFrom(..) => "set_real_ip_from",
             // index module
             Index(..) => "index",
+            // fastcgi module
+            FastcgiPass(..) => "fastcgi_pass",
+            FastcgiParam { .. } => "fastcgi_param",
+            FastcgiBuffering(..) => "fastcgi_buffering",
+            FastcgiBufferSize(..) => "fastcgi_buffer_size",
+            FastcgiCache(..) => "fastcgi_cache",
+            FastcgiCacheKey(..) => "fastcgi_cache_key",
+            FastcgiCacheValid(..) => "fastcgi_cache_valid",
+            FastcgiConnectTimeout(..) => "fastcgi_connect_timeout",
+            FastcgiReadTimeout(..) => "fastcgi_read_timeout",
+            FastcgiSendTimeout(..) => "fastcgi_send_timeout",
+            FastcgiNextUpstream(..) => "fastcgi_next_upstream",
+            FastcgiNextUpstreamTries(..) => "fastcgi_next_upstream_tries",
+            FastcgiNextUpstreamTimeout(..) => "fastcgi_next_upstream_timeout",
+            FastcgiInterceptErrors(..) => "fastcgi_intercept_errors",
+            FastcgiHideHeader(..) => "fastcgi_hide_header",
+            FastcgiPassHeader(..) => "fastcgi_pass_header",
+            FastcgiIndex(..) => "fastcgi_index",
+            FastcgiSplitPathInfo(..) => "fastcgi_split_path_info",
+            // uwsgi module
+            UwsgiPass(..) => "uwsgi_pass",
+            UwsgiParam { .. } => "uwsgi_param",
+            UwsgiBuffering(..) => "uwsgi_buffering",
+            UwsgiBufferSize(..) => "uwsgi_buffer_size",
+            UwsgiCache(..) => "uwsgi_cache",
+            UwsgiCacheKey(..) => "uwsgi_cache_key",
+            UwsgiCacheValid(..) => "uwsgi_cache_valid",
+            UwsgiConnectTimeout(..) => "uwsgi_connect_timeout",
+            UwsgiReadTimeout(..) => "uwsgi_read_timeout",
+            UwsgiSendTimeout(..) => "uwsgi_send_timeout",
+            UwsgiNextUpstream(..) => "uwsgi_next_upstream",
+            UwsgiNextUpstreamTries(..) => "uwsgi_next_upstream_tries",
+            UwsgiNextUpstreamTimeout(..) => "uwsgi_next_upstream_timeout",
+            UwsgiInterceptErrors(..) => "uwsgi_intercept_errors",
+            UwsgiHideHeader(..) => "uwsgi_hide_header",
+            UwsgiPassHeader(..) => "uwsgi_pass_header",
+            // scgi module
+            ScgiPass(..) => "scgi_pass",
+            ScgiParam { .. } => "scgi_param",
+            ScgiBuffering(..) => "scgi_buffering",
+            ScgiBufferSize(..) => "scgi_buffer_size",
+            ScgiCache(..) => "scgi_cache",
+            ScgiCacheKey(..) => "scgi_cache_key",
+            ScgiCacheValid(..) => "scgi_cache_valid",
+            ScgiConnectTimeout(..) => "scgi_connect_timeout",
+            ScgiReadTimeout(..) => "scgi_read_timeout",
+            ScgiSendTimeout(..) => "scgi_send_timeout",
+            ScgiNextUpstream(..) => "scgi_next_upstream",
+            ScgiNextUpstreamTries(..) => "scgi_next_upstream_tries",
+            ScgiNextUpstreamTimeout(..) => "scgi_next_upstream_timeout",
+            ScgiInterceptErrors(..) => "scgi_intercept_errors",
+            ScgiHideHeader(..) => "scgi_hide_header",
+            ScgiPassHeader(..) => "scgi_pass_header",
+            // grpc module
+            GrpcPass(..) => "grpc_pass",
+            GrpcSetHeader { .. } => "grpc_set_header",
+            GrpcReadTimeout(..) => "grpc_read_timeout",
+            GrpcSendTimeout(..) => "grpc_send_timeout",
+            GrpcConnectTimeout(..) => "grpc_connect_timeout",
+            GrpcSslCertificate(..) => "grpc_ssl_certificate",
+            GrpcSslCertificateKey(..) => "grpc_ssl_certificate_key",
+            GrpcSslCiphers(..) => "grpc_ssl_ciphers",
+            GrpcSslProtocols(..) => "grpc_ssl_protocols",
+            GrpcSslServerName(..) => "grpc_ssl_server_name",
+            GrpcSslName(..) => "grpc_ssl_name",
+            GrpcSslVerify(..) => "grpc_ssl_verify",
+            GrpcSslVerifyDepth(..) => "grpc_ssl_verify_depth",
+            GrpcSslTrustedCertificate(..) => "grpc_ssl_trusted_certificate",
+            GrpcSslSessionReuse(..) => "grpc_ssl_session_reuse",
+            GrpcNextUpstream(..) => "grpc_next_upstream",
+            GrpcNextUpstreamTries(..) => "grpc_next_upstream_tries",
+            GrpcNextUpstreamTimeout(..) => "grpc_next_upstream_timeout",
+            GrpcBufferSize(..) => "grpc_buffer_size",
+            GrpcInterceptErrors(..) => "grpc_intercept_errors",
+            GrpcHideHeader(..) => "grpc_hide_header",
+            // stream module
+            Stream(..) => "stream",
+            ProxyTimeout(..) => "proxy_timeout",
+            ProxyResponses(..) => "proxy_responses",
+            SslPreread(..) => "ssl_preread",
+            // limit_req and limit_conn modules
+            LimitReqZone(..) => "limit_req_zone",
+            LimitReq(..) => "limit_req",
+            LimitReqStatus(..) => "limit_req_status",
+            LimitReqLogLevel(..) => "limit_req_log_level",
+            LimitReqDryRun(..) => "limit_req_dry_run",
+            LimitConnZone(..) => "limit_conn_zone",
+            LimitConn { .. } => "limit_conn",
+            LimitConnStatus(..) => "limit_conn_status",
+            LimitRate(..) => "limit_rate",
+            LimitRateAfter(..) => "limit_rate_after",
+            // upstream module
+            Upstream(..) => "upstream",
+            UpstreamServer(..) => "server",
+            LeastConn => "least_conn",
+            IpHash => "ip_hash",
+            Hash { .. } => "hash",
+            Random { .. } => "random",
+            Keepalive(..) => "keepalive",
+            KeepaliveRequests(..) => "keepalive_requests",
+            Zone { .. } => "zone",
+            Unknown { ref name, .. } => name,
         }
     }
 
@@ -506,6 +1059,22 @@ impl Item {
             Daemon(_) => None,
             MasterProcess(_) => None,
             WorkerProcesses(_) => None,
+            User { .. } => None,
+            Pid(_) => None,
+            WorkerRlimitNofile(_) => None,
+            WorkerCpuAffinity(_) => None,
+            WorkerPriority(_) => None,
+            Env { .. } => None,
+            LoadModule(_) => None,
+            PcreJit(_) => None,
+            ThreadPool(_) => None,
+            TimerResolution(_) => None,
+            LockFile(_) => None,
+            Events(ref e) => Some(&e.directives[..]),
+            WorkerConnections(_) => None,
+            Use(_) => None,
+            MultiAccept(_) => None,
+            AcceptMutex(_) => None,
             Http(ref h) => Some(&h.directives[..]),
             Server(ref s) => Some(&s.directives[..]),
             Location(ref l) => Some(&l.directives[..]),
@@ -542,6 +1111,7 @@ impl Item {
             ErrorLog {..} => None,
             Rewrite(..) => None,
             Return(..) => None,
+            Break => None,
             If(ref val) => Some(&val.directives),
             TryFiles(..) => None,
             ServerName(..) => None,
@@ -558,6 +1128,24 @@ impl Item {
             ServerTokens(..) => None,
             SslCertificate(..) => None,
             SslCertificateKey(..) => None,
+            SslProtocols(..) => None,
+            SslCiphers(..) => None,
+            SslPreferServerCiphers(..) => None,
+            SslSessionCache(..) => None,
+            SslSessionTimeout(..) => None,
+            SslSessionTickets(..) => None,
+            SslDhparam(..) => None,
+            SslEcdhCurve(..) => None,
+            SslStapling(..) => None,
+            SslStaplingVerify(..) => None,
+            SslStaplingFile(..) => None,
+            SslStaplingResponder(..) => None,
+            SslTrustedCertificate(..) => None,
+            SslClientCertificate(..) => None,
+            SslVerifyClient(..) => None,
+            SslVerifyDepth(..) => None,
+            SslEarlyData(..) => None,
+            SslConfCommand { .. } => None,
             // openresty
             RewriteByLuaFile(..) => None,
             BalancerByLuaFile(..) => None,
@@ -581,6 +1169,106 @@ impl Item {
             SetRealIpFrom(..) => None,
             // index module
             Index(..) => None,
+            FastcgiPass(..) => None,
+            FastcgiParam { .. } => None,
+            FastcgiBuffering(..) => None,
+            FastcgiBufferSize(..) => None,
+            FastcgiCache(..) => None,
+            FastcgiCacheKey(..) => None,
+            FastcgiCacheValid(..) => None,
+            FastcgiConnectTimeout(..) => None,
+            FastcgiReadTimeout(..) => None,
+            FastcgiSendTimeout(..) => None,
+            FastcgiNextUpstream(..) => None,
+            FastcgiNextUpstreamTries(..) => None,
+            FastcgiNextUpstreamTimeout(..) => None,
+            FastcgiInterceptErrors(..) => None,
+            FastcgiHideHeader(..) => None,
+            FastcgiPassHeader(..) => None,
+            FastcgiIndex(..) => None,
+            FastcgiSplitPathInfo(..) => None,
+            UwsgiPass(..) => None,
+            UwsgiParam { .. } => None,
+            UwsgiBuffering(..) => None,
+            UwsgiBufferSize(..) => None,
+            UwsgiCache(..) => None,
+            UwsgiCacheKey(..) => None,
+            UwsgiCacheValid(..) => None,
+            UwsgiConnectTimeout(..) => None,
+            UwsgiReadTimeout(..) => None,
+            UwsgiSendTimeout(..) => None,
+            UwsgiNextUpstream(..) => None,
+            UwsgiNextUpstreamTries(..) => None,
+            UwsgiNextUpstreamTimeout(..) => None,
+            UwsgiInterceptErrors(..) => None,
+            UwsgiHideHeader(..) => None,
+            UwsgiPassHeader(..) => None,
+            ScgiPass(..) => None,
+            ScgiParam { .. } => None,
+            ScgiBuffering(..) => None,
+            ScgiBufferSize(..) => None,
+            ScgiCache(..) => None,
+            ScgiCacheKey(..) => None,
+            ScgiCacheValid(..) => None,
+            ScgiConnectTimeout(..) => None,
+            ScgiReadTimeout(..) => None,
+            ScgiSendTimeout(..) => None,
+            ScgiNextUpstream(..) => None,
+            ScgiNextUpstreamTries(..) => None,
+            ScgiNextUpstreamTimeout(..) => None,
+            ScgiInterceptErrors(..) => None,
+            ScgiHideHeader(..) => None,
+            ScgiPassHeader(..) => None,
+            // grpc module
+            GrpcPass(..) => None,
+            GrpcSetHeader { .. } => None,
+            GrpcReadTimeout(..) => None,
+            GrpcSendTimeout(..) => None,
+            GrpcConnectTimeout(..) => None,
+            GrpcSslCertificate(..) => None,
+            GrpcSslCertificateKey(..) => None,
+            GrpcSslCiphers(..) => None,
+            GrpcSslProtocols(..) => None,
+            GrpcSslServerName(..) => None,
+            GrpcSslName(..) => None,
+            GrpcSslVerify(..) => None,
+            GrpcSslVerifyDepth(..) => None,
+            GrpcSslTrustedCertificate(..) => None,
+            GrpcSslSessionReuse(..) => None,
+            GrpcNextUpstream(..) => None,
+            GrpcNextUpstreamTries(..) => None,
+            GrpcNextUpstreamTimeout(..) => None,
+            GrpcBufferSize(..) => None,
+            GrpcInterceptErrors(..) => None,
+            GrpcHideHeader(..) => None,
+            // stream module
+            Stream(ref s) => Some(&s.directives[..]),
+            ProxyTimeout(_) => None,
+            ProxyResponses(_) => None,
+            SslPreread(_) => None,
+            // limit_req and limit_conn modules
+            LimitReqZone(..) => None,
+            LimitReq(..) => None,
+            LimitReqStatus(..) => None,
+            LimitReqLogLevel(..) => None,
+            LimitReqDryRun(..) => None,
+            LimitConnZone(..) => None,
+            LimitConn { .. } => None,
+            LimitConnStatus(..) => None,
+            LimitRate(..) => None,
+            LimitRateAfter(..) => None,
+            // upstream module
+            Upstream(ref u) => Some(&u.directives[..]),
+            UpstreamServer(..) => None,
+            LeastConn => None,
+            IpHash => None,
+            Hash { .. } => None,
+            Random { .. } => None,
+            Keepalive(..) => None,
+            KeepaliveRequests(..) => None,
+            Zone { .. } => None,
+            Unknown { block: Some(ref b), .. } => Some(&b[..]),
+            Unknown { block: None, .. } => None,
         }
     }
 
@@ -590,6 +1278,22 @@ impl Item {
             Daemon(_) => None,
             MasterProcess(_) => None,
             WorkerProcesses(_) => None,
+            User { .. } => None,
+            Pid(_) => None,
+            WorkerRlimitNofile(_) => None,
+            WorkerCpuAffinity(_) => None,
+            WorkerPriority(_) => None,
+            Env { .. } => None,
+            LoadModule(_) => None,
+            PcreJit(_) => None,
+            ThreadPool(_) => None,
+            TimerResolution(_) => None,
+            LockFile(_) => None,
+            Events(ref mut e) => Some(&mut e.directives),
+            WorkerConnections(_) => None,
+            Use(_) => None,
+            MultiAccept(_) => None,
+            AcceptMutex(_) => None,
             Http(ref mut h) => Some(&mut h.directives),
             Server(ref mut s) => Some(&mut s.directives),
             Location(ref mut l) => Some(&mut l.directives),
@@ -626,6 +1330,7 @@ impl Item {
             ErrorLog {..} => None,
             Rewrite(..) => None,
             Return(..) => None,
+            Break => None,
             If(ref mut val) => Some(&mut val.directives),
             TryFiles(..) => None,
             ServerName(..) => None,
@@ -642,6 +1347,24 @@ impl Item {
             ServerTokens(..) => None,
             SslCertificate(..) => None,
             SslCertificateKey(..) => None,
+            SslProtocols(..) => None,
+            SslCiphers(..) => None,
+            SslPreferServerCiphers(..) => None,
+            SslSessionCache(..) => None,
+            SslSessionTimeout(..) => None,
+            SslSessionTickets(..) => None,
+            SslDhparam(..) => None,
+            SslEcdhCurve(..) => None,
+            SslStapling(..) => None,
+            SslStaplingVerify(..) => None,
+            SslStaplingFile(..) => None,
+            SslStaplingResponder(..) => None,
+            SslTrustedCertificate(..) => None,
+            SslClientCertificate(..) => None,
+            SslVerifyClient(..) => None,
+            SslVerifyDepth(..) => None,
+            SslEarlyData(..) => None,
+            SslConfCommand { .. } => None,
             // openresty
             RewriteByLuaFile(..) => None,
             BalancerByLuaFile(..) => None,
@@ -665,6 +1388,106 @@ impl Item {
             SetRealIpFrom(..) => None,
             // index module
             Index(..) => None,
+            FastcgiPass(..) => None,
+            FastcgiParam { .. } => None,
+            FastcgiBuffering(..) => None,
+            FastcgiBufferSize(..) => None,
+            FastcgiCache(..) => None,
+            FastcgiCacheKey(..) => None,
+            FastcgiCacheValid(..) => None,
+            FastcgiConnectTimeout(..) => None,
+            FastcgiReadTimeout(..) => None,
+            FastcgiSendTimeout(..) => None,
+            FastcgiNextUpstream(..) => None,
+            FastcgiNextUpstreamTries(..) => None,
+            FastcgiNextUpstreamTimeout(..) => None,
+            FastcgiInterceptErrors(..) => None,
+            FastcgiHideHeader(..) => None,
+            FastcgiPassHeader(..) => None,
+            FastcgiIndex(..) => None,
+            FastcgiSplitPathInfo(..) => None,
+            UwsgiPass(..) => None,
+            UwsgiParam { .. } => None,
+            UwsgiBuffering(..) => None,
+            UwsgiBufferSize(..) => None,
+            UwsgiCache(..) => None,
+            UwsgiCacheKey(..) => None,
+            UwsgiCacheValid(..) => None,
+            UwsgiConnectTimeout(..) => None,
+            UwsgiReadTimeout(..) => None,
+            UwsgiSendTimeout(..) => None,
+            UwsgiNextUpstream(..) => None,
+            UwsgiNextUpstreamTries(..) => None,
+            UwsgiNextUpstreamTimeout(..) => None,
+            UwsgiInterceptErrors(..) => None,
+            UwsgiHideHeader(..) => None,
+            UwsgiPassHeader(..) => None,
+            ScgiPass(..) => None,
+            ScgiParam { .. } => None,
+            ScgiBuffering(..) => None,
+            ScgiBufferSize(..) => None,
+            ScgiCache(..) => None,
+            ScgiCacheKey(..) => None,
+            ScgiCacheValid(..) => None,
+            ScgiConnectTimeout(..) => None,
+            ScgiReadTimeout(..) => None,
+            ScgiSendTimeout(..) => None,
+            ScgiNextUpstream(..) => None,
+            ScgiNextUpstreamTries(..) => None,
+            ScgiNextUpstreamTimeout(..) => None,
+            ScgiInterceptErrors(..) => None,
+            ScgiHideHeader(..) => None,
+            ScgiPassHeader(..) => None,
+            // grpc module
+            GrpcPass(..) => None,
+            GrpcSetHeader { .. } => None,
+            GrpcReadTimeout(..) => None,
+            GrpcSendTimeout(..) => None,
+            GrpcConnectTimeout(..) => None,
+            GrpcSslCertificate(..) => None,
+            GrpcSslCertificateKey(..) => None,
+            GrpcSslCiphers(..) => None,
+            GrpcSslProtocols(..) => None,
+            GrpcSslServerName(..) => None,
+            GrpcSslName(..) => None,
+            GrpcSslVerify(..) => None,
+            GrpcSslVerifyDepth(..) => None,
+            GrpcSslTrustedCertificate(..) => None,
+            GrpcSslSessionReuse(..) => None,
+            GrpcNextUpstream(..) => None,
+            GrpcNextUpstreamTries(..) => None,
+            GrpcNextUpstreamTimeout(..) => None,
+            GrpcBufferSize(..) => None,
+            GrpcInterceptErrors(..) => None,
+            GrpcHideHeader(..) => None,
+            // stream module
+            Stream(ref mut s) => Some(&mut s.directives),
+            ProxyTimeout(_) => None,
+            ProxyResponses(_) => None,
+            SslPreread(_) => None,
+            // limit_req and limit_conn modules
+            LimitReqZone(..) => None,
+            LimitReq(..) => None,
+            LimitReqStatus(..) => None,
+            LimitReqLogLevel(..) => None,
+            LimitReqDryRun(..) => None,
+            LimitConnZone(..) => None,
+            LimitConn { .. } => None,
+            LimitConnStatus(..) => None,
+            LimitRate(..) => None,
+            LimitRateAfter(..) => None,
+            // upstream module
+            Upstream(ref mut u) => Some(&mut u.directives),
+            UpstreamServer(..) => None,
+            LeastConn => None,
+            IpHash => None,
+            Hash { .. } => None,
+            Random { .. } => None,
+            Keepalive(..) => None,
+            KeepaliveRequests(..) => None,
+            Zone { .. } => None,
+            Unknown { block: Some(ref mut b), .. } => Some(b),
+            Unknown { block: None, .. } => None,
         }
     }
 
@@ -684,6 +1507,22 @@ impl Item {
             Daemon(_) => {},
             MasterProcess(_) => {},
             WorkerProcesses(_) => {},
+            User { .. } => {},
+            Pid(ref mut v) => f(v),
+            WorkerRlimitNofile(_) => {},
+            WorkerCpuAffinity(_) => {},
+            WorkerPriority(_) => {},
+            Env { .. } => {},
+            LoadModule(ref mut v) => f(v),
+            PcreJit(_) => {},
+            ThreadPool(_) => {},
+            TimerResolution(ref mut v) => v.visit_value(&mut f),
+            LockFile(ref mut v) => f(v),
+            Events(_) => {},
+            WorkerConnections(_) => {},
+            Use(_) => {},
+            MultiAccept(_) => {},
+            AcceptMutex(_) => {},
             Http(_) => {},
             Server(_) => {},
             Location(_) => {},
@@ -695,8 +1534,8 @@ impl Item {
                 f(value);
             }
             ProxyMethod(ref mut v) => f(v),
-            ProxyReadTimeout(ref mut v) => f(v),
-            ProxyConnectTimeout(ref mut v) => f(v),
+            ProxyReadTimeout(ref mut v) => v.visit_value(&mut f),
+            ProxyConnectTimeout(ref mut v) => v.visit_value(&mut f),
             ProxyHideHeader(ref mut v) => f(v),
             ProxyPassHeader(ref mut v) => f(v),
             ProxyCache(ref mut v) => f(v),
@@ -711,7 +1550,7 @@ impl Item {
             ProxyInterceptErrors(..) => {},
             ProxyBuffering(..) => {},
             ProxyNextUpstreamTries(ref mut v) => f(v),
-            ProxyNextUpstreamTimeout(ref mut v) => f(v),
+            ProxyNextUpstreamTimeout(ref mut v) => v.visit_value(&mut f),
             ProxyNextUpstream(_) => {},
             Gzip(_) => {},
             GzipStatic(_) => {},
@@ -762,18 +1601,40 @@ impl Item {
             Include(ref mut v) => f(v),
             EmptyGif => {}
             Internal => {}
+            Break => {}
             Etag(..) => {}
             RecursiveErrorPages(..) => {}
             ChunkedTransferEncoding(..) => {}
             KeepaliveTimeout(ref mut a, ref mut b) => {
-                f(a);
+                a.visit_value(&mut f);
                 if let Some(b) = b {
-                    f(b);
+                    b.visit_value(&mut f);
                 }
             }
             ServerTokens(ref mut v) => f(v),
             SslCertificate(ref mut v) => f(v),
             SslCertificateKey(ref mut v) => f(v),
+            SslProtocols(_) => {},
+            SslCiphers(ref mut v) => f(v),
+            SslPreferServerCiphers(_) => {},
+            SslSessionCache(_) => {},
+            SslSessionTimeout(ref mut v) => v.visit_value(&mut f),
+            SslSessionTickets(_) => {},
+            SslDhparam(ref mut v) => f(v),
+            SslEcdhCurve(ref mut v) => f(v),
+            SslStapling(_) => {},
+            SslStaplingVerify(_) => {},
+            SslStaplingFile(ref mut v) => f(v),
+            SslStaplingResponder(ref mut v) => f(v),
+            SslTrustedCertificate(ref mut v) => f(v),
+            SslClientCertificate(ref mut v) => f(v),
+            SslVerifyClient(_) => {},
+            SslVerifyDepth(_) => {},
+            SslEarlyData(_) => {},
+            SslConfCommand { ref mut name, ref mut value } => {
+                f(name);
+                f(value);
+            }
             ServerName(_) => {},
             Set { ref mut value, .. } => f(value),
             Map(::ast::Map {
@@ -790,7 +1651,7 @@ impl Item {
                     f(v);
                 }
             }
-            ClientMaxBodySize(ref mut v) => f(v),
+            ClientMaxBodySize(ref mut v) => v.visit_value(&mut f),
             // openresty
             RewriteByLuaFile(ref mut v) => f(v),
             BalancerByLuaFile(ref mut v) => f(v),
@@ -822,6 +1683,130 @@ impl Item {
                     f(v);
                 }
             }
+            // fastcgi module
+            FastcgiPass(ref mut v) => f(v),
+            FastcgiParam { ref mut name, ref mut value, .. } => {
+                f(name);
+                f(value);
+            }
+            FastcgiBuffering(_) => {},
+            FastcgiBufferSize(ref mut v) => v.visit_value(&mut f),
+            FastcgiCache(ref mut v) => f(v),
+            FastcgiCacheKey(ref mut v) => f(v),
+            FastcgiCacheValid(self::ProxyCacheValid::Normal(ref mut v)) => f(v),
+            FastcgiCacheValid(self::ProxyCacheValid::Specific(_, ref mut v)) => f(v),
+            FastcgiCacheValid(self::ProxyCacheValid::Any(ref mut v)) => f(v),
+            FastcgiConnectTimeout(ref mut v) => v.visit_value(&mut f),
+            FastcgiReadTimeout(ref mut v) => v.visit_value(&mut f),
+            FastcgiSendTimeout(ref mut v) => v.visit_value(&mut f),
+            FastcgiNextUpstream(_) => {},
+            FastcgiNextUpstreamTries(ref mut v) => f(v),
+            FastcgiNextUpstreamTimeout(ref mut v) => v.visit_value(&mut f),
+            FastcgiInterceptErrors(_) => {},
+            FastcgiHideHeader(ref mut v) => f(v),
+            FastcgiPassHeader(ref mut v) => f(v),
+            FastcgiIndex(ref mut v) => f(v),
+            FastcgiSplitPathInfo(_) => {},
+            // uwsgi module
+            UwsgiPass(ref mut v) => f(v),
+            UwsgiParam { ref mut name, ref mut value, .. } => {
+                f(name);
+                f(value);
+            }
+            UwsgiBuffering(_) => {},
+            UwsgiBufferSize(ref mut v) => v.visit_value(&mut f),
+            UwsgiCache(ref mut v) => f(v),
+            UwsgiCacheKey(ref mut v) => f(v),
+            UwsgiCacheValid(self::ProxyCacheValid::Normal(ref mut v)) => f(v),
+            UwsgiCacheValid(self::ProxyCacheValid::Specific(_, ref mut v)) => f(v),
+            UwsgiCacheValid(self::ProxyCacheValid::Any(ref mut v)) => f(v),
+            UwsgiConnectTimeout(ref mut v) => v.visit_value(&mut f),
+            UwsgiReadTimeout(ref mut v) => v.visit_value(&mut f),
+            UwsgiSendTimeout(ref mut v) => v.visit_value(&mut f),
+            UwsgiNextUpstream(_) => {},
+            UwsgiNextUpstreamTries(ref mut v) => f(v),
+            UwsgiNextUpstreamTimeout(ref mut v) => v.visit_value(&mut f),
+            UwsgiInterceptErrors(_) => {},
+            UwsgiHideHeader(ref mut v) => f(v),
+            UwsgiPassHeader(ref mut v) => f(v),
+            // scgi module
+            ScgiPass(ref mut v) => f(v),
+            ScgiParam { ref mut name, ref mut value, .. } => {
+                f(name);
+                f(value);
+            }
+            ScgiBuffering(_) => {},
+            ScgiBufferSize(ref mut v) => v.visit_value(&mut f),
+            ScgiCache(ref mut v) => f(v),
+            ScgiCacheKey(ref mut v) => f(v),
+            ScgiCacheValid(self::ProxyCacheValid::Normal(ref mut v)) => f(v),
+            ScgiCacheValid(self::ProxyCacheValid::Specific(_, ref mut v)) => f(v),
+            ScgiCacheValid(self::ProxyCacheValid::Any(ref mut v)) => f(v),
+            ScgiConnectTimeout(ref mut v) => v.visit_value(&mut f),
+            ScgiReadTimeout(ref mut v) => v.visit_value(&mut f),
+            ScgiSendTimeout(ref mut v) => v.visit_value(&mut f),
+            ScgiNextUpstream(_) => {},
+            ScgiNextUpstreamTries(ref mut v) => f(v),
+            ScgiNextUpstreamTimeout(ref mut v) => v.visit_value(&mut f),
+            ScgiInterceptErrors(_) => {},
+            ScgiHideHeader(ref mut v) => f(v),
+            ScgiPassHeader(ref mut v) => f(v),
+            // grpc module
+            GrpcPass(ref mut p) => f(&mut p.address),
+            GrpcSetHeader { ref mut field, ref mut value } => {
+                f(field);
+                f(value);
+            }
+            GrpcReadTimeout(ref mut v) => v.visit_value(&mut f),
+            GrpcSendTimeout(ref mut v) => v.visit_value(&mut f),
+            GrpcConnectTimeout(ref mut v) => v.visit_value(&mut f),
+            GrpcSslCertificate(ref mut v) => f(v),
+            GrpcSslCertificateKey(ref mut v) => f(v),
+            GrpcSslCiphers(ref mut v) => f(v),
+            GrpcSslProtocols(_) => {},
+            GrpcSslServerName(_) => {},
+            GrpcSslName(ref mut v) => f(v),
+            GrpcSslVerify(_) => {},
+            GrpcSslVerifyDepth(_) => {},
+            GrpcSslTrustedCertificate(ref mut v) => f(v),
+            GrpcSslSessionReuse(_) => {},
+            GrpcNextUpstream(_) => {},
+            GrpcNextUpstreamTries(ref mut v) => f(v),
+            GrpcNextUpstreamTimeout(ref mut v) => v.visit_value(&mut f),
+            GrpcBufferSize(ref mut v) => v.visit_value(&mut f),
+            GrpcInterceptErrors(_) => {},
+            GrpcHideHeader(ref mut v) => f(v),
+            // stream module
+            Stream(_) => {},
+            ProxyTimeout(ref mut v) => v.visit_value(&mut f),
+            ProxyResponses(ref mut v) => f(v),
+            SslPreread(_) => {},
+            // limit_req and limit_conn modules
+            LimitReqZone(ref mut zone) => f(&mut zone.key),
+            LimitReq(..) => {},
+            LimitReqStatus(..) => {},
+            LimitReqLogLevel(..) => {},
+            LimitReqDryRun(..) => {},
+            LimitConnZone(ref mut zone) => f(&mut zone.key),
+            LimitConn { .. } => {},
+            LimitConnStatus(..) => {},
+            LimitRate(ref mut v) => v.visit_value(&mut f),
+            LimitRateAfter(ref mut v) => v.visit_value(&mut f),
+            // upstream module
+            Upstream(_) => {},
+            UpstreamServer(_) => {},
+            LeastConn => {},
+            IpHash => {},
+            Hash { ref mut key, .. } => f(key),
+            Random { .. } => {},
+            Keepalive(..) => {},
+            KeepaliveRequests(ref mut v) => f(v),
+            Zone { .. } => {},
+            Unknown { ref mut args, .. } => {
+                for v in args {
+                    f(v);
+                }
+            }
         }
     }
 }
@@ -0,0 +1,424 @@
+//! Contexts (blocks) where directives are allowed
+//!
+//! [`parse_main`](../fn.parse_main.html) checks every directive against
+//! the table in [`allowed_contexts`](fn.allowed_contexts.html), the same
+//! way nginx does on startup.
+use std::fmt;
+
+use ast::{self, Directive, Item};
+use error::{ParseError, ErrorKind};
+use position::advance;
+
+
+/// Configuration context (a kind of block) nested directives belong to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Context {
+    /// Top level of the config file
+    Main,
+    /// The `events` block
+    Events,
+    /// The `http` block
+    Http,
+    /// The `server` block inside `http`
+    Server,
+    /// The `location` block
+    Location,
+    /// The `if` block inside `server`
+    IfInServer,
+    /// The `if` block inside `location`
+    IfInLocation,
+    /// The `limit_except` block
+    LimitExcept,
+    /// The `upstream` block inside `http`
+    Upstream,
+    /// The `stream` block
+    Stream,
+    /// The `server` block inside `stream`
+    StreamServer,
+    /// The `upstream` block inside `stream`
+    StreamUpstream,
+    /// The `mail` block
+    Mail,
+}
+
+use self::Context::*;
+
+const MAIN: &[Context] = &[Main];
+const EVENTS: &[Context] = &[Events];
+const HTTP: &[Context] = &[Http];
+const SERVER: &[Context] = &[Server];
+const LOCATION: &[Context] = &[Location];
+const HTTP_ALL: &[Context] = &[Http, Server, Location];
+const HTTP_IF: &[Context] = &[Http, Server, Location, IfInLocation];
+const HTTP_SERVER: &[Context] = &[Http, Server];
+const HTTP_STREAM: &[Context] = &[Http, Stream];
+const PASS: &[Context] = &[Location, IfInLocation];
+const REWRITE: &[Context] = &[Server, Location, IfInServer, IfInLocation];
+const STREAM_ALL: &[Context] = &[Stream, StreamServer];
+const PROXY_TIMEOUTS: &[Context] = &[Http, Server, Location,
+                                     Stream, StreamServer];
+const SSL: &[Context] = &[Http, Server, Stream, StreamServer];
+const ACCESS: &[Context] = &[Http, Server, Location, LimitExcept,
+                             Stream, StreamServer];
+const ACCESS_LOG: &[Context] = &[Http, Server, Location, IfInLocation,
+                                 LimitExcept, Stream, StreamServer];
+const ERROR_LOG: &[Context] = &[Main, Http, Mail, Stream, Server,
+                                StreamServer, Location];
+const UPSTREAM: &[Context] = &[Upstream];
+const ANY_UPSTREAM: &[Context] = &[Upstream, StreamUpstream];
+// directive of both the core http and the upstream modules
+const KEEPALIVE_REQUESTS: &[Context] = &[Http, Server, Location, Upstream];
+
+impl Context {
+    /// Context of the block of the directive if it's placed in this context
+    ///
+    /// Returns `None` if directive has no block or if it's unknown (so its
+    /// contents can't be checked).
+    pub fn nested(self, item: &Item) -> Option<Context> {
+        let stream = self == Stream;
+        match *item {
+            Item::Events(..) => Some(Events),
+            Item::Http(..) => Some(Http),
+            Item::Stream(..) => Some(Stream),
+            Item::Server(..) if stream => Some(StreamServer),
+            Item::Server(..) => Some(Server),
+            Item::Upstream(..) if stream => Some(StreamUpstream),
+            Item::Upstream(..) => Some(Upstream),
+            Item::Location(..) => Some(Location),
+            Item::If(..) if self == Server => Some(IfInServer),
+            Item::If(..) => Some(IfInLocation),
+            Item::LimitExcept(..) => Some(LimitExcept),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Main => "main",
+            Events => "events",
+            Http => "http",
+            Server => "server",
+            Location => "location",
+            IfInServer => "if in server",
+            IfInLocation => "if in location",
+            LimitExcept => "limit_except",
+            Upstream => "upstream",
+            Stream => "stream",
+            StreamServer => "stream server",
+            StreamUpstream => "stream upstream",
+            Mail => "mail",
+        })
+    }
+}
+
+/// Contexts where the directive is allowed
+///
+/// Returns `None` for `include` and unknown directives, which are allowed
+/// anywhere.
+pub fn allowed_contexts(item: &Item) -> Option<&'static [Context]> {
+    use ast::Item::*;
+    let contexts = match *item {
+        | Daemon(..)
+        | MasterProcess(..)
+        | WorkerProcesses(..)
+        | User { .. }
+        | Pid(..)
+        | WorkerRlimitNofile(..)
+        | WorkerCpuAffinity(..)
+        | WorkerPriority(..)
+        | Env { .. }
+        | LoadModule(..)
+        | PcreJit(..)
+        | ThreadPool(..)
+        | TimerResolution(..)
+        | LockFile(..)
+        | Events(..)
+        | Http(..)
+        | Stream(..)
+        => MAIN,
+        | WorkerConnections(..)
+        | Use(..)
+        | MultiAccept(..)
+        | AcceptMutex(..)
+        => EVENTS,
+        Server(..) => &[Context::Http, Context::Stream, Context::Mail],
+        Location(..) => &[Context::Server, Context::Location],
+        Listen(..) => &[Context::Server, StreamServer, Context::Mail],
+        ProxyPass(..) => &[Context::Location, IfInLocation,
+                           Context::LimitExcept, StreamServer],
+        | ProxySetHeader { .. }
+        | ProxyMethod(..)
+        | ProxyReadTimeout(..)
+        | ProxyHideHeader(..)
+        | ProxyPassHeader(..)
+        | ProxyPassRequestBody(..)
+        | ProxyPassRequestHeaders(..)
+        | ProxyHttpVersion(..)
+        | ProxyIgnoreHeaders(..)
+        | ProxyInterceptErrors(..)
+        | ProxyBuffering(..)
+        | ProxyCache(..)
+        | ProxyCacheKey(..)
+        | ProxyCacheValid(..)
+        | ProxyNextUpstream(..)
+        => HTTP_ALL,
+        | ProxyConnectTimeout(..)
+        | ProxyNextUpstreamTries(..)
+        | ProxyNextUpstreamTimeout(..)
+        => PROXY_TIMEOUTS,
+        Gzip(..) => HTTP_IF,
+        GzipStatic(..) => HTTP_ALL,
+        GzipProxied(..) => HTTP_ALL,
+        AddHeader(..) => HTTP_IF,
+        Expires(..) => HTTP_IF,
+        Root(..) => HTTP_IF,
+        Alias(..) => LOCATION,
+        ErrorPage(..) => HTTP_IF,
+        DefaultType(..) => HTTP_ALL,
+        ErrorLog { .. } => ERROR_LOG,
+        Rewrite(..) => REWRITE,
+        Break => REWRITE,
+        Return(..) => &[Context::Server, Context::Location, IfInServer,
+                        IfInLocation, StreamServer],
+        If(..) => &[Context::Server, Context::Location],
+        TryFiles(..) => &[Context::Server, Context::Location],
+        ServerName(..) => &[Context::Server, Context::Mail],
+        Set { .. } => &[Context::Server, Context::Location, IfInServer,
+                        IfInLocation, StreamServer],
+        Map(..) => HTTP_STREAM,
+        ClientMaxBodySize(..) => HTTP_ALL,
+        Include(..) => return None,
+        EmptyGif => LOCATION,
+        Internal => LOCATION,
+        LimitExcept(..) => LOCATION,
+        Etag(..) => HTTP_ALL,
+        RecursiveErrorPages(..) => HTTP_ALL,
+        ChunkedTransferEncoding(..) => HTTP_ALL,
+        KeepaliveTimeout(..) => &[Context::Http, Context::Server,
+                                  Context::Location, Context::Upstream],
+        ServerTokens(..) => HTTP_ALL,
+        // ssl module
+        | SslCertificate(..)
+        | SslCertificateKey(..)
+        | SslProtocols(..)
+        | SslCiphers(..)
+        | SslPreferServerCiphers(..)
+        | SslSessionCache(..)
+        | SslSessionTimeout(..)
+        | SslSessionTickets(..)
+        | SslDhparam(..)
+        | SslEcdhCurve(..)
+        | SslTrustedCertificate(..)
+        | SslClientCertificate(..)
+        | SslVerifyClient(..)
+        | SslVerifyDepth(..)
+        | SslConfCommand { .. }
+        => SSL,
+        | SslStapling(..)
+        | SslStaplingVerify(..)
+        | SslStaplingFile(..)
+        | SslStaplingResponder(..)
+        | SslEarlyData(..)
+        => HTTP_SERVER,
+        // openresty
+        | RewriteByLuaFile(..)
+        | AccessByLuaFile(..)
+        | HeaderFilterByLuaFile(..)
+        | BodyFilterByLuaFile(..)
+        | LogByLuaFile(..)
+        => HTTP_IF,
+        ContentByLuaFile(..) => PASS,
+        BalancerByLuaFile(..) => UPSTREAM,
+        LuaNeedRequestBody(..) => HTTP_ALL,
+        SslCertificateByLuaFile(..) => SERVER,
+        SslSessionFetchByLuaFile(..) => HTTP,
+        SslSessionStoreByLuaFile(..) => HTTP,
+        // access module
+        Allow(..) => ACCESS,
+        Deny(..) => ACCESS,
+        // log module
+        AccessLog(..) => ACCESS_LOG,
+        // real_ip module
+        RealIpHeader(..) => HTTP_ALL,
+        RealIpRecursive(..) => HTTP_ALL,
+        SetRealIpFrom(..) => &[Context::Http, Context::Server,
+                               Context::Location, Context::Stream,
+                               StreamServer],
+        // index module
+        Index(..) => HTTP_ALL,
+        // fastcgi, uwsgi, scgi and grpc modules
+        | FastcgiPass(..)
+        | UwsgiPass(..)
+        | ScgiPass(..)
+        | GrpcPass(..)
+        => PASS,
+        | FastcgiParam { .. }
+        | FastcgiBuffering(..)
+        | FastcgiBufferSize(..)
+        | FastcgiCache(..)
+        | FastcgiCacheKey(..)
+        | FastcgiCacheValid(..)
+        | FastcgiConnectTimeout(..)
+        | FastcgiReadTimeout(..)
+        | FastcgiSendTimeout(..)
+        | FastcgiNextUpstream(..)
+        | FastcgiNextUpstreamTries(..)
+        | FastcgiNextUpstreamTimeout(..)
+        | FastcgiInterceptErrors(..)
+        | FastcgiHideHeader(..)
+        | FastcgiPassHeader(..)
+        | FastcgiIndex(..)
+        | UwsgiParam { .. }
+        | UwsgiBuffering(..)
+        | UwsgiBufferSize(..)
+        | UwsgiCache(..)
+        | UwsgiCacheKey(..)
+        | UwsgiCacheValid(..)
+        | UwsgiConnectTimeout(..)
+        | UwsgiReadTimeout(..)
+        | UwsgiSendTimeout(..)
+        | UwsgiNextUpstream(..)
+        | UwsgiNextUpstreamTries(..)
+        | UwsgiNextUpstreamTimeout(..)
+        | UwsgiInterceptErrors(..)
+        | UwsgiHideHeader(..)
+        | UwsgiPassHeader(..)
+        | ScgiParam { .. }
+        | ScgiBuffering(..)
+        | ScgiBufferSize(..)
+        | ScgiCache(..)
+        | ScgiCacheKey(..)
+        | ScgiCacheValid(..)
+        | ScgiConnectTimeout(..)
+        | ScgiReadTimeout(..)
+        | ScgiSendTimeout(..)
+        | ScgiNextUpstream(..)
+        | ScgiNextUpstreamTries(..)
+        | ScgiNextUpstreamTimeout(..)
+        | ScgiInterceptErrors(..)
+        | ScgiHideHeader(..)
+        | ScgiPassHeader(..)
+        | GrpcSetHeader { .. }
+        | GrpcReadTimeout(..)
+        | GrpcSendTimeout(..)
+        | GrpcConnectTimeout(..)
+        | GrpcSslCertificate(..)
+        | GrpcSslCertificateKey(..)
+        | GrpcSslCiphers(..)
+        | GrpcSslProtocols(..)
+        | GrpcSslServerName(..)
+        | GrpcSslName(..)
+        | GrpcSslVerify(..)
+        | GrpcSslVerifyDepth(..)
+        | GrpcSslTrustedCertificate(..)
+        | GrpcSslSessionReuse(..)
+        | GrpcNextUpstream(..)
+        | GrpcNextUpstreamTries(..)
+        | GrpcNextUpstreamTimeout(..)
+        | GrpcBufferSize(..)
+        | GrpcInterceptErrors(..)
+        | GrpcHideHeader(..)
+        => HTTP_ALL,
+        FastcgiSplitPathInfo(..) => LOCATION,
+        // stream module
+        ProxyTimeout(..) => STREAM_ALL,
+        ProxyResponses(..) => STREAM_ALL,
+        SslPreread(..) => STREAM_ALL,
+        // limit_req and limit_conn modules
+        LimitReqZone(..) => HTTP,
+        | LimitReq(..)
+        | LimitReqStatus(..)
+        | LimitReqLogLevel(..)
+        | LimitReqDryRun(..)
+        | LimitConnStatus(..)
+        => HTTP_ALL,
+        LimitConnZone(..) => HTTP_STREAM,
+        LimitConn { .. } => PROXY_TIMEOUTS,
+        LimitRate(..) => HTTP_IF,
+        LimitRateAfter(..) => HTTP_IF,
+        // upstream module
+        Upstream(..) => HTTP_STREAM,
+        | UpstreamServer(..)
+        | LeastConn
+        | Hash { .. }
+        | Random { .. }
+        | Zone { .. }
+        => ANY_UPSTREAM,
+        | IpHash
+        | Keepalive(..)
+        => UPSTREAM,
+        KeepaliveRequests(..) => KEEPALIVE_REQUESTS,
+        Unknown { .. } => return None,
+    };
+    Some(contexts)
+}
+
+fn error(dir: &Directive, message: String) -> ParseError {
+    let name = dir.item.directive_name();
+    ParseError::message(ErrorKind::Context,
+        dir.position, advance(dir.position, name),
+        Some(name.to_string()), message)
+}
+
+fn check_listen(listen: &ast::Listen, context: Context)
+    -> Result<(), String>
+{
+    let http_only = [
+        ("default_server", listen.default_server),
+        ("http2", listen.ext == Some(ast::HttpExt::Http2)),
+        ("spdy", listen.ext == Some(ast::HttpExt::Spdy)),
+        ("setfib", listen.setfib.is_some()),
+    ];
+    if context == StreamServer {
+        if let Some(&(name, _)) = http_only.iter().find(|&&(_, x)| x) {
+            return Err(format!(
+                "listen parameter {:?} is not allowed in stream context",
+                name));
+        }
+    } else if listen.udp {
+        return Err("listen parameter \"udp\" is only allowed \
+                    in stream context".into());
+    }
+    Ok(())
+}
+
+/// Checks that directives (recursively) are allowed in the context
+pub(crate) fn validate(directives: &[Directive], context: Context)
+    -> Result<(), ParseError>
+{
+    let mut errors = Vec::new();
+    validate_all(directives, context, &mut errors);
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Same as `validate` but collects all the errors instead of the first one
+pub(crate) fn validate_all(directives: &[Directive], context: Context,
+    errors: &mut Vec<ParseError>)
+{
+    for dir in directives {
+        if let Some(allowed) = allowed_contexts(&dir.item) {
+            if !allowed.contains(&context) {
+                errors.push(error(dir, format!(
+                    "directive {:?} is not allowed in {} context",
+                    dir.item.directive_name(), context)));
+                // nested directives are likely misplaced along with it
+                continue;
+            }
+        }
+        if let Item::Listen(ref listen) = dir.item {
+            if let Err(msg) = check_listen(listen, context) {
+                errors.push(error(dir, msg));
+            }
+        }
+        if let Some(nested) = context.nested(&dir.item) {
+            if let Some(children) = dir.item.children() {
+                validate_all(children, nested, errors);
+            }
+        }
+    }
+}
@@ -6,7 +6,7 @@ use combine::error::StreamError;
 use combine::easy::Error;
 
 use ast::{self, Item};
-use grammar::{value, bool, block, Code};
+use grammar::{value, typed, bool, block, Code};
 use helpers::{semi, ident, string, prefix};
 use tokenizer::{TokenStream, Token};
 use value::Value;
@@ -16,7 +16,7 @@ fn error_page<'a>()
     -> impl Parser<Output=Item, Input=TokenStream<'a>>
 {
     use ast::ErrorPageResponse;
-    use value::Item::*;
+    use value::Part::*;
 
     fn lit<'a, 'x>(val: &'a Value) -> Result<&'a str, Error<Token<'x>, Token<'x>>> {
         if val.data.is_empty() {
@@ -80,6 +80,7 @@ fn error_page<'a>()
 enum ListenParts {
     DefaultServer,
     Ssl,
+    Udp,
     Ext(ast::HttpExt),
     ProxyProtocol,
     SetFib(i32),
@@ -101,8 +102,8 @@ fn listen<'a>()
 
     ident("listen")
     .with(string().and_then(|s| -> Result<_, Error<_, _>> {
-        let v = if s.value.starts_with("unix:") {
-            Address::Unix(PathBuf::from(&s.value[6..]))
+        let v = if let Some(path) = s.value.strip_prefix("unix:") {
+            Address::Unix(PathBuf::from(path))
         } else if s.value.starts_with("*:") {
             Address::StarPort(s.value[2..].parse()?)
         } else {
@@ -114,6 +115,7 @@ fn listen<'a>()
     .and(many::<Vec<_>, _>(choice((
         ident("default_server").map(|_| DefaultServer),
         ident("ssl").map(|_| Ssl),
+        ident("udp").map(|_| Udp),
         ident("http2").map(|_| Ext(HttpExt::Http2)),
         ident("spdy").map(|_| Ext(HttpExt::Spdy)),
         ident("proxy_protocol").map(|_| ProxyProtocol),
@@ -137,6 +139,7 @@ fn listen<'a>()
             match item {
                 DefaultServer => lst.default_server = true,
                 Ssl => lst.ssl = true,
+                Udp => lst.udp = true,
                 Ext(ext) => lst.ext = Some(ext),
                 ProxyProtocol => lst.proxy_protocol = true,
                 SetFib(v) => lst.setfib = Some(v),
@@ -167,6 +170,26 @@ fn limit_except<'a>()
     })
 }
 
+pub fn error_level<'a>()
+    -> impl Parser<Output=ast::ErrorLevel, Input=TokenStream<'a>>
+{
+    string().and_then(|t| {
+        use ast::ErrorLevel::*;
+        match t.value {
+            "debug" => Ok(Debug),
+            "info" => Ok(Info),
+            "notice" => Ok(Notice),
+            "warn" => Ok(Warn),
+            "error" => Ok(Error),
+            "crit" => Ok(Crit),
+            "alert" => Ok(Alert),
+            "emerg" => Ok(Emerg),
+            _ => Err(::combine::easy::Error::unexpected_message(
+                    "invalid log level")),
+        }
+    })
+}
+
 pub fn directives<'a>()
     -> impl Parser<Output=Item, Input=TokenStream<'a>>
 {
@@ -187,26 +210,12 @@ pub fn directives<'a>()
         ident("chunked_transfer_encoding").with(bool()).skip(semi())
             .map(Item::ChunkedTransferEncoding),
         ident("keepalive_timeout")
-            .with(value())
-            .and(optional(value()))
+            .with(typed())
+            .and(optional(typed()))
             .map(|(timeo, htimeo)| Item::KeepaliveTimeout(timeo, htimeo))
             .skip(semi()),
         ident("error_log").with(value())
-            .and(optional(string().and_then(|t| {
-                use ast::ErrorLevel::*;
-                match t.value {
-                    "debug" => Ok(Debug),
-                    "info" => Ok(Info),
-                    "notice" => Ok(Notice),
-                    "warn" => Ok(Warn),
-                    "error" => Ok(Error),
-                    "crit" => Ok(Crit),
-                    "alert" => Ok(Alert),
-                    "emerg" => Ok(Emerg),
-                    _ => Err(::combine::easy::Error::unexpected_message(
-                            "invalid log level")),
-                }
-            })))
+            .and(optional(error_level()))
             .skip(semi())
             .map(|(file, level)| Item::ErrorLog { file, level })
     ))
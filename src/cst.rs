@@ -0,0 +1,373 @@
+//! Lossless concrete syntax tree
+//!
+//! The tree keeps original text of every directive along with whitespace
+//! and comments around it. It's used to write back a modified config
+//! while touching only the directives that were actually changed:
+//!
+//! ```rust
+//! # use nginx_config::cst::Document;
+//! # use nginx_config::ast::Item;
+//! let text = "daemon  on;   # keep me\npcre_jit on;\n";
+//! let doc = Document::parse(text).unwrap();
+//! let mut main = doc.ast().clone();
+//! main.directives[1].item = Item::PcreJit(false);
+//! assert_eq!(doc.render(&main), "daemon  on;   # keep me\npcre_jit off;\n");
+//! ```
+use std::collections::HashMap;
+use std::ops::Range;
+
+use combine::{StreamOnce, Positioned};
+use combine::easy::{Error, Errors};
+use combine::error::StreamError;
+
+use ast::{Main, Directive};
+use display::format_at;
+use error::{ParseError, ErrorKind};
+use format::Style;
+use grammar::parse_main;
+use position::Pos;
+use tokenizer::{TokenStream, Token, Kind};
+
+
+/// A parsed config file which remembers its original text
+#[derive(Debug, Clone)]
+pub struct Document {
+    source: String,
+    ast: Main,
+    nodes: Vec<Node>,
+}
+
+/// A directive in the concrete syntax tree
+///
+/// All offsets are byte offsets in the source text of the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    position: Pos,
+    leading_start: usize,
+    start: usize,
+    tokens: Vec<Range<usize>>,
+    header_end: usize,
+    block: Option<Block>,
+    end: usize,
+    trailing_end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Block {
+    children: Vec<Node>,
+    tail_start: usize,
+    close: usize,
+}
+
+struct Builder<'a> {
+    tokens: TokenStream<'a>,
+    prev_end: usize,
+}
+
+impl<'a> Builder<'a> {
+    fn error(&self, position: Pos, error: Error<Token<'a>, Token<'a>>)
+        -> ParseError
+    {
+        ParseError::new(Errors::new(position, error), self.tokens.source())
+    }
+
+    fn list(&mut self, nested: bool)
+        -> Result<(Vec<Node>, usize), ParseError>
+    {
+        let mut nodes = Vec::new();
+        loop {
+            let position = self.tokens.position();
+            let start = self.tokens.offset();
+            let token = match self.tokens.uncons() {
+                Ok(token) => token,
+                Err(ref e) if e == &Error::end_of_input() && !nested => {
+                    return Ok((nodes, self.prev_end));
+                }
+                Err(e) => return Err(self.error(position, e)),
+            };
+            match token.kind {
+                Kind::String => {
+                    let leading_start = self.prev_end;
+                    let node = self.directive(position, leading_start, start)?;
+                    nodes.push(node);
+                }
+                Kind::BlockEnd if nested => {
+                    return Ok((nodes, self.prev_end));
+                }
+                _ => {
+                    return Err(self.error(position,
+                        Error::unexpected_message("directive expected"))
+                        .with_kind(ErrorKind::UnexpectedBlock));
+                }
+            }
+        }
+    }
+
+    fn directive(&mut self, position: Pos, leading_start: usize, start: usize)
+        -> Result<Node, ParseError>
+    {
+        let (_, name_end) = self.tokens.last_token();
+        let mut tokens = Vec::with_capacity(4);
+        tokens.push(start..name_end);
+        loop {
+            let pos = self.tokens.position();
+            let token = self.tokens.uncons()
+                .map_err(|e| self.error(pos, e))?;
+            let (tok_start, tok_end) = self.tokens.last_token();
+            match token.kind {
+                Kind::String => tokens.push(tok_start..tok_end),
+                Kind::Semicolon => {
+                    self.prev_end = self.trailing_end();
+                    return Ok(Node {
+                        position, leading_start, start, tokens,
+                        header_end: tok_end,
+                        block: None,
+                        end: tok_end,
+                        trailing_end: self.prev_end,
+                    });
+                }
+                Kind::BlockStart => {
+                    self.prev_end = tok_end;
+                    let (children, tail_start) = self.list(true)?;
+                    let (close, end) = self.tokens.last_token();
+                    self.prev_end = self.trailing_end();
+                    return Ok(Node {
+                        position, leading_start, start, tokens,
+                        header_end: tok_end,
+                        block: Some(Block { children, tail_start, close }),
+                        end,
+                        trailing_end: self.prev_end,
+                    });
+                }
+                Kind::BlockEnd => {
+                    return Err(self.error(pos,
+                        Error::unexpected_message("unexpected end of block"))
+                        .with_kind(ErrorKind::UnexpectedBlock));
+                }
+            }
+        }
+    }
+
+    /// End of the comment placed on the same line after the last token
+    fn trailing_end(&self) -> usize {
+        let (_, end) = self.tokens.last_token();
+        let gap = &self.tokens.source()[end..self.tokens.offset()];
+        let line = gap.split('\n').next().unwrap_or("");
+        if line.trim_start().starts_with('#') {
+            end + line.trim_end().len()
+        } else {
+            end
+        }
+    }
+}
+
+impl Node {
+    /// Position of the directive name
+    pub fn position(&self) -> Pos {
+        self.position
+    }
+    /// Byte range of the directive text, from its name up to the semicolon
+    /// or the closing brace
+    pub fn span(&self) -> Range<usize> {
+        self.start..self.end
+    }
+    /// Byte range of whitespace and comments preceding the directive
+    pub fn leading_trivia(&self) -> Range<usize> {
+        self.leading_start..self.start
+    }
+    /// Byte range of a comment on the same line after the directive
+    /// (including whitespace before it)
+    pub fn trailing_trivia(&self) -> Range<usize> {
+        self.end..self.trailing_end
+    }
+    /// Byte ranges of the directive name and arguments, quotes included
+    pub fn tokens(&self) -> &[Range<usize>] {
+        &self.tokens
+    }
+    /// Nested nodes if the directive has a block
+    pub fn children(&self) -> Option<&[Node]> {
+        self.block.as_ref().map(|b| &b.children[..])
+    }
+    fn full(&self) -> Range<usize> {
+        self.leading_start..self.trailing_end
+    }
+}
+
+fn without_children(dir: &Directive) -> Directive {
+    let mut dir = dir.clone();
+    if let Some(children) = dir.item.children_mut() {
+        children.clear();
+    }
+    dir
+}
+
+fn find(nodes: &[Node], pos: Pos) -> Option<&Node> {
+    for node in nodes {
+        if node.position == pos {
+            return Some(node);
+        }
+        if let Some(found) = node.children().and_then(|ch| find(ch, pos)) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+struct Renderer<'a> {
+    source: &'a str,
+    style: Style,
+    buf: String,
+}
+
+impl<'a> Renderer<'a> {
+    fn indent_of(&self, node: &Node) -> &'a str {
+        let source = self.source;
+        let line_start = source[..node.start].rfind('\n')
+            .map(|x| x + 1).unwrap_or(0);
+        let line = &source[line_start..node.start];
+        &line[..line.len() - line.trim_start().len()]
+    }
+
+    fn width(&self, indent: &str) -> u32 {
+        indent.chars()
+            .map(|c| if c == '\t' { self.style.indent_width() } else { 1 })
+            .sum()
+    }
+
+    // Formats the directive, replacing indentation of the first line
+    fn format(&mut self, dir: &Directive, indent: &str) {
+        let width = self.width(indent);
+        let text = format_at(dir, &self.style, width);
+        self.buf.push_str(indent);
+        self.buf.push_str(text.trim_start_matches(' ').trim_end_matches('\n'));
+    }
+
+    fn list(&mut self, nodes: &[Node], originals: &[Directive],
+        directives: &[Directive], indent: &str)
+    {
+        let by_position = nodes.iter().zip(originals)
+            .map(|(node, orig)| (node.position, (node, orig)))
+            .collect::<HashMap<_, _>>();
+        let indent = nodes.first().map(|n| self.indent_of(n))
+            .unwrap_or(indent);
+        for dir in directives {
+            match by_position.get(&dir.position) {
+                Some(&(node, orig)) => self.directive(node, orig, dir),
+                None => {
+                    if !self.buf.is_empty() && !self.buf.ends_with('\n') {
+                        self.buf.push('\n');
+                    }
+                    self.format(dir, indent);
+                }
+            }
+        }
+    }
+
+    fn directive(&mut self, node: &Node, orig: &Directive, dir: &Directive) {
+        let source = self.source;
+        if dir == orig {
+            self.buf.push_str(&source[node.full()]);
+            return;
+        }
+        let indent = self.indent_of(node);
+        let width = self.width(indent);
+        if dir.comments != orig.comments {
+            if !self.buf.is_empty() {
+                self.buf.push('\n');
+            }
+            self.format(dir, indent);
+            return;
+        }
+        let nested = match (node.block.as_ref(),
+                            orig.item.children(), dir.item.children())
+        {
+            (Some(block), Some(orig_children), Some(children))
+            if without_children(dir) == without_children(orig)
+            => Some((block, orig_children, children)),
+            _ => None,
+        };
+        if let Some((block, orig_children, children)) = nested {
+            let inner = format!("{}{}", indent,
+                " ".repeat(self.style.indent_width() as usize));
+            self.buf.push_str(&source[node.leading_start..node.header_end]);
+            self.list(&block.children, orig_children, children, &inner);
+            self.buf.push_str(&source[block.tail_start..node.end]);
+            self.buf.push_str(&source[node.trailing_trivia()]);
+            return;
+        }
+        let mut bare = dir.clone();
+        bare.comments.leading.clear();
+        bare.comments.trailing = None;
+        self.buf.push_str(&source[node.leading_trivia()]);
+        let text = format_at(&bare, &self.style, width);
+        self.buf.push_str(text.trim_start_matches(' ').trim_end_matches('\n'));
+        self.buf.push_str(&source[node.trailing_trivia()]);
+    }
+}
+
+impl Document {
+    /// Parse config in the main context keeping the original text
+    pub fn parse(source: &str) -> Result<Document, ParseError> {
+        let ast = parse_main(source)?;
+        let mut builder = Builder {
+            tokens: TokenStream::new(source),
+            prev_end: 0,
+        };
+        let (nodes, _) = builder.list(false)?;
+        Ok(Document { source: source.to_string(), ast, nodes })
+    }
+    /// Original text of the document
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+    /// The syntax tree of the document as it was parsed
+    pub fn ast(&self) -> &Main {
+        &self.ast
+    }
+    /// Top-level nodes of the concrete syntax tree
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+    /// Find the node of the directive which is at the specified position
+    ///
+    /// Use `Directive::position` of the parsed directives to look up
+    /// the original text.
+    pub fn node_at(&self, position: Pos) -> Option<&Node> {
+        find(&self.nodes, position)
+    }
+    /// Original text of the node
+    pub fn text(&self, node: &Node) -> &str {
+        &self.source[node.span()]
+    }
+    /// Write a modified version of the document
+    ///
+    /// Directives which are equal to the original ones (including
+    /// position) are written byte-for-byte. If only nested directives of
+    /// a block are changed the block itself is kept intact. Changed and
+    /// new directives are formatted with default style and the indentation
+    /// of their siblings.
+    pub fn render(&self, main: &Main) -> String {
+        let mut renderer = Renderer {
+            source: &self.source,
+            style: Style::default(),
+            buf: String::with_capacity(self.source.len()),
+        };
+        renderer.list(&self.nodes, &self.ast.directives,
+                      &main.directives, "");
+        let tail_start = self.nodes.last().map(|n| n.trailing_end)
+            .unwrap_or(0);
+        if main.end_comments == self.ast.end_comments {
+            renderer.buf.push_str(&self.source[tail_start..]);
+        } else {
+            if !renderer.buf.ends_with('\n') {
+                renderer.buf.push('\n');
+            }
+            for comment in &main.end_comments {
+                renderer.buf.push('#');
+                renderer.buf.push_str(comment);
+                renderer.buf.push('\n');
+            }
+        }
+        renderer.buf
+    }
+}
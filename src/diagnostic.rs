@@ -0,0 +1,267 @@
+//! Human-friendly rendering of errors
+//!
+//! A [`Diagnostic`](struct.Diagnostic.html) shows the offending line of the
+//! config with the problematic token underlined, similarly to how `rustc`
+//! does:
+//!
+//! ```rust
+//! # use nginx_config::parse_main;
+//! # use nginx_config::diagnostic::Diagnostic;
+//! let text = "http {\n    gzip maybe;\n}\n";
+//! let err = parse_main(text).unwrap_err();
+//! let output = Diagnostic::from(&err).path("nginx.conf").render(text);
+//! assert_eq!(output, "\
+//! error: Unexpected `maybe`
+//!  --> nginx.conf:2:10
+//!   |
+//! 2 |     gzip maybe;
+//!   |          ^^^^^
+//!   |
+//!   = note: Expected `on` or `off`
+//! ");
+//! ```
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use error::ParseError;
+use include::IncludeError;
+use position::Pos;
+
+
+/// Severity of the diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    /// Config can't be used
+    Error,
+    /// Config is valid but probably doesn't do what's intended
+    Warning,
+}
+
+/// A message pointing to some place in the config
+///
+/// It can be created from a [`ParseError`](../struct.ParseError.html) or
+/// manually (e.g. for custom lint checks) and rendered given the source
+/// text of the file.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    severity: Severity,
+    message: String,
+    start: Pos,
+    end: Pos,
+    path: Option<PathBuf>,
+    notes: Vec<String>,
+    included_from: Vec<(PathBuf, Pos)>,
+    suggestions: Vec<String>,
+    color: bool,
+}
+
+mod ansi {
+    pub const RED: &str = "\x1b[1;31m";
+    pub const YELLOW: &str = "\x1b[1;33m";
+    pub const BLUE: &str = "\x1b[1;34m";
+    pub const CYAN: &str = "\x1b[1;36m";
+    pub const BOLD: &str = "\x1b[1m";
+    pub const RESET: &str = "\x1b[0m";
+}
+
+impl Diagnostic {
+    /// Create a diagnostic covering the span from `start` to `end`
+    ///
+    /// End is exclusive, when it equals start a single character is
+    /// underlined.
+    pub fn new<S: Into<String>>(severity: Severity, message: S,
+                                start: Pos, end: Pos)
+        -> Diagnostic
+    {
+        Diagnostic {
+            severity,
+            message: message.into(),
+            start, end,
+            path: None,
+            notes: Vec::new(),
+            included_from: Vec::new(),
+            suggestions: Vec::new(),
+            color: false,
+        }
+    }
+    /// Create a diagnostic for the error of resolving includes
+    ///
+    /// Returns `None` for errors that don't point into the config text
+    /// (i.e. I/O errors and include cycles).
+    pub fn from_include_error(err: &IncludeError) -> Option<Diagnostic> {
+        match *err {
+            IncludeError::Parse { ref path, ref error, ref included_from } => {
+                let mut diag = Diagnostic::from(error);
+                diag.path(path);
+                for &(ref path, pos) in included_from {
+                    diag.included_from(path, pos);
+                }
+                Some(diag)
+            }
+            IncludeError::Variables { ref path, position } => {
+                let mut diag = Diagnostic::new(Severity::Error,
+                    "variables are not allowed in include path",
+                    position, position);
+                diag.path(path);
+                Some(diag)
+            }
+            IncludeError::Io { .. } | IncludeError::Cycle { .. } => None,
+        }
+    }
+    /// Set the path of the file shown in the location line
+    pub fn path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+    /// Add a note shown below the source snippet
+    pub fn note<S: Into<String>>(&mut self, note: S) -> &mut Self {
+        self.notes.push(note.into());
+        self
+    }
+    /// Add an `include` directive which led to the file, innermost first
+    pub fn included_from<P: AsRef<Path>>(&mut self, path: P, pos: Pos)
+        -> &mut Self
+    {
+        self.included_from.push((path.as_ref().to_path_buf(), pos));
+        self
+    }
+    /// Add a "did you mean" suggestion
+    pub fn suggestion<S: Into<String>>(&mut self, text: S) -> &mut Self {
+        self.suggestions.push(text.into());
+        self
+    }
+    /// Enable or disable ANSI colors (disabled by default)
+    pub fn color(&mut self, enable: bool) -> &mut Self {
+        self.color = enable;
+        self
+    }
+    /// Severity of the diagnostic
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+    /// Main message of the diagnostic
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn paint(&self, buf: &mut String, style: &str, text: &str) {
+        if self.color {
+            buf.push_str(style);
+            buf.push_str(text);
+            buf.push_str(ansi::RESET);
+        } else {
+            buf.push_str(text);
+        }
+    }
+
+    /// Render the diagnostic given the text of the file it points to
+    pub fn render(&self, source: &str) -> String {
+        let mut buf = String::new();
+        let (label, label_style) = match self.severity {
+            Severity::Error => ("error", ansi::RED),
+            Severity::Warning => ("warning", ansi::YELLOW),
+        };
+        self.paint(&mut buf, label_style, label);
+        self.paint(&mut buf, ansi::BOLD, &format!(": {}", self.message));
+        buf.push('\n');
+
+        let line_no = self.start.line.to_string();
+        let gutter = " ".repeat(line_no.len());
+        self.paint(&mut buf, ansi::BLUE, &format!("{}--> ", gutter));
+        if let Some(ref path) = self.path {
+            write!(buf, "{}:", path.display()).unwrap();
+        }
+        writeln!(buf, "{}", self.start).unwrap();
+
+        let line = source.lines().nth(self.start.line.saturating_sub(1));
+        if let Some(line) = line {
+            let line = line.trim_end_matches('\r');
+            self.paint(&mut buf, ansi::BLUE, &format!("{} |\n", gutter));
+            self.paint(&mut buf, ansi::BLUE, &format!("{} |", line_no));
+            if !line.is_empty() {
+                buf.push(' ');
+                buf.push_str(line);
+            }
+            buf.push('\n');
+            // keep tabs so that carets are aligned with the text,
+            // tokenizer counts a tab as 8 columns
+            let mut prefix = String::new();
+            let mut column = 1;
+            for c in line.chars() {
+                if column >= self.start.column {
+                    break;
+                }
+                if c == '\t' {
+                    prefix.push('\t');
+                    column += 8;
+                } else {
+                    prefix.push(' ');
+                    column += 1;
+                }
+            }
+            let line_end = line.chars()
+                .map(|c| if c == '\t' { 8 } else { 1 })
+                .sum::<usize>() + 1;
+            let end = if self.end.line == self.start.line {
+                self.end.column
+            } else {
+                line_end
+            };
+            let width = end.saturating_sub(self.start.column).max(1);
+            self.paint(&mut buf, ansi::BLUE, &format!("{} |", gutter));
+            write!(buf, " {}", prefix).unwrap();
+            self.paint(&mut buf, label_style, &"^".repeat(width));
+            buf.push('\n');
+        }
+
+        let has_notes = !self.notes.is_empty() ||
+            !self.included_from.is_empty() ||
+            !self.suggestions.is_empty();
+        if has_notes {
+            self.paint(&mut buf, ansi::BLUE, &format!("{} |\n", gutter));
+        }
+        for note in &self.notes {
+            self.paint(&mut buf, ansi::BLUE, &format!("{} = ", gutter));
+            writeln!(buf, "note: {}", note).unwrap();
+        }
+        for &(ref path, pos) in &self.included_from {
+            self.paint(&mut buf, ansi::BLUE, &format!("{} = ", gutter));
+            writeln!(buf, "note: included from {}:{}",
+                     path.display(), pos.line).unwrap();
+        }
+        if !self.suggestions.is_empty() {
+            let options = self.suggestions.iter()
+                .map(|s| format!("`{}`", s))
+                .collect::<Vec<_>>();
+            self.paint(&mut buf, ansi::BLUE, &format!("{} = ", gutter));
+            self.paint(&mut buf, ansi::CYAN, "help");
+            writeln!(buf, ": did you mean {}?", options.join(" or ")).unwrap();
+        }
+        buf
+    }
+}
+
+/// Replaces tokenizer kinds in parser messages by the actual characters
+fn token_names(line: &str) -> String {
+    line.replace("`BlockStart`", "`{`")
+        .replace("`BlockEnd`", "`}`")
+        .replace("`Semicolon`", "`;`")
+}
+
+impl<'a> From<&'a ParseError> for Diagnostic {
+    fn from(err: &'a ParseError) -> Diagnostic {
+        let (start, end) = err.span();
+        let mut lines = err.message_lines().into_iter()
+            .map(|line| token_names(&line));
+        let message = lines.next()
+            .unwrap_or_else(|| String::from("invalid config"));
+        let mut diag = Diagnostic::new(Severity::Error, message, start, end);
+        for line in lines {
+            diag.note(line);
+        }
+        for item in err.suggestions() {
+            diag.suggestion(item.clone());
+        }
+        diag
+    }
+}
@@ -9,12 +9,36 @@ impl Displayable for ast::Main {
         for item in &self.directives {
             item.display(f);
         }
+        for comment in &self.end_comments {
+            f.comment(comment);
+        }
     }
 }
 
 impl Displayable for ast::Directive {
     fn display(&self, f: &mut Formatter) {
-        self.item.display(f)
+        f.leading_comments(&self.comments.leading);
+        f.push_block_end_comments(&self.comments.block_end);
+        self.item.display(f);
+        f.pop_block_end_comments();
+        if let Some(ref comment) = self.comments.trailing {
+            f.trailing_comment(comment);
+        }
+    }
+}
+
+// comments of map entries, missing ones are fine for constructed nodes
+fn map_entry_start(f: &mut Formatter, comments: Option<&ast::Comments>) {
+    if let Some(c) = comments {
+        f.leading_comments(&c.leading);
+    }
+    f.indent();
+}
+
+fn map_entry_end(f: &mut Formatter, comments: Option<&ast::Comments>) {
+    f.end();
+    if let Some(comment) = comments.and_then(|c| c.trailing.as_ref()) {
+        f.trailing_comment(comment);
     }
 }
 
@@ -31,7 +55,21 @@ fn simple_block<D: fmt::Display>(f: &mut Formatter, name: D,
     f.end_block();
 }
 
-fn one_arg_dir(name: &str, val: &value::Value, f: &mut Formatter) {
+fn error_level(level: ast::ErrorLevel) -> &'static str {
+    use ast::ErrorLevel::*;
+    match level {
+        Debug => "debug",
+        Info => "info",
+        Notice => "notice",
+        Warn => "warn",
+        Error => "error",
+        Crit => "crit",
+        Alert => "alert",
+        Emerg => "emerg",
+    }
+}
+
+fn one_arg_dir<T: Displayable>(name: &str, val: &T, f: &mut Formatter) {
     f.indent();
     f.write(name);
     f.write(" ");
@@ -54,6 +92,26 @@ impl Displayable for ast::Item {
             | RecursiveErrorPages(opt)
             | ChunkedTransferEncoding(opt)
             | RealIpRecursive(opt)
+            | SslPreread(opt)
+            | LimitReqDryRun(opt)
+            | GrpcSslServerName(opt)
+            | GrpcSslVerify(opt)
+            | GrpcSslSessionReuse(opt)
+            | GrpcInterceptErrors(opt)
+            | FastcgiBuffering(opt)
+            | FastcgiInterceptErrors(opt)
+            | UwsgiBuffering(opt)
+            | UwsgiInterceptErrors(opt)
+            | ScgiBuffering(opt)
+            | ScgiInterceptErrors(opt)
+            | SslPreferServerCiphers(opt)
+            | SslSessionTickets(opt)
+            | SslStapling(opt)
+            | SslStaplingVerify(opt)
+            | SslEarlyData(opt)
+            | PcreJit(opt)
+            | MultiAccept(opt)
+            | AcceptMutex(opt)
             => {
                 f.indent();
                 f.write(self.directive_name());
@@ -72,6 +130,272 @@ impl Displayable for ast::Item {
                 f.fmt(&n);
                 f.end();
             }
+            User { ref user, ref group } => {
+                f.indent();
+                f.write("user ");
+                write_literal(f, user);
+                if let Some(ref group) = *group {
+                    f.write(" ");
+                    write_literal(f, group);
+                }
+                f.end();
+            }
+            | WorkerRlimitNofile(num)
+            | WorkerConnections(num)
+            => {
+                f.indent();
+                f.write(self.directive_name());
+                f.write(" ");
+                f.fmt(&num);
+                f.end();
+            }
+            WorkerCpuAffinity(ref aff) => {
+                f.indent();
+                f.write("worker_cpu_affinity");
+                match *aff {
+                    ast::WorkerCpuAffinity::Auto(ref mask) => {
+                        f.write(" auto");
+                        if let Some(ref mask) = *mask {
+                            f.write(" ");
+                            write_literal(f, mask);
+                        }
+                    }
+                    ast::WorkerCpuAffinity::Masks(ref masks) => {
+                        for mask in masks {
+                            f.write(" ");
+                            write_literal(f, mask);
+                        }
+                    }
+                }
+                f.end();
+            }
+            WorkerPriority(num) => {
+                f.indent();
+                f.write("worker_priority ");
+                f.fmt(&num);
+                f.end();
+            }
+            Env { ref name, ref value } => {
+                f.indent();
+                f.write("env ");
+                match *value {
+                    Some(ref value) => {
+                        write_literal(f, &format!("{}={}", name, value));
+                    }
+                    None => write_literal(f, name),
+                }
+                f.end();
+            }
+            ThreadPool(ast::ThreadPool { ref name, threads, max_queue }) => {
+                f.indent();
+                f.write("thread_pool ");
+                write_literal(f, name);
+                f.fmt(&format_args!(" threads={}", threads));
+                if let Some(max_queue) = max_queue {
+                    f.fmt(&format_args!(" max_queue={}", max_queue));
+                }
+                f.end();
+            }
+            | SslProtocols(ref protocols)
+            | GrpcSslProtocols(ref protocols)
+            => {
+                use ast::SslProtocol::*;
+                f.indent();
+                f.write(self.directive_name());
+                for proto in protocols {
+                    f.write(match *proto {
+                        SslV2 => " SSLv2",
+                        SslV3 => " SSLv3",
+                        TlsV1 => " TLSv1",
+                        TlsV1_1 => " TLSv1.1",
+                        TlsV1_2 => " TLSv1.2",
+                        TlsV1_3 => " TLSv1.3",
+                    });
+                }
+                f.end();
+            }
+            SslSessionCache(ref cache) => {
+                use ast::SslSessionCache::*;
+                use ast::SslCache::*;
+                f.indent();
+                f.write("ssl_session_cache");
+                match *cache {
+                    Off => f.write(" off"),
+                    None => f.write(" none"),
+                    Caches(ref caches) => {
+                        for cache in caches {
+                            match *cache {
+                                Builtin(Some(size)) => {
+                                    f.fmt(&format_args!(" builtin:{}", size));
+                                }
+                                Builtin(Option::None) => f.write(" builtin"),
+                                Shared { ref name, ref size } => {
+                                    f.write(" shared:");
+                                    f.write(escape(name));
+                                    f.write(":");
+                                    size.display(f);
+                                }
+                            }
+                        }
+                    }
+                }
+                f.end();
+            }
+            SslVerifyClient(verify) => {
+                use ast::SslVerifyClient::*;
+                f.indent();
+                f.write("ssl_verify_client ");
+                f.write(match verify {
+                    On => "on",
+                    Off => "off",
+                    Optional => "optional",
+                    OptionalNoCa => "optional_no_ca",
+                });
+                f.end();
+            }
+            | SslVerifyDepth(depth)
+            | GrpcSslVerifyDepth(depth)
+            => {
+                f.indent();
+                f.write(self.directive_name());
+                f.write(" ");
+                f.fmt(&depth);
+                f.end();
+            }
+            SslConfCommand { ref name, ref value } => {
+                f.indent();
+                f.write("ssl_conf_command ");
+                name.display(f);
+                f.write(" ");
+                value.display(f);
+                f.end();
+            }
+            | FastcgiParam { ref name, ref value, if_not_empty }
+            | UwsgiParam { ref name, ref value, if_not_empty }
+            | ScgiParam { ref name, ref value, if_not_empty }
+            => {
+                f.indent();
+                f.write(self.directive_name());
+                f.write(" ");
+                name.display(f);
+                f.write(" ");
+                value.display(f);
+                if if_not_empty {
+                    f.write(" if_not_empty");
+                }
+                f.end();
+            }
+            FastcgiSplitPathInfo(ref regex) => {
+                f.indent();
+                f.write("fastcgi_split_path_info ");
+                f.write(escape(regex));
+                f.end();
+            }
+            GrpcPass(ast::GrpcPass { scheme, ref address }) => {
+                let mut address = address.clone();
+                match scheme {
+                    Some(ast::GrpcScheme::Grpc) => {
+                        address.prepend_literal("grpc://");
+                    }
+                    Some(ast::GrpcScheme::Grpcs) => {
+                        address.prepend_literal("grpcs://");
+                    }
+                    None => {}
+                }
+                one_arg_dir(self.directive_name(), &address, f);
+            }
+            GrpcSetHeader { ref field, ref value } => {
+                f.indent();
+                f.write("grpc_set_header ");
+                field.display(f);
+                f.write(" ");
+                value.display(f);
+                f.end();
+            }
+            Stream(ref s) => {
+                simple_block(f, "stream", &s.directives);
+            }
+            LimitReqZone(ref zone) => {
+                f.indent();
+                f.write("limit_req_zone ");
+                zone.key.display(f);
+                f.write(" zone=");
+                f.write(escape(&zone.zone));
+                f.write(":");
+                zone.size.display(f);
+                f.write(" rate=");
+                zone.rate.display(f);
+                if zone.sync {
+                    f.write(" sync");
+                }
+                f.end();
+            }
+            LimitReq(ref req) => {
+                f.indent();
+                f.write("limit_req zone=");
+                f.write(escape(&req.zone));
+                if let Some(burst) = req.burst {
+                    f.fmt(&format_args!(" burst={}", burst));
+                }
+                match req.delay {
+                    Some(ast::LimitReqDelay::NoDelay) => f.write(" nodelay"),
+                    Some(ast::LimitReqDelay::Delay(num)) => {
+                        f.fmt(&format_args!(" delay={}", num));
+                    }
+                    None => {}
+                }
+                f.end();
+            }
+            | LimitReqStatus(code)
+            | LimitConnStatus(code)
+            => {
+                f.indent();
+                f.write(self.directive_name());
+                f.write(" ");
+                f.fmt(&code);
+                f.end();
+            }
+            LimitReqLogLevel(level) => {
+                f.indent();
+                f.write("limit_req_log_level ");
+                f.write(error_level(level));
+                f.end();
+            }
+            LimitConnZone(ref zone) => {
+                f.indent();
+                f.write("limit_conn_zone ");
+                zone.key.display(f);
+                f.write(" zone=");
+                f.write(escape(&zone.zone));
+                f.write(":");
+                zone.size.display(f);
+                f.end();
+            }
+            LimitConn { ref zone, limit } => {
+                f.indent();
+                f.write("limit_conn ");
+                write_literal(f, zone);
+                f.write(" ");
+                f.fmt(&limit);
+                f.end();
+            }
+            Events(ref e) => {
+                simple_block(f, "events", &e.directives);
+            }
+            Use(method) => {
+                use ast::ConnectionMethod::*;
+                f.indent();
+                f.write("use ");
+                f.write(match method {
+                    Select => "select",
+                    Poll => "poll",
+                    Kqueue => "kqueue",
+                    Epoll => "epoll",
+                    DevPoll => "/dev/poll",
+                    EventPort => "eventport",
+                });
+                f.end();
+            }
             Http(ref h) => {
                 simple_block(f, "http", &h.directives);
             }
@@ -166,29 +490,33 @@ impl Displayable for ast::Item {
                 f.write(" ");
                 f.start_block();
                 if m.volatile {
-                    f.indent();
+                    map_entry_start(f, Some(&m.comments.volatile));
                     f.write("volatile");
-                    f.end();
+                    map_entry_end(f, Some(&m.comments.volatile));
                 }
                 if m.hostnames {
-                    f.indent();
+                    map_entry_start(f, Some(&m.comments.hostnames));
                     f.write("hostnames");
-                    f.end();
+                    map_entry_end(f, Some(&m.comments.hostnames));
                 }
                 if let Some(ref def) = m.default {
-                    f.indent();
+                    map_entry_start(f, Some(&m.comments.default));
                     f.write("default ");
                     def.display(f);
-                    f.end();
+                    map_entry_end(f, Some(&m.comments.default));
                 }
-                for inc in &m.includes {
-                    f.indent();
+                for (idx, inc) in m.includes.iter().enumerate() {
+                    let comments = m.comments.includes.get(idx);
+                    map_entry_start(f, comments);
                     f.write("include ");
                     f.write(escape(inc));
-                    f.end();
+                    map_entry_end(f, comments);
                 }
-                for &(ref pat, ref value) in &m.patterns {
-                    f.indent();
+                for (idx, &(ref pat, ref value)) in
+                    m.patterns.iter().enumerate()
+                {
+                    let comments = m.comments.patterns.get(idx);
+                    map_entry_start(f, comments);
                     match *pat {
                         Exact(ref v) if matches!(&v[..],
                             | "volatile"
@@ -209,7 +537,7 @@ impl Displayable for ast::Item {
                     }
                     f.write(" ");
                     value.display(f);
-                    f.end();
+                    map_entry_end(f, comments);
                 }
                 f.end_block();
             }
@@ -232,7 +560,6 @@ impl Displayable for ast::Item {
             | Root(ref val)
             | Alias(ref val)
             | DefaultType(ref val)
-            | ClientMaxBodySize(ref val)
             | Include(ref val)
             | RewriteByLuaFile(ref val)
             | BalancerByLuaFile(ref val)
@@ -247,23 +574,93 @@ impl Displayable for ast::Item {
             | SslSessionStoreByLuaFile(ref val)
             | SslCertificate(ref val)
             | SslCertificateKey(ref val)
+            | SslCiphers(ref val)
+            | SslDhparam(ref val)
+            | SslEcdhCurve(ref val)
+            | SslStaplingFile(ref val)
+            | SslStaplingResponder(ref val)
+            | SslTrustedCertificate(ref val)
+            | SslClientCertificate(ref val)
             | ProxyPass(ref val)
             | ProxyCache(ref val)
             | ProxyCacheKey(ref val)
             | ProxyMethod(ref val)
-            | ProxyReadTimeout(ref val)
-            | ProxyConnectTimeout(ref val)
             | ProxyHideHeader(ref val)
             | ProxyPassHeader(ref val)
             | ProxyNextUpstreamTries(ref val)
-            | ProxyNextUpstreamTimeout(ref val)
             | ServerTokens(ref val)
             | RealIpHeader(ref val)
+            | GrpcSslCertificate(ref val)
+            | GrpcSslCertificateKey(ref val)
+            | GrpcSslCiphers(ref val)
+            | GrpcSslName(ref val)
+            | GrpcSslTrustedCertificate(ref val)
+            | GrpcNextUpstreamTries(ref val)
+            | GrpcHideHeader(ref val)
+            | FastcgiPass(ref val)
+            | FastcgiCache(ref val)
+            | FastcgiCacheKey(ref val)
+            | FastcgiNextUpstreamTries(ref val)
+            | FastcgiHideHeader(ref val)
+            | FastcgiPassHeader(ref val)
+            | FastcgiIndex(ref val)
+            | UwsgiPass(ref val)
+            | UwsgiCache(ref val)
+            | UwsgiCacheKey(ref val)
+            | UwsgiNextUpstreamTries(ref val)
+            | UwsgiHideHeader(ref val)
+            | UwsgiPassHeader(ref val)
+            | ScgiPass(ref val)
+            | ScgiCache(ref val)
+            | ScgiCacheKey(ref val)
+            | ScgiNextUpstreamTries(ref val)
+            | ScgiHideHeader(ref val)
+            | ScgiPassHeader(ref val)
+            | ProxyResponses(ref val)
+            | Pid(ref val)
+            | LoadModule(ref val)
+            | LockFile(ref val)
+            => {
+                one_arg_dir(self.directive_name(), val, f);
+            }
+            | ClientMaxBodySize(ref val)
+            | GrpcBufferSize(ref val)
+            | FastcgiBufferSize(ref val)
+            | UwsgiBufferSize(ref val)
+            | ScgiBufferSize(ref val)
+            | LimitRate(ref val)
+            | LimitRateAfter(ref val)
+            => {
+                one_arg_dir(self.directive_name(), val, f);
+            }
+            | SslSessionTimeout(ref val)
+            | ProxyReadTimeout(ref val)
+            | ProxyConnectTimeout(ref val)
+            | ProxyNextUpstreamTimeout(ref val)
+            | ProxyTimeout(ref val)
+            | GrpcReadTimeout(ref val)
+            | GrpcSendTimeout(ref val)
+            | GrpcConnectTimeout(ref val)
+            | GrpcNextUpstreamTimeout(ref val)
+            | FastcgiConnectTimeout(ref val)
+            | FastcgiReadTimeout(ref val)
+            | FastcgiSendTimeout(ref val)
+            | FastcgiNextUpstreamTimeout(ref val)
+            | UwsgiConnectTimeout(ref val)
+            | UwsgiReadTimeout(ref val)
+            | UwsgiSendTimeout(ref val)
+            | UwsgiNextUpstreamTimeout(ref val)
+            | ScgiConnectTimeout(ref val)
+            | ScgiReadTimeout(ref val)
+            | ScgiSendTimeout(ref val)
+            | ScgiNextUpstreamTimeout(ref val)
+            | TimerResolution(ref val)
             => {
                 one_arg_dir(self.directive_name(), val, f);
             }
             | EmptyGif
             | Internal
+            | Break
             => {
                 f.indent();
                 f.write(self.directive_name());
@@ -450,7 +847,11 @@ impl Displayable for ast::Item {
                 }
                 f.end();
             }
-            ProxyCacheValid(ref val) => {
+            | ProxyCacheValid(ref val)
+            | FastcgiCacheValid(ref val)
+            | UwsgiCacheValid(ref val)
+            | ScgiCacheValid(ref val)
+            => {
                 use ast::ProxyCacheValid::*;
                 f.indent();
                 f.write(self.directive_name());
@@ -485,7 +886,12 @@ impl Displayable for ast::Item {
                 }
                 f.end();
             }
-            ProxyNextUpstream(ref items) => {
+            | ProxyNextUpstream(ref items)
+            | GrpcNextUpstream(ref items)
+            | FastcgiNextUpstream(ref items)
+            | UwsgiNextUpstream(ref items)
+            | ScgiNextUpstream(ref items)
+            => {
                 use ast::ProxyNextUpstreamFlag::*;
                 f.indent();
                 f.write(self.directive_name());
@@ -523,7 +929,7 @@ impl Displayable for ast::Item {
                 }
                 if let Some(ref buf) = lg.buffer {
                     f.write(" buffer=");
-                    f.fmt(&escape(buf));
+                    buf.display(f);
                 }
                 if let Some(ref gzip) = lg.gzip {
                     if let Some(level) = gzip {
@@ -535,7 +941,7 @@ impl Displayable for ast::Item {
                 }
                 if let Some(ref flush) = lg.flush {
                     f.write(" flush=");
-                    f.fmt(&escape(flush));
+                    flush.display(f);
                 }
                 if let Some(ref condition) = lg.condition {
                     f.write(" if=");
@@ -565,18 +971,8 @@ impl Displayable for ast::Item {
                 f.write(" ");
                 file.display(f);
                 if let Some(level) = level {
-                    use ast::ErrorLevel::*;
                     f.write(" ");
-                    f.write(match level {
-                        Debug => "debug",
-                        Info => "info",
-                        Notice => "notice",
-                        Warn => "warn",
-                        Error => "error",
-                        Crit => "crit",
-                        Alert => "alert",
-                        Emerg => "emerg",
-                    });
+                    f.write(error_level(level));
                 }
                 f.end();
             }
@@ -589,6 +985,88 @@ impl Displayable for ast::Item {
                 }
                 f.end();
             }
+            Upstream(ast::Upstream { ref name, ref directives, .. }) => {
+                simple_block(f,
+                    format_args!("upstream {}", escape(name)),
+                    directives);
+            }
+            UpstreamServer(ref srv) => {
+                f.indent();
+                srv.display(f);
+            }
+            | LeastConn
+            | IpHash
+            => {
+                f.indent();
+                f.write(self.directive_name());
+                f.end();
+            }
+            Hash { ref key, consistent } => {
+                f.indent();
+                f.write("hash ");
+                key.display(f);
+                if consistent {
+                    f.write(" consistent");
+                }
+                f.end();
+            }
+            Random { two, method } => {
+                use ast::RandomMethod::*;
+                f.indent();
+                f.write("random");
+                if two {
+                    f.write(" two");
+                }
+                match method {
+                    Some(LeastConn) => f.write(" least_conn"),
+                    Some(LeastTimeHeader) => f.write(" least_time=header"),
+                    Some(LeastTimeLastByte)
+                    => f.write(" least_time=last_byte"),
+                    None => {}
+                }
+                f.end();
+            }
+            Keepalive(num) => {
+                f.indent();
+                f.write("keepalive ");
+                f.fmt(&num);
+                f.end();
+            }
+            KeepaliveRequests(ref val) => {
+                one_arg_dir(self.directive_name(), val, f);
+            }
+            Zone { ref name, ref size } => {
+                f.indent();
+                f.write("zone ");
+                f.write(escape(name));
+                if let Some(ref size) = *size {
+                    f.write(" ");
+                    size.display(f);
+                }
+                f.end();
+            }
+            Unknown { ref name, ref args, ref block } => {
+                if block.is_some() {
+                    f.margin();
+                }
+                f.indent();
+                f.write(name);
+                for arg in args {
+                    f.write(" ");
+                    arg.display(f);
+                }
+                match *block {
+                    Some(ref directives) => {
+                        f.write(" ");
+                        f.start_block();
+                        for dir in directives {
+                            dir.display(f);
+                        }
+                        f.end_block();
+                    }
+                    None => f.end(),
+                }
+            }
         }
     }
 }
@@ -599,6 +1077,7 @@ impl Displayable for ast::Listen {
         self.address.display(f);
         if self.default_server { f.write(" default_server") }
         if self.ssl { f.write(" ssl") }
+        if self.udp { f.write(" udp") }
         match self.ext {
             Some(ast::HttpExt::Http2) => f.write(" http2"),
             Some(ast::HttpExt::Spdy) => f.write(" spdy"),
@@ -631,6 +1110,114 @@ impl Displayable for ast::Listen {
     }
 }
 
+impl Displayable for ast::UpstreamServer {
+    fn display(&self, f: &mut Formatter) {
+        f.write("server ");
+        self.address.display(f);
+        if let Some(weight) = self.weight {
+            f.fmt(&format_args!(" weight={}", weight));
+        }
+        if let Some(max_conns) = self.max_conns {
+            f.fmt(&format_args!(" max_conns={}", max_conns));
+        }
+        if let Some(max_fails) = self.max_fails {
+            f.fmt(&format_args!(" max_fails={}", max_fails));
+        }
+        if let Some(ref fail_timeout) = self.fail_timeout {
+            f.write(" fail_timeout=");
+            fail_timeout.display(f);
+        }
+        if self.backup { f.write(" backup") }
+        if self.down { f.write(" down") }
+        if self.resolve { f.write(" resolve") }
+        if let Some(ref service) = self.service {
+            f.fmt(&format_args!(" service={}", escape(service)));
+        }
+        for param in &self.other {
+            f.write(" ");
+            write_literal(f, param);
+        }
+        f.end();
+    }
+}
+
+impl Displayable for ast::UpstreamAddress {
+    fn display(&self, f: &mut Formatter) {
+        use ast::UpstreamAddress::*;
+        use std::net::SocketAddr;
+        match *self {
+            Ip(ip, Some(port)) => f.fmt(&SocketAddr::new(ip, port)),
+            Ip(ip, None) if ip.is_ipv6() => f.fmt(&format_args!("[{}]", ip)),
+            Ip(ip, None) => f.fmt(&ip),
+            Name(ref name, Some(port))
+            => write_literal(f, &format!("{}:{}", name, port)),
+            Name(ref name, None) => write_literal(f, name),
+            Unix(ref path)
+            => write_literal(f, &format!("unix:{}", path.display())),
+        }
+    }
+}
+
+impl Displayable for ast::Rate {
+    fn display(&self, f: &mut Formatter) {
+        f.fmt(&self.requests);
+        f.write(match self.unit {
+            ast::RateUnit::PerSecond => "r/s",
+            ast::RateUnit::PerMinute => "r/m",
+        });
+    }
+}
+
+impl Displayable for ast::Size {
+    fn display(&self, f: &mut Formatter) {
+        use units::{KILOBYTE, MEGABYTE, GIGABYTE};
+        if let Some(text) = self.text() {
+            f.write(text);
+            return;
+        }
+        match self.bytes() {
+            0 => f.write("0"),
+            b if b % GIGABYTE == 0 => f.fmt(&format_args!("{}g", b/GIGABYTE)),
+            b if b % MEGABYTE == 0 => f.fmt(&format_args!("{}m", b/MEGABYTE)),
+            b if b % KILOBYTE == 0 => f.fmt(&format_args!("{}k", b/KILOBYTE)),
+            b => f.fmt(&b),
+        }
+    }
+}
+
+impl Displayable for ast::Duration {
+    fn display(&self, f: &mut Formatter) {
+        use units::{SECOND, MINUTE, HOUR, DAY};
+        if let Some(text) = self.text() {
+            f.write(text);
+            return;
+        }
+        if self.millis() == 0 {
+            f.write("0");
+            return;
+        }
+        let mut rest = self.millis();
+        for &(unit, scale) in &[("d", DAY), ("h", HOUR), ("m", MINUTE),
+                                ("s", SECOND), ("ms", 1)]
+        {
+            if rest >= scale {
+                f.fmt(&(rest / scale));
+                f.write(unit);
+                rest %= scale;
+            }
+        }
+    }
+}
+
+impl<T: Displayable> Displayable for ast::Typed<T> {
+    fn display(&self, f: &mut Formatter) {
+        match *self {
+            ast::Typed::Parsed(ref v) => v.display(f),
+            ast::Typed::Variables(ref v) => v.display(f),
+        }
+    }
+}
+
 impl Displayable for ast::Address {
     fn display(&self, f: &mut Formatter) {
         use ast::Address::*;
@@ -651,6 +1238,14 @@ fn to_string<T: Displayable>(v: &T) -> String {
     formatter.into_string()
 }
 
+pub(crate) fn format_at<T: Displayable>(v: &T, style: &Style, indent: u32)
+    -> String
+{
+    let mut formatter = Formatter::with_indent(style, indent);
+    v.display(&mut formatter);
+    formatter.into_string()
+}
+
 macro_rules! impl_display {
     ($( $typ: ty, )+) => {
         $(
@@ -667,11 +1262,21 @@ impl_display!(
     ast::Main,
     ast::Listen,
     ast::Address,
+    ast::UpstreamServer,
+    ast::UpstreamAddress,
+    ast::Rate,
+    ast::Size,
+    ast::Duration,
     ast::Directive,
     ast::Item,
     value::Value,
 );
 
+/// Writes an argument which has no variables, quoting it if needed
+fn write_literal(f: &mut Formatter, text: &str) {
+    ast::Value::literal(text).display(f);
+}
+
 fn escape(s: &str) -> &str {
     // TODO(tailhook) escape raw value
     return s
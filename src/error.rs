@@ -1,98 +1,288 @@
-use combine::easy::{Errors, Error};
+use std::fmt;
 
-use tokenizer::Token;
-use position::Pos;
+use combine::{StreamOnce, Positioned};
+use combine::easy::{Errors, Error, Info};
+
+use tokenizer::{Token, TokenStream, Kind};
+use position::{Pos, advance};
 
 pub type InternalError<'a> = Errors<Token<'a>, Token<'a>, Pos>;
 
 
 /// Error parsing config
 ///
-/// This structure is opaque for forward compatibility. We are exploring a
-/// way to improve both error message and API.
-#[derive(Fail, Debug)]
-#[fail(display="parse error: {}", _0)]
-pub struct ParseError(Errors<String, String, Pos>);
+/// Besides the human-readable message (which is the `Display` output),
+/// the error exposes what kind of problem it is, which part of the source
+/// it covers and which directive it belongs to.
+#[derive(Debug, PartialEq)]
+pub struct ParseError(Box<Details>);
 
-#[cfg(not(feature="fuzzy_errors"))]
-impl<'a> From<InternalError<'a>> for ParseError {
-    fn from(e: InternalError<'a>) -> ParseError {
-        ParseError(e
-            .map_token(|t| t.value.to_string())
-            .map_range(|t| t.value.to_string()))
+#[derive(Debug, PartialEq)]
+struct Details {
+    kind: ErrorKind,
+    start: Pos,
+    end: Pos,
+    directive: Option<String>,
+    suggestions: Vec<String>,
+    errors: Errors<String, String, Pos>,
+}
+
+/// Kind of the parse error
+///
+/// There is no kind for unknown directives: like nginx modules which this
+/// crate doesn't know about, they are parsed into `ast::Item::Unknown`
+/// and are allowed in any context, so they are never an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Argument of the directive is not valid
+    InvalidValue,
+    /// Directive has too few or too many arguments
+    ArgumentCount,
+    /// Quoted string isn't closed before the end of line
+    UnterminatedString,
+    /// Block where the directive has no block or a stray `}`
+    UnexpectedBlock,
+    /// File ends in the middle of a directive or a block
+    UnexpectedEof,
+    /// Directive is not allowed in the context it's placed in
+    Context,
+    /// Any other error (not produced by the parser currently, but the
+    /// kind is guessed from the parser output which may change)
+    Other,
+}
+
+impl ParseError {
+    /// Kind of the error
+    pub fn kind(&self) -> ErrorKind {
+        self.0.kind
+    }
+    /// Position of the start of the offending token
+    pub fn position(&self) -> Pos {
+        self.0.start
+    }
+    /// Start and end positions of the offending token
+    ///
+    /// End is exclusive, i.e. it's the position just after the token. When
+    /// there is no token (e.g. at the end of file) start equals end.
+    pub fn span(&self) -> (Pos, Pos) {
+        (self.0.start, self.0.end)
+    }
+    /// Name of the directive which contains the error
+    ///
+    /// For errors at the end of a block (like unclosed block) this is the
+    /// directive which the block belongs to.
+    pub fn directive(&self) -> Option<&str> {
+        self.0.directive.as_ref().map(|x| &x[..])
+    }
+    /// Options that are close to the invalid value ("did you mean")
+    ///
+    /// Only filled when `fuzzy_errors` feature is enabled.
+    pub fn suggestions(&self) -> &[String] {
+        &self.0.suggestions
+    }
+
+    /// Lines of the message without the position line
+    pub(crate) fn message_lines(&self) -> Vec<String> {
+        self.0.errors.to_string().lines().skip(1)
+            .map(String::from)
+            .collect()
+    }
+
+    /// Converts a parser error adding info about the token and directive
+    ///
+    /// The `source` must be the text where the error was found.
+    pub(crate) fn new(error: InternalError, source: &str) -> ParseError {
+        let kind = error_kind(&error);
+        let (end, directive) = locate(error.position, source);
+        let (errors, suggestions) = convert_errors(error);
+        ParseError(Box::new(Details {
+            kind, start: errors.position, end, directive, suggestions, errors,
+        }))
+    }
+
+    /// Overrides the kind when it's known better than guessed from tokens
+    pub(crate) fn with_kind(mut self, kind: ErrorKind) -> ParseError {
+        self.0.kind = kind;
+        self
+    }
+
+    /// Error which isn't produced by parser, with a plain message
+    pub(crate) fn message(kind: ErrorKind, start: Pos, end: Pos,
+        directive: Option<String>, message: String)
+        -> ParseError
+    {
+        ParseError(Box::new(Details {
+            kind, start, end, directive,
+            suggestions: Vec::new(),
+            errors: Errors::new(start, Error::Message(Info::Owned(message))),
+        }))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "parse error: {}", self.0.errors)
+    }
+}
+
+impl ::failure::Fail for ParseError {}
+
+fn error_kind(error: &InternalError) -> ErrorKind {
+    let expects_semi = error.errors.iter().any(|e| {
+        matches!(*e, Error::Expected(Info::Owned(ref x)) if x == "Semicolon")
+    });
+    for item in &error.errors {
+        match *item {
+            ref e if e == &Error::end_of_input() => {
+                return ErrorKind::UnexpectedEof;
+            }
+            Error::Unexpected(Info::Owned(ref msg))
+                if msg == "unterminated string value"
+            => return ErrorKind::UnterminatedString,
+            Error::Unexpected(Info::Token(tok)) => {
+                return match tok.kind {
+                    Kind::Semicolon => ErrorKind::ArgumentCount,
+                    Kind::String if expects_semi => ErrorKind::ArgumentCount,
+                    Kind::String => ErrorKind::InvalidValue,
+                    Kind::BlockStart | Kind::BlockEnd
+                    => ErrorKind::UnexpectedBlock,
+                };
+            }
+            Error::Unexpected(_) | Error::Message(_) | Error::Other(_) => {
+                // messages are produced by validation of the values and
+                // `Other` by parsing numbers with `FromStr`
+                return ErrorKind::InvalidValue;
+            }
+            _ => {}
+        }
     }
+    ErrorKind::Other
 }
 
+/// Finds the end of the token at `pos` and the directive it belongs to
+fn locate(pos: Pos, source: &str) -> (Pos, Option<String>) {
+    let mut tokens = TokenStream::with_file(source, pos.file);
+    let mut blocks = Vec::new();
+    let mut current = None;
+    loop {
+        let start = tokens.position();
+        let token = match tokens.uncons() {
+            Ok(token) => token,
+            Err(_) => break,
+        };
+        if start >= pos {
+            let end = if start == pos { advance(start, token.value) }
+                      else { pos };
+            let directive = match token.kind {
+                Kind::String if current.is_none() => Some(token.value),
+                _ => current,
+            };
+            return (end, directive.or_else(|| blocks.last().cloned())
+                                  .map(String::from));
+        }
+        match token.kind {
+            Kind::String if current.is_none() => current = Some(token.value),
+            Kind::String => {}
+            Kind::Semicolon => current = None,
+            Kind::BlockStart => blocks.push(current.take().unwrap_or("")),
+            Kind::BlockEnd => {
+                blocks.pop();
+                current = None;
+            }
+        }
+    }
+    (pos, current.or_else(|| blocks.last().cloned()).map(String::from))
+}
+
+#[cfg(feature="fuzzy_errors")]
 fn convert(error: Error<Token, Token>) -> Error<String, String> {
     error
     .map_token(|t| t.value.to_string())
     .map_range(|t| t.value.to_string())
 }
 
+#[cfg(not(feature="fuzzy_errors"))]
+fn convert_errors(e: InternalError)
+    -> (Errors<String, String, Pos>, Vec<String>)
+{
+    (e.map_token(|t| t.value.to_string())
+      .map_range(|t| t.value.to_string()),
+     Vec::new())
+}
+
 #[cfg(feature="fuzzy_errors")]
-impl<'a> From<InternalError<'a>> for ParseError {
-    fn from(e: InternalError<'a>) -> ParseError {
-        use strsim::jaro_winkler;
-        use combine::easy::{Info};
-
-        let mut error_buf = Vec::new();
-        let mut expected_buf = Vec::new();
-        let mut unexpected = None;
-        // Note: we assume that "expected" will go after error
-        //       in output and that's fine
-        for item in e.errors {
-            match item {
-                Error::Expected(info) => {
-                    expected_buf.push(info);
-                    continue;
-                }
-                Error::Unexpected(ref val) => {
-                    unexpected = Some(val.to_string());
-                }
-                _ => {}
+fn convert_errors(e: InternalError)
+    -> (Errors<String, String, Pos>, Vec<String>)
+{
+    use strsim::jaro_winkler;
+
+    let mut error_buf = Vec::new();
+    let mut expected_buf = Vec::new();
+    let mut unexpected = None;
+    // Note: we assume that "expected" will go after error
+    //       in output and that's fine
+    for item in e.errors {
+        match item {
+            Error::Expected(info) => {
+                expected_buf.push(info);
+                continue;
+            }
+            Error::Unexpected(ref val) => {
+                unexpected = Some(val.to_string());
             }
-            error_buf.push(convert(item));
+            _ => {}
         }
-        println!("Unexpected {:?}, expected {:?}", unexpected, expected_buf);
-        if let Some(unexpected) = unexpected {
-            if expected_buf.len() > 3 {
-                let mut close = Vec::new();
-                for item in &expected_buf {
-                    match item {
-                        Info::Borrowed(item) => {
-                            let conf = jaro_winkler(&unexpected, item);
-                            if conf > 0.8 {
-                                close.push((item, conf));
-                            }
-                        }
-                        _ => {
-                            // assuming any other thing is just a text, not
-                            // expected token
-                        }
+        error_buf.push(convert(item));
+    }
+    let mut close = Vec::new();
+    if let Some(ref unexpected) = unexpected {
+        for item in &expected_buf {
+            match item {
+                Info::Borrowed(item) => {
+                    let conf = jaro_winkler(unexpected, item);
+                    if conf > 0.8 {
+                        close.push((*item, conf));
                     }
                 }
-                close.sort_by_key(|&(_, ref x)| (10000. - 10000. * x) as u32);
-                close.truncate(3);
-                for (item, _) in &close {
-                    error_buf.push(convert(Error::Expected(
-                        Info::Borrowed(item))));
-                }
-                if close.len() < expected_buf.len() {
-                    error_buf.push(Error::Expected(Info::Owned(format!(
-                        "one of {} options",
-                        expected_buf.len() - close.len(),
-                    ))));
-                }
-            } else {
-                for e in expected_buf {
-                    error_buf.push(convert(Error::Expected(e)));
+                _ => {
+                    // assuming any other thing is just a text, not
+                    // expected token
                 }
             }
-        } else {
-            for e in expected_buf {
-                error_buf.push(convert(Error::Expected(e)));
-            }
         }
-        return ParseError(Errors { position: e.position, errors: error_buf })
+        close.sort_by_key(|&(_, ref x)| (10000. - 10000. * x) as u32);
+        close.truncate(3);
+    }
+    let suggestions = close.iter().map(|&(x, _)| x.to_string()).collect();
+    if unexpected.is_some() && expected_buf.len() > 3 {
+        for &(item, _) in &close {
+            error_buf.push(convert(Error::Expected(Info::Borrowed(item))));
+        }
+        if close.len() < expected_buf.len() {
+            error_buf.push(Error::Expected(Info::Owned(format!(
+                "one of {} options",
+                expected_buf.len() - close.len(),
+            ))));
+        }
+    } else {
+        for e in expected_buf {
+            error_buf.push(convert(Error::Expected(e)));
+        }
+    }
+    (Errors { position: e.position, errors: error_buf }, suggestions)
+}
+
+#[cfg(test)]
+mod test {
+    use combine::easy::{Errors, Error, Info};
+
+    use position::Pos;
+    use super::{error_kind, ErrorKind};
+
+    #[test]
+    fn other() {
+        let error = Errors::new(Pos::default(),
+            Error::Expected(Info::Borrowed("on")));
+        assert_eq!(error_kind(&error), ErrorKind::Other);
     }
 }
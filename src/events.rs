@@ -0,0 +1,40 @@
+use combine::{Parser};
+use combine::{choice};
+
+use ast::{self, Item};
+use grammar::{bool, block};
+use helpers::{semi, ident, string};
+use tokenizer::{TokenStream};
+
+
+fn connection_method<'a>()
+    -> impl Parser<Output=ast::ConnectionMethod, Input=TokenStream<'a>>
+{
+    use ast::ConnectionMethod::*;
+    choice((
+        ident("select").map(|_| Select),
+        ident("poll").map(|_| Poll),
+        ident("kqueue").map(|_| Kqueue),
+        ident("epoll").map(|_| Epoll),
+        ident("/dev/poll").map(|_| DevPoll),
+        ident("eventport").map(|_| EventPort),
+    ))
+}
+
+pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    choice((
+        ident("events").with(block())
+            .map(|(position, directives)| {
+                Item::Events(ast::Events { position, directives })
+            }),
+        ident("worker_connections").with(string())
+            .and_then(|t| t.value.parse().map(Item::WorkerConnections))
+            .skip(semi()),
+        ident("use").with(connection_method()).skip(semi())
+            .map(Item::Use),
+        ident("multi_accept").with(bool()).skip(semi())
+            .map(Item::MultiAccept),
+        ident("accept_mutex").with(bool()).skip(semi())
+            .map(Item::AcceptMutex),
+    ))
+}
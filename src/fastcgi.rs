@@ -0,0 +1,56 @@
+use combine::{Parser};
+use combine::{choice, optional};
+
+use ast::{Item};
+use helpers::{semi, ident};
+use tokenizer::TokenStream;
+use grammar::{value, typed, bool, raw};
+use proxy::{cache_valid, next_upstream_flags};
+
+
+pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    choice((
+        ident("fastcgi_pass").with(value()).skip(semi())
+            .map(Item::FastcgiPass),
+        ident("fastcgi_param").with(value()).and(value())
+            .and(optional(ident("if_not_empty")).map(|x| x.is_some()))
+            .skip(semi())
+            .map(|((name, value), if_not_empty)| {
+                Item::FastcgiParam { name, value, if_not_empty }
+            }),
+        ident("fastcgi_buffering").with(bool()).skip(semi())
+            .map(Item::FastcgiBuffering),
+        ident("fastcgi_buffer_size").with(typed()).skip(semi())
+            .map(Item::FastcgiBufferSize),
+        ident("fastcgi_cache").with(value()).skip(semi())
+            .map(Item::FastcgiCache),
+        ident("fastcgi_cache_key").with(value()).skip(semi())
+            .map(Item::FastcgiCacheKey),
+        ident("fastcgi_cache_valid").with(cache_valid()).skip(semi())
+            .map(Item::FastcgiCacheValid),
+        ident("fastcgi_connect_timeout").with(typed()).skip(semi())
+            .map(Item::FastcgiConnectTimeout),
+        ident("fastcgi_read_timeout").with(typed()).skip(semi())
+            .map(Item::FastcgiReadTimeout),
+        ident("fastcgi_send_timeout").with(typed()).skip(semi())
+            .map(Item::FastcgiSendTimeout),
+        ident("fastcgi_next_upstream")
+            .with(next_upstream_flags("invalid fastcgi upstream flag"))
+            .skip(semi())
+            .map(Item::FastcgiNextUpstream),
+        ident("fastcgi_next_upstream_tries").with(value()).skip(semi())
+            .map(Item::FastcgiNextUpstreamTries),
+        ident("fastcgi_next_upstream_timeout").with(typed()).skip(semi())
+            .map(Item::FastcgiNextUpstreamTimeout),
+        ident("fastcgi_intercept_errors").with(bool()).skip(semi())
+            .map(Item::FastcgiInterceptErrors),
+        ident("fastcgi_hide_header").with(value()).skip(semi())
+            .map(Item::FastcgiHideHeader),
+        ident("fastcgi_pass_header").with(value()).skip(semi())
+            .map(Item::FastcgiPassHeader),
+        ident("fastcgi_index").with(value()).skip(semi())
+            .map(Item::FastcgiIndex),
+        ident("fastcgi_split_path_info").with(raw()).skip(semi())
+            .map(Item::FastcgiSplitPathInfo),
+    ))
+}
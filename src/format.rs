@@ -7,6 +7,8 @@ pub(crate) struct Formatter<'a> {
     buf: String,
     style: &'a Style,
     indent: u32,
+    comments: Vec<String>,
+    block_end_comments: Vec<Vec<String>>,
 }
 
 /// A configuration of formatting style
@@ -32,6 +34,9 @@ impl Style {
         self.indent = indent;
         self
     }
+    pub(crate) fn indent_width(&self) -> u32 {
+        self.indent
+    }
 }
 
 pub(crate) trait Displayable {
@@ -44,15 +49,67 @@ impl<'a> Formatter<'a> {
             buf: String::with_capacity(1024),
             style,
             indent: 0,
+            comments: Vec::new(),
+            block_end_comments: Vec::new(),
         }
     }
 
-    pub fn indent(&mut self) {
+    /// Formatter for a part of config nested at the `indent` level
+    pub fn with_indent(style: &Style, indent: u32) -> Formatter<'_> {
+        Formatter {
+            indent,
+            .. Formatter::new(style)
+        }
+    }
+
+    fn write_indent(&mut self) {
         for _ in 0..self.indent {
             self.buf.push(' ');
         }
     }
 
+    pub fn indent(&mut self) {
+        for comment in ::std::mem::take(&mut self.comments) {
+            self.comment(&comment);
+        }
+        self.write_indent();
+    }
+
+    /// Write a comment on a separate line
+    pub fn comment(&mut self, text: &str) {
+        self.write_indent();
+        self.buf.push('#');
+        self.buf.push_str(text);
+        self.endline();
+    }
+
+    /// Put a comment at the end of the last line written
+    pub fn trailing_comment(&mut self, text: &str) {
+        if self.buf.ends_with('\n') {
+            self.buf.pop();
+        }
+        self.buf.push_str(" #");
+        self.buf.push_str(text);
+        self.endline();
+    }
+
+    /// Comments to write before the next directive (after the margin)
+    pub fn leading_comments(&mut self, comments: &[String]) {
+        self.comments.extend(comments.iter().cloned());
+    }
+
+    /// Comments to write before the closing brace of the next block
+    ///
+    /// Must be paired with `pop_block_end_comments` after the directive
+    /// which owns the block is written.
+    pub fn push_block_end_comments(&mut self, comments: &[String]) {
+        self.block_end_comments.push(comments.to_vec());
+    }
+
+    pub fn pop_block_end_comments(&mut self) {
+        self.block_end_comments.pop();
+    }
+
     pub fn end(&mut self) {
         self.buf.push(';');
         self.buf.push('\n');
@@ -68,8 +125,15 @@ impl<'a> Formatter<'a> {
     }
 
     pub fn end_block(&mut self) {
-        self.indent = self.indent.checked_sub(self.style.indent)
-            .expect("negative indent");
+        let comments = self.block_end_comments.last_mut()
+            .map(::std::mem::take)
+            .unwrap_or_default();
+        for comment in comments {
+            self.comment(&comment);
+        }
+        // unbalanced blocks can't come from the parser, but don't panic
+        // when formatting manually constructed nodes either
+        self.indent = self.indent.saturating_sub(self.style.indent);
         self.indent();
         self.buf.push('}');
         self.endline();
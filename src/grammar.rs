@@ -1,24 +1,40 @@
-use combine::{eof, many, many1, Parser};
+use std::str::FromStr;
+
+use combine::{eof, many, many1, Parser, StreamOnce};
 use combine::{choice, position};
 use combine::combinator::{opaque, no_partial, FnOpaque};
-use combine::error::StreamError;
+use combine::error::{StreamError, Tracked, FastResult, ConsumedResult};
 use combine::easy::Error;
+use combine::stream::Resetable;
 
 use ast::{self, Main, Directive, Item};
-use error::ParseError;
+use error::{ParseError, InternalError};
 use helpers::{semi, ident, text, string};
-use position::Pos;
-use tokenizer::{TokenStream, Token};
+use helpers::{leading_comments, trailing_comment, block_end_comments};
+use helpers::end_position;
+use position::{Pos, Span, FileId};
+use tokenizer::{TokenStream, Token, Kind};
 use value::Value;
 
 use access;
+use context::{self, Context};
 use core;
+use events;
+use fastcgi;
+use grpc;
 use gzip;
 use headers;
 use proxy;
 use rewrite;
+use scgi;
+use ssl;
+use stream;
+use limits;
 use log;
+use main_core;
 use real_ip;
+use upstream;
+use uwsgi;
 
 
 pub enum Code {
@@ -38,17 +54,16 @@ pub fn value<'a>() -> impl Parser<Output=Value, Input=TokenStream<'a>> {
     .and_then(|(p, v)| Value::parse(p, v))
 }
 
-pub fn worker_processes<'a>()
-    -> impl Parser<Output=Item, Input=TokenStream<'a>>
+/// Value which is parsed as `T` (e.g. size or time) if it has no variables
+pub fn typed<'a, T>()
+    -> impl Parser<Output=ast::Typed<T>, Input=TokenStream<'a>>
+    where T: FromStr<Err=String>
 {
-    use ast::WorkerProcesses;
-    ident("worker_processes")
-    .with(choice((
-        ident("auto").map(|_| WorkerProcesses::Auto),
-        string().and_then(|s| s.value.parse().map(WorkerProcesses::Exact)),
-    )))
-    .skip(semi())
-    .map(Item::WorkerProcesses)
+    value().and_then(|v| match v.as_literal().map(|x| x.parse()) {
+        Some(Ok(parsed)) => Ok(ast::Typed::Parsed(parsed)),
+        Some(Err(e)) => Err(Error::unexpected_message(e)),
+        None => Ok(ast::Typed::Variables(v)),
+    })
 }
 
 pub fn server_name<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
@@ -99,15 +114,21 @@ pub fn map<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
         }
     }))
     .skip(kind(BlockStart))
-    .and(many(choice((
-        ident("hostnames").map(|_| Tok::Hostnames),
-        ident("volatile").map(|_| Tok::Volatile),
-        ident("default").with(value()).map(|v| Tok::Default(v)),
-        ident("include").with(raw()).map(|v| Tok::Include(v)),
-        raw().and(value()).map(|(s, v)| Tok::Pattern(s, v)),
-    )).skip(semi())))
+    .and(many((
+        leading_comments(),
+        choice((
+            ident("hostnames").map(|_| Tok::Hostnames),
+            ident("volatile").map(|_| Tok::Volatile),
+            ident("default").with(value()).map(|v| Tok::Default(v)),
+            ident("include").with(raw()).map(|v| Tok::Include(v)),
+            raw().and(value()).map(|(s, v)| Tok::Pattern(s, v)),
+        )).skip(semi()),
+        trailing_comment(),
+    ).map(|(leading, tok, trailing)| {
+        (tok, ast::Comments { leading, trailing, block_end: Vec::new() })
+    })))
     .skip(kind(BlockEnd))
-    .map(|((expression, variable), vec): ((_, _), Vec<Tok>)| {
+    .map(|((expression, variable), vec): ((_, _), Vec<(Tok, _)>)| {
         let mut res = ::ast::Map {
             variable, expression,
             default: None,
@@ -115,15 +136,29 @@ pub fn map<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
             volatile: false,
             includes: Vec::new(),
             patterns: Vec::new(),
+            comments: Box::default(),
         };
-        for val in vec {
+        for (val, comments) in vec {
             match val {
-                Tok::Hostnames => res.hostnames = true,
-                Tok::Volatile => res.volatile = true,
-                Tok::Default(v) => res.default = Some(v),
-                Tok::Include(path) => res.includes.push(path),
+                Tok::Hostnames => {
+                    res.hostnames = true;
+                    res.comments.hostnames = comments;
+                }
+                Tok::Volatile => {
+                    res.volatile = true;
+                    res.comments.volatile = comments;
+                }
+                Tok::Default(v) => {
+                    res.default = Some(v);
+                    res.comments.default = comments;
+                }
+                Tok::Include(path) => {
+                    res.includes.push(path);
+                    res.comments.includes.push(comments);
+                }
                 Tok::Pattern(x, targ) => {
                     use ast::MapPattern::*;
+                    res.comments.patterns.push(comments);
                     let mut s = &x[..];
                     if s.starts_with('~') {
                         res.patterns.push((Regex(s[1..].to_string()), targ));
@@ -161,14 +196,109 @@ pub fn block<'a>()
         f(&mut no_partial((
                 position(),
                 kind(BlockStart)
-                    .with(many(directive()))
+                    .with(Recover(many(directive())))
                     .skip(kind(BlockEnd)),
-                position(),
+                end_position(),
         ))
         .map(|(s, dirs, e)| ((s, e), dirs)))
     })
 }
 
+/// Directives of a block which skips invalid ones in recovering mode
+///
+/// Unless the stream is recovering this is just the wrapped `many`
+/// parser, so error messages are exactly the same as without it.
+struct Recover<P>(P);
+
+impl<'a, P> Parser for Recover<P>
+    where P: Parser<Input=TokenStream<'a>, Output=Vec<Directive>>
+{
+    type Input = TokenStream<'a>;
+    type Output = Vec<Directive>;
+    type PartialState = P::PartialState;
+
+    fn parse_partial(&mut self, input: &mut TokenStream<'a>,
+                     state: &mut P::PartialState)
+        -> ConsumedResult<Vec<Directive>, TokenStream<'a>>
+    {
+        if !input.is_recovering() {
+            return self.0.parse_partial(input, state);
+        }
+        let start = input.offset();
+        let directives = recover_directives(input, false);
+        if input.offset() == start {
+            FastResult::EmptyOk(directives)
+        } else {
+            FastResult::ConsumedOk(directives)
+        }
+    }
+    fn add_error(&mut self, error: &mut Tracked<InternalError<'a>>) {
+        self.0.add_error(error)
+    }
+}
+
+/// Parses directives until the end of block (or input) skipping errors
+///
+/// An invalid directive is skipped up to the next `;` or past its block,
+/// whichever comes first. At the top level stray `}` is skipped too.
+fn recover_directives<'a>(input: &mut TokenStream<'a>, top_level: bool)
+    -> Vec<Directive>
+{
+    let mut directives = Vec::new();
+    loop {
+        let before = input.checkpoint();
+        match input.uncons() {
+            Err(_) => break,
+            Ok(Token { kind: Kind::BlockEnd, .. }) if !top_level => {
+                input.reset(before);
+                break;
+            }
+            Ok(_) => input.reset(before.clone()),
+        }
+        match directive().parse_stream(input) {
+            Ok((dir, _)) => directives.push(dir),
+            Err(e) => {
+                input.reset(before);
+                input.record_error(e.into_inner().error);
+                let start = input.offset();
+                skip_directive(input);
+                if input.offset() == start {
+                    // stray block end at the top level
+                    input.uncons().ok();
+                }
+            }
+        }
+    }
+    directives
+}
+
+fn skip_directive(input: &mut TokenStream) {
+    let mut depth = 0;
+    loop {
+        let before = input.checkpoint();
+        let tok = match input.uncons() {
+            Ok(tok) => tok,
+            Err(_) => break,
+        };
+        match tok.kind {
+            Kind::Semicolon if depth == 0 => break,
+            Kind::BlockStart => depth += 1,
+            Kind::BlockEnd if depth == 0 => {
+                // belongs to the enclosing block
+                input.reset(before);
+                break;
+            }
+            Kind::BlockEnd => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Kind::Semicolon | Kind::String => {}
+        }
+    }
+}
+
 // A string that forbids variables
 pub fn raw<'a>() -> impl Parser<Output=String, Input=TokenStream<'a>> {
     // TODO(tailhook) unquote single and double quotes
@@ -176,6 +306,15 @@ pub fn raw<'a>() -> impl Parser<Output=String, Input=TokenStream<'a>> {
     string().and_then(|t| Ok::<_, Error<_, _>>(t.value.to_string()))
 }
 
+/// Argument which can't have variables, with quotes and escapes removed
+pub fn literal<'a>() -> impl Parser<Output=String, Input=TokenStream<'a>> {
+    value().and_then(|v| match v.as_literal() {
+        Some(text) => Ok(text.to_string()),
+        None => Err(Error::unexpected_message(
+            "variables are not allowed here")),
+    })
+}
+
 pub fn location<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
     use ast::LocationPattern::*;
     ident("location").with(choice((
@@ -184,10 +323,9 @@ pub fn location<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
         text("~").with(raw().map(Regex)),
         text("~*").with(raw().map(RegexInsensitive)),
         raw()
-            .map(|v| if v.starts_with('*') {
-                Named(v)
-            } else {
-                Prefix(v)
+            .map(|v| match v.strip_prefix('@') {
+                Some(name) => Named(name.to_string()),
+                None => Prefix(v),
             }),
     ))).and(block())
     .map(|(pattern, (position, directives))| {
@@ -219,7 +357,7 @@ impl Code {
 pub fn try_files<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
     use ast::TryFilesLastOption::*;
     use ast::Item::TryFiles;
-    use value::Item::*;
+    use value::Part::*;
 
     ident("try_files")
     .with(many1(value()))
@@ -271,70 +409,188 @@ pub fn openresty<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
     ))
 }
 
+pub fn unknown<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    (
+        string().map(|t| t.value.to_string()),
+        many((position(), string())
+            .map(|(p, t)| Value::parse_lenient(p, t))),
+        choice((
+            semi().map(|_| None),
+            block().map(|(_, directives)| Some(directives)),
+        )),
+    )
+    .map(|(name, args, block)| Item::Unknown { name, args, block })
+}
+
 pub fn directive<'a>() -> impl Parser<Output=Directive, Input=TokenStream<'a>>
 {
-    position()
-    .and(choice((
-        ident("daemon").with(bool()).skip(semi())
-            .map(Item::Daemon),
-        ident("master_process").with(bool()).skip(semi())
-            .map(Item::MasterProcess),
-        worker_processes(),
-        ident("http").with(block())
-            .map(|(position, directives)| ast::Http { position, directives })
-            .map(Item::Http),
-        ident("server").with(block())
-            .map(|(position, directives)| ast::Server { position, directives })
-            .map(Item::Server),
-        rewrite::directives(),
-        try_files(),
-        ident("include").with(value()).skip(semi()).map(Item::Include),
-        ident("ssl_certificate").with(value()).skip(semi())
-            .map(Item::SslCertificate),
-        ident("ssl_certificate_key").with(value()).skip(semi())
-            .map(Item::SslCertificateKey),
-        location(),
-        headers::directives(),
-        server_name(),
-        map(),
-        ident("client_max_body_size").with(value()).skip(semi())
-            .map(Item::ClientMaxBodySize),
-        proxy::directives(),
-        gzip::directives(),
-        core::directives(),
-        access::directives(),
-        log::directives(),
-        real_ip::directives(),
-        openresty(),
-        // it's own module
-        ident("empty_gif").skip(semi()).map(|_| Item::EmptyGif),
-        ident("index").with(many(value())).skip(semi())
-            .map(Item::Index),
-    )))
-    .map(|(pos, dir)| Directive {
-        position: pos,
-        item: dir,
+    (
+        leading_comments(),
+        position(),
+        choice((
+            main_core::directives(),
+            events::directives(),
+            ident("http").with(block())
+                .map(|(position, directives)| {
+                    ast::Http { position, directives }
+                })
+                .map(Item::Http),
+            ident("server").with(choice((
+                block()
+                    .map(|(position, directives)| {
+                        ast::Server { position, directives }
+                    })
+                    .map(Item::Server),
+                upstream::server(),
+            ))),
+            rewrite::directives(),
+            try_files(),
+            ident("include").with(value()).skip(semi()).map(Item::Include),
+            ssl::directives(),
+            location(),
+            headers::directives(),
+            server_name(),
+            map(),
+            ident("client_max_body_size").with(typed()).skip(semi())
+                .map(Item::ClientMaxBodySize),
+            proxy::directives(),
+            gzip::directives(),
+            core::directives(),
+            access::directives(),
+            log::directives(),
+            real_ip::directives(),
+            upstream::directives(),
+            openresty(),
+            // it's own module
+            ident("empty_gif").skip(semi()).map(|_| Item::EmptyGif),
+            ident("index").with(many(value())).skip(semi())
+                .map(Item::Index),
+        )).or(choice((
+            stream::directives(),
+            limits::directives(),
+            fastcgi::directives(),
+            uwsgi::directives(),
+            scgi::directives(),
+            grpc::directives(),
+        ))).or(unknown()),
+        end_position(),
+        trailing_comment(),
+        block_end_comments(),
+    )
+    .map(|(leading, position, item, end, trailing, block_end)| Directive {
+        position,
+        span: Span { start: position, end },
+        item,
+        comments: ast::Comments { leading, trailing, block_end },
     })
 }
 
 
 /// Parses a piece of config in "main" context (i.e. top-level)
 ///
-/// Currently, this is the same as parse_directives (except wraps everyting
-/// to a `Main` struct), but we expect to
-/// add validation/context checks in this function.
+/// Unlike `parse_directives` this keeps comments after the last directive
+/// and checks that every known directive is placed in a context where
+/// nginx allows it (see [`context::allowed_contexts`]).
+///
+/// [`context::allowed_contexts`]: context/fn.allowed_contexts.html
 pub fn parse_main(s: &str) -> Result<Main, ParseError> {
-    parse_directives(s).map(|directives| Main { directives })
+    let mut tokens = TokenStream::new(s);
+    let ((directives, end_comments), _) = many1::<Vec<_>, _>(directive())
+        .and(leading_comments())
+        .skip(eof())
+        .parse_stream(&mut tokens)
+        .map_err(|e| ParseError::new(e.into_inner().error, s))?;
+    context::validate(&directives, Context::Main)?;
+    Ok(Main { directives, end_comments })
+}
+
+/// Parses a piece of config in "main" context reporting all the errors
+///
+/// Unlike `parse_main` this doesn't stop at the first error: an invalid
+/// directive is skipped up to the next `;` (or past its block if it has
+/// one) and parsing continues. Returns the directives which were parsed
+/// successfully along with all parse and context errors sorted by
+/// position. An error inside of a block drops only the invalid directive,
+/// not the whole block.
+pub fn parse_main_recovering(s: &str) -> (Main, Vec<ParseError>) {
+    let mut tokens = TokenStream::recovering(s);
+    let directives = recover_directives(&mut tokens, true);
+    let end_comments = leading_comments().parse_stream(&mut tokens)
+        .map(|(comments, _)| comments)
+        .unwrap_or_default();
+    let mut errors = tokens.take_errors().into_iter()
+        .map(|e| ParseError::new(e, s))
+        .collect::<Vec<_>>();
+    context::validate_all(&directives, Context::Main, &mut errors);
+    errors.sort_by_key(|e| e.position());
+    (Main { directives, end_comments }, errors)
+}
+
+/// Parses a piece of config from arbitrary context reporting all the errors
+///
+/// This is a recovering counterpart of `parse_directives`, see
+/// `parse_main_recovering` for details.
+pub fn parse_directives_recovering(s: &str)
+    -> (Vec<Directive>, Vec<ParseError>)
+{
+    let mut tokens = TokenStream::recovering(s);
+    let directives = recover_directives(&mut tokens, true);
+    let errors = tokens.take_errors().into_iter()
+        .map(|e| ParseError::new(e, s))
+        .collect();
+    (directives, errors)
 }
 
 /// Parses a piece of config from arbitrary context
 ///
 /// This implies no validation of what context directives belong to.
+///
+/// Comments after the last directive are dropped, as there is no directive
+/// to attach them to. Use `parse_main` to keep them in `Main.end_comments`.
 pub fn parse_directives(s: &str) -> Result<Vec<Directive>, ParseError> {
-    let mut tokens = TokenStream::new(s);
+    parse_file_directives(s, FileId::default())
+}
+
+/// Parses a piece of config and validates it as being put in the context
+///
+/// For example, contents of a file included into the `http` block can be
+/// checked with `parse_directives_in(text, Context::Http)`.
+pub fn parse_directives_in(s: &str, context: Context)
+    -> Result<Vec<Directive>, ParseError>
+{
+    let directives = parse_directives(s)?;
+    context::validate(&directives, context)?;
+    Ok(directives)
+}
+
+/// Same as `parse_directives` but positions refer to the specified file
+///
+/// Comments after the last directive are dropped, see
+/// `parse_included_file` for the function which keeps them.
+pub(crate) fn parse_file_directives(s: &str, file: FileId)
+    -> Result<Vec<Directive>, ParseError>
+{
+    let mut tokens = TokenStream::with_file(s, file);
     let (doc, _) = many1(directive())
         .skip(eof())
         .parse_stream(&mut tokens)
-        .map_err(|e| e.into_inner().error)?;
+        .map_err(|e| ParseError::new(e.into_inner().error, s))?;
     Ok(doc)
 }
+
+/// Parses a file included into another config
+///
+/// Unlike `parse_file_directives` the file may be empty, and comments after
+/// the last directive are returned too (snippets in `conf.d` often consist
+/// only of comments).
+pub(crate) fn parse_included_file(s: &str, file: FileId)
+    -> Result<(Vec<Directive>, Vec<String>), ParseError>
+{
+    let mut tokens = TokenStream::with_file(s, file);
+    let (result, _) = many::<Vec<_>, _>(directive())
+        .and(leading_comments())
+        .skip(eof())
+        .parse_stream(&mut tokens)
+        .map_err(|e| ParseError::new(e.into_inner().error, s))?;
+    Ok(result)
+}
@@ -0,0 +1,81 @@
+use combine::{Parser};
+use combine::{choice, many1};
+use combine::error::StreamError;
+use combine::easy::Error;
+
+use ast::{self, Item, GrpcScheme};
+use helpers::{semi, ident, string};
+use tokenizer::TokenStream;
+use grammar::{value, typed, bool};
+use proxy::next_upstream_flags;
+use ssl::protocol;
+
+
+fn grpc_pass<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    ident("grpc_pass")
+    .with(value().and_then(|mut address| {
+        let scheme = if address.strip_literal_prefix("grpc://") {
+            Some(GrpcScheme::Grpc)
+        } else if address.strip_literal_prefix("grpcs://") {
+            Some(GrpcScheme::Grpcs)
+        } else if address.to_string().contains("://") {
+            return Err(Error::unexpected_message(
+                "only grpc:// and grpcs:// schemes are supported"));
+        } else {
+            None
+        };
+        Ok(ast::GrpcPass { scheme, address })
+    }))
+    .skip(semi())
+    .map(Item::GrpcPass)
+}
+
+pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    choice((
+        grpc_pass(),
+        ident("grpc_set_header").with(value()).and(value())
+            .skip(semi())
+            .map(|(field, value)| Item::GrpcSetHeader { field, value }),
+        ident("grpc_read_timeout").with(typed()).skip(semi())
+            .map(Item::GrpcReadTimeout),
+        ident("grpc_send_timeout").with(typed()).skip(semi())
+            .map(Item::GrpcSendTimeout),
+        ident("grpc_connect_timeout").with(typed()).skip(semi())
+            .map(Item::GrpcConnectTimeout),
+        ident("grpc_ssl_certificate").with(value()).skip(semi())
+            .map(Item::GrpcSslCertificate),
+        ident("grpc_ssl_certificate_key").with(value()).skip(semi())
+            .map(Item::GrpcSslCertificateKey),
+        ident("grpc_ssl_ciphers").with(value()).skip(semi())
+            .map(Item::GrpcSslCiphers),
+        ident("grpc_ssl_protocols").with(many1(protocol())).skip(semi())
+            .map(Item::GrpcSslProtocols),
+        ident("grpc_ssl_server_name").with(bool()).skip(semi())
+            .map(Item::GrpcSslServerName),
+        ident("grpc_ssl_name").with(value()).skip(semi())
+            .map(Item::GrpcSslName),
+        ident("grpc_ssl_verify").with(bool()).skip(semi())
+            .map(Item::GrpcSslVerify),
+        ident("grpc_ssl_verify_depth").with(string())
+            .and_then(|t| t.value.parse().map(Item::GrpcSslVerifyDepth))
+            .skip(semi()),
+        ident("grpc_ssl_trusted_certificate").with(value()).skip(semi())
+            .map(Item::GrpcSslTrustedCertificate),
+        ident("grpc_ssl_session_reuse").with(bool()).skip(semi())
+            .map(Item::GrpcSslSessionReuse),
+        ident("grpc_next_upstream")
+            .with(next_upstream_flags("invalid grpc upstream flag"))
+            .skip(semi())
+            .map(Item::GrpcNextUpstream),
+        ident("grpc_next_upstream_tries").with(value()).skip(semi())
+            .map(Item::GrpcNextUpstreamTries),
+        ident("grpc_next_upstream_timeout").with(typed()).skip(semi())
+            .map(Item::GrpcNextUpstreamTimeout),
+        ident("grpc_buffer_size").with(typed()).skip(semi())
+            .map(Item::GrpcBufferSize),
+        ident("grpc_intercept_errors").with(bool()).skip(semi())
+            .map(Item::GrpcInterceptErrors),
+        ident("grpc_hide_header").with(value()).skip(semi())
+            .map(Item::GrpcHideHeader),
+    ))
+}
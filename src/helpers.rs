@@ -1,7 +1,7 @@
 use std::marker::PhantomData;
 
-use combine::{Parser, ConsumedResult, satisfy, StreamOnce};
-use combine::error::{Tracked};
+use combine::{Parser, ConsumedResult, satisfy, StreamOnce, parser};
+use combine::error::{Consumed, Tracked};
 use combine::stream::easy::{Error, Errors, Info};
 
 use tokenizer::{TokenStream, Kind, Token};
@@ -65,6 +65,37 @@ pub fn string<'x>() -> TokenMatch<'x> {
     kind(Kind::String)
 }
 
+pub fn leading_comments<'a>()
+    -> impl Parser<Output=Vec<String>, Input=TokenStream<'a>>
+{
+    parser(|input: &mut TokenStream<'a>| {
+        Ok((input.leading_comments(), Consumed::Empty(())))
+    })
+}
+
+pub fn trailing_comment<'a>()
+    -> impl Parser<Output=Option<String>, Input=TokenStream<'a>>
+{
+    parser(|input: &mut TokenStream<'a>| {
+        Ok((input.trailing_comment(), Consumed::Empty(())))
+    })
+}
+
+/// Position just after the last token consumed
+pub fn end_position<'a>() -> impl Parser<Output=Pos, Input=TokenStream<'a>> {
+    parser(|input: &mut TokenStream<'a>| {
+        Ok((input.last_token_end(), Consumed::Empty(())))
+    })
+}
+
+pub fn block_end_comments<'a>()
+    -> impl Parser<Output=Vec<String>, Input=TokenStream<'a>>
+{
+    parser(|input: &mut TokenStream<'a>| {
+        Ok((input.block_end_comments(), Consumed::Empty(())))
+    })
+}
+
 impl<'a> Parser for TokenMatch<'a> {
     type Input = TokenStream<'a>;
     type Output = Token<'a>;
@@ -106,6 +137,25 @@ impl<'a> Parser for Value<'a> {
     }
 }
 
+/// Strips quotes if the text inside is the same as it would be unquoted
+///
+/// Quoted text which has escapes or characters that need quoting is
+/// returned as is, so it doesn't match any prefix.
+fn unquote(value: &str) -> &str {
+    let inner = match value.as_bytes().first() {
+        Some(&q) if (q == b'"' || q == b'\'') && value.len() >= 2
+            && value.as_bytes()[value.len()-1] == q
+        => &value[1..value.len()-1],
+        _ => return value,
+    };
+    let special = |c: char| c.is_whitespace() ||
+        matches!(c, '"' | '\'' | '\\' | ';' | '{' | '}' | '#');
+    if inner.is_empty() || inner.contains(special) {
+        return value;
+    }
+    inner
+}
+
 impl<'a> Parser for Prefix<'a> {
     type Input = TokenStream<'a>;
     type Output = &'a str;
@@ -116,9 +166,9 @@ impl<'a> Parser for Prefix<'a> {
         -> ConsumedResult<Self::Output, Self::Input>
     {
         satisfy(|c: Token<'a>| {
-            c.kind == Kind::String && c.value.starts_with(self.value)
+            c.kind == Kind::String && unquote(c.value).starts_with(self.value)
         })
-        .map(|t: Token<'a>| &t.value[self.value.len()..])
+        .map(|t: Token<'a>| &unquote(t.value)[self.value.len()..])
         .parse_lazy(input)
     }
 
@@ -0,0 +1,439 @@
+//! Expanding `include` directives
+//!
+//! The [`parse_file_with_includes`] function reads a config file and
+//! replaces every `include` directive by the directives of the included
+//! files. Files are read through the [`Loader`] trait so that configs can
+//! be resolved from an in-memory filesystem as well as from disk:
+//!
+//! ```rust
+//! # use std::collections::BTreeMap;
+//! # use std::io;
+//! # use std::path::{Path, PathBuf};
+//! use nginx_config::include::{Loader, parse_file_with_includes};
+//!
+//! struct Memory(BTreeMap<PathBuf, &'static str>);
+//!
+//! impl Loader for Memory {
+//!     fn read_file(&self, path: &Path) -> io::Result<String> {
+//!         self.0.get(path).map(|x| x.to_string())
+//!             .ok_or_else(|| io::ErrorKind::NotFound.into())
+//!     }
+//!     fn read_dir(&self, path: &Path) -> io::Result<Vec<String>> {
+//!         Ok(self.0.keys()
+//!             .filter(|p| p.parent() == Some(path))
+//!             .filter_map(|p| p.file_name()?.to_str().map(String::from))
+//!             .collect())
+//!     }
+//! }
+//!
+//! let fs = Memory(vec![
+//!     (PathBuf::from("/etc/nginx/nginx.conf"),
+//!      "http { include conf.d/*.conf; }"),
+//!     (PathBuf::from("/etc/nginx/conf.d/b.conf"), "gzip on;"),
+//!     (PathBuf::from("/etc/nginx/conf.d/a.conf"), "etag off;"),
+//! ].into_iter().collect());
+//! let main = parse_file_with_includes("/etc/nginx/nginx.conf", &fs)
+//!     .unwrap();
+//! assert_eq!(main.to_string(), "\
+//! http {
+//!     etag off;
+//!     gzip on;
+//! }
+//! ");
+//! ```
+//!
+//! Use [`parse_file_with_source_map`] to find out which file each
+//! directive comes from: every included file gets its own [`FileId`] in
+//! positions and the [`SourceMap`] keeps its path and include chain.
+//!
+//! [`parse_file_with_includes`]: fn.parse_file_with_includes.html
+//! [`parse_file_with_source_map`]: fn.parse_file_with_source_map.html
+//! [`Loader`]: trait.Loader.html
+//! [`SourceMap`]: struct.SourceMap.html
+//! [`FileId`]: ../struct.FileId.html
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf, Component};
+
+use glob::{Pattern, MatchOptions};
+
+use ast::{Main, Directive, Item};
+use context::{self, Context};
+use error::ParseError;
+use grammar::{parse_main, parse_included_file};
+use position::{Pos, FileId};
+
+
+/// Source of config files for include resolution
+pub trait Loader {
+    /// Read the whole file as a string
+    fn read_file(&self, path: &Path) -> io::Result<String>;
+    /// List names of the entries of a directory (in any order)
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<String>>;
+}
+
+/// Loader which reads files from disk
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsLoader;
+
+/// Paths and include chains of the files loaded by
+/// [`parse_file_with_source_map`](fn.parse_file_with_source_map.html)
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    base: PathBuf,
+    files: Vec<SourceFile>,
+}
+
+#[derive(Debug, Clone)]
+struct SourceFile {
+    path: PathBuf,
+    included_from: Option<Pos>,
+}
+
+/// Error resolving includes
+#[derive(Fail, Debug)]
+pub enum IncludeError {
+    /// Error reading a file or a directory
+    #[fail(display="error reading {:?}: {}", path, error)]
+    Io {
+        /// Path of the file or directory
+        path: PathBuf,
+        /// Original error
+        #[cause] error: io::Error,
+    },
+    /// Error parsing a file
+    #[fail(display="error parsing {:?}: {}", path, error)]
+    Parse {
+        /// Path of the file
+        path: PathBuf,
+        /// Original error
+        #[cause] error: ParseError,
+        /// Positions of the `include` directives which led to the file,
+        /// along with the paths of files containing them, innermost first
+        included_from: Vec<(PathBuf, Pos)>,
+    },
+    /// The include path contains variables, so it can't be resolved
+    #[fail(display="{:?}, {}: variables are not allowed in include path",
+           path, position)]
+    Variables {
+        /// File containing the include directive
+        path: PathBuf,
+        /// Position of the include directive
+        position: Pos,
+    },
+    /// A file includes itself directly or through other files
+    #[fail(display="include cycle: {:?}", chain)]
+    Cycle {
+        /// Files in the order of inclusion, the last one is the same as
+        /// some previous one
+        chain: Vec<PathBuf>,
+    },
+}
+
+impl Loader for FsLoader {
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(path)? {
+            if let Some(name) = entry?.file_name().to_str() {
+                names.push(name.to_string());
+            }
+        }
+        Ok(names)
+    }
+}
+
+impl SourceMap {
+    fn new(main: &Path) -> SourceMap {
+        SourceMap {
+            base: main.parent().map(|p| p.to_path_buf()).unwrap_or_default(),
+            files: vec![SourceFile {
+                path: main.to_path_buf(),
+                included_from: None,
+            }],
+        }
+    }
+    fn add(&mut self, path: &Path, included_from: Pos) -> FileId {
+        self.files.push(SourceFile {
+            path: path.to_path_buf(),
+            included_from: Some(included_from),
+        });
+        FileId(self.files.len() as u32 - 1)
+    }
+    /// Path of the file (relative paths are joined with the directory of
+    /// the main config)
+    ///
+    /// # Panics
+    ///
+    /// When file id is not from this source map
+    pub fn path(&self, file: FileId) -> &Path {
+        &self.files[file.index()].path
+    }
+    /// Position of the `include` directive which loaded the file
+    ///
+    /// Returns `None` for the main file.
+    pub fn included_from(&self, file: FileId) -> Option<Pos> {
+        self.files.get(file.index()).and_then(|f| f.included_from)
+    }
+    /// Positions of the `include` directives leading to the file,
+    /// the innermost first
+    pub fn include_chain(&self, file: FileId) -> Vec<Pos> {
+        let mut chain = Vec::new();
+        let mut cur = self.included_from(file);
+        while let Some(pos) = cur {
+            chain.push(pos);
+            cur = self.included_from(pos.file);
+        }
+        chain
+    }
+    /// Number of files loaded (including the main one)
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+    /// Returns `true` if no files are loaded
+    ///
+    /// Source maps returned by the parser always contain at least the
+    /// main file, so this is `false` for them.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+    fn short_path(&self, file: FileId) -> &Path {
+        let path = self.path(file);
+        path.strip_prefix(&self.base).unwrap_or(path)
+    }
+    /// Format position with the file name and include chain, e.g.
+    /// `conf.d/api.conf:12:5 (included from nginx.conf:40)`
+    ///
+    /// File paths are shown relative to the directory of the main config
+    /// when possible.
+    pub fn describe(&self, pos: Pos) -> String {
+        let mut buf = format!("{}:{}",
+            self.short_path(pos.file).display(), pos);
+        for inc in self.include_chain(pos.file) {
+            buf.push_str(&format!(" (included from {}:{})",
+                self.short_path(inc.file).display(), inc.line));
+        }
+        buf
+    }
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+struct Resolver<'a, L: Loader + 'a> {
+    loader: &'a L,
+    stack: Vec<PathBuf>,
+    map: SourceMap,
+}
+
+impl<'a, L: Loader> Resolver<'a, L> {
+    /// Expands a glob pattern matching each path component separately,
+    /// results are sorted like `glob(3)` does
+    fn glob(&self, pattern: &Path) -> Result<Vec<PathBuf>, IncludeError> {
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: true,
+        };
+        let mut paths = vec![PathBuf::new()];
+        for component in pattern.components() {
+            let part = match component {
+                Component::Normal(part) => part.to_string_lossy(),
+                _ => {
+                    for path in &mut paths {
+                        path.push(component.as_os_str());
+                    }
+                    continue;
+                }
+            };
+            if !is_glob(&part) {
+                for path in &mut paths {
+                    path.push(&*part);
+                }
+                continue;
+            }
+            let matcher = Pattern::new(&part).map_err(|e| IncludeError::Io {
+                path: pattern.to_path_buf(),
+                error: io::Error::new(io::ErrorKind::InvalidInput, e.msg),
+            })?;
+            let mut next = Vec::new();
+            for dir in paths {
+                let names = match self.loader.read_dir(&dir) {
+                    Ok(names) => names,
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                        continue;
+                    }
+                    Err(error) => {
+                        return Err(IncludeError::Io { path: dir, error });
+                    }
+                };
+                for name in names {
+                    if matcher.matches_with(&name, options) {
+                        next.push(dir.join(name));
+                    }
+                }
+            }
+            paths = next;
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Returns directives of the file and comments after the last one
+    ///
+    /// Directives are validated against the `context` of the `include`
+    /// (if it's known) the same way as if they were written inline.
+    fn load(&mut self, path: &Path, included_from: Pos,
+        context: Option<Context>)
+        -> Result<(Vec<Directive>, Vec<String>), IncludeError>
+    {
+        let text = self.loader.read_file(path)
+            .map_err(|error| IncludeError::Io {
+                path: path.to_path_buf(),
+                error,
+            })?;
+        let file = self.map.add(path, included_from);
+        let (directives, end_comments) = parse_included_file(&text, file)
+            .and_then(|(directives, end_comments)| {
+                if let Some(context) = context {
+                    context::validate(&directives, context)?;
+                }
+                Ok((directives, end_comments))
+            })
+            .map_err(|error| IncludeError::Parse {
+                path: path.to_path_buf(),
+                error,
+                included_from: self.map.include_chain(file).into_iter()
+                    .map(|pos| (self.map.path(pos.file).to_path_buf(), pos))
+                    .collect(),
+            })?;
+        self.enter(path)?;
+        let (directives, mut comments) =
+            self.expand(directives, path, context)?;
+        self.stack.pop();
+        comments.extend(end_comments);
+        Ok((directives, comments))
+    }
+
+    fn enter(&mut self, path: &Path) -> Result<(), IncludeError> {
+        if self.stack.iter().any(|p| p == path) {
+            let mut chain = mem::take(&mut self.stack);
+            chain.push(path.to_path_buf());
+            return Err(IncludeError::Cycle { chain });
+        }
+        self.stack.push(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Replaces includes by the contents of the files
+    ///
+    /// Comments of the `include` directives and the ones at the end of the
+    /// included files are attached to the next directive. The comments
+    /// which are not followed by a directive are returned.
+    fn expand(&mut self, directives: Vec<Directive>, file: &Path,
+        context: Option<Context>)
+        -> Result<(Vec<Directive>, Vec<String>), IncludeError>
+    {
+        let mut result = Vec::with_capacity(directives.len());
+        let mut pending = Vec::new();
+        for mut dir in directives {
+            if let Item::Include(ref value) = dir.item {
+                let pattern = value.as_literal()
+                    .ok_or_else(|| IncludeError::Variables {
+                        path: file.to_path_buf(),
+                        position: dir.position,
+                    })?;
+                let pattern = self.map.base.join(pattern);
+                let files = if is_glob(&pattern.to_string_lossy()) {
+                    self.glob(&pattern)?
+                } else {
+                    vec![pattern]
+                };
+                pending.append(&mut dir.comments.leading);
+                pending.extend(dir.comments.trailing.take());
+                for path in files {
+                    let (included, comments) =
+                        self.load(&path, dir.position, context)?;
+                    for mut inc in included {
+                        prepend(&mut inc.comments.leading, &mut pending);
+                        result.push(inc);
+                    }
+                    pending.extend(comments);
+                }
+                continue;
+            }
+            let nested_context = context.and_then(|c| c.nested(&dir.item));
+            if let Some(children) = dir.item.children_mut() {
+                let nested = mem::take(children);
+                let (nested, mut comments) =
+                    self.expand(nested, file, nested_context)?;
+                *children = nested;
+                prepend(&mut dir.comments.block_end, &mut comments);
+            }
+            prepend(&mut dir.comments.leading, &mut pending);
+            result.push(dir);
+        }
+        Ok((result, pending))
+    }
+}
+
+/// Moves `comments` to the start of `target`
+fn prepend(target: &mut Vec<String>, comments: &mut Vec<String>) {
+    if !comments.is_empty() {
+        comments.append(target);
+        mem::swap(target, comments);
+    }
+}
+
+/// Parse config file in main context and expand all `include` directives
+///
+/// Relative include paths are resolved against the directory of the main
+/// config file (as nginx does when it's started with `-c path`). Patterns
+/// containing `*`, `?` or `[` are expanded in alphabetical order and
+/// may match no files at all.
+///
+/// Directives of the included files are checked to be allowed in the
+/// context of the `include` directive, same as the ones written inline.
+pub fn parse_file_with_includes<P, L>(path: P, loader: &L)
+    -> Result<Main, IncludeError>
+    where P: AsRef<Path>, L: Loader,
+{
+    parse_file_with_source_map(path, loader).map(|(main, _)| main)
+}
+
+/// Same as [`parse_file_with_includes`] but also returns the source map
+///
+/// Positions of the directives from included files have their own
+/// [`FileId`] which can be resolved to a path using the source map.
+///
+/// [`parse_file_with_includes`]: fn.parse_file_with_includes.html
+/// [`FileId`]: ../struct.FileId.html
+pub fn parse_file_with_source_map<P, L>(path: P, loader: &L)
+    -> Result<(Main, SourceMap), IncludeError>
+    where P: AsRef<Path>, L: Loader,
+{
+    let path = path.as_ref();
+    let text = loader.read_file(path)
+        .map_err(|error| IncludeError::Io { path: path.to_path_buf(), error })?;
+    let mut main = parse_main(&text)
+        .map_err(|error| IncludeError::Parse {
+            path: path.to_path_buf(),
+            error,
+            included_from: Vec::new(),
+        })?;
+    let mut resolver = Resolver {
+        loader,
+        stack: vec![path.to_path_buf()],
+        map: SourceMap::new(path),
+    };
+    let directives = mem::take(&mut main.directives);
+    let (directives, mut comments) =
+        resolver.expand(directives, path, Some(Context::Main))?;
+    main.directives = directives;
+    prepend(&mut main.end_comments, &mut comments);
+    Ok((main, resolver.map))
+}
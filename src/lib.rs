@@ -14,30 +14,50 @@
 extern crate combine;
 #[cfg(feature="fuzzy_errors")] extern crate strsim;
 #[macro_use] extern crate failure;
+extern crate glob;
 #[macro_use] extern crate matches;
+#[cfg(feature="simulation")] extern crate regex;
 #[cfg(test)] #[macro_use] extern crate pretty_assertions;
 
 pub mod ast;
+pub mod context;
+pub mod cst;
+pub mod diagnostic;
+pub mod include;
 mod display;
 mod error;
 mod format;
 mod grammar;
 mod helpers;
 mod position;
+#[cfg(feature="simulation")] pub mod rewriting;
+#[cfg(feature="simulation")] pub mod routing;
 mod tokenizer;
-mod value;
+mod units;
+pub mod value;
 pub mod visitors;
 
 mod access;
 mod core;
+mod events;
+mod fastcgi;
+mod grpc;
 mod gzip;
 mod headers;
 mod proxy;
 mod rewrite;
+mod scgi;
+mod ssl;
+mod stream;
+mod limits;
 mod log;
+mod main_core;
 mod real_ip;
+mod upstream;
+mod uwsgi;
 
-pub use grammar::{parse_main, parse_directives};
+pub use grammar::{parse_main, parse_directives, parse_directives_in};
+pub use grammar::{parse_main_recovering, parse_directives_recovering};
 pub use format::Style;
-pub use position::Pos;
-pub use error::ParseError;
+pub use position::{Pos, Span, FileId};
+pub use error::{ParseError, ErrorKind};
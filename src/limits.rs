@@ -0,0 +1,149 @@
+use combine::{many, Parser};
+use combine::choice;
+use combine::error::StreamError;
+use combine::easy::Error;
+
+use ast::{self, Item, ErrorLevel, LimitReqDelay, Size};
+use core::error_level;
+use grammar::{value, typed, bool, literal};
+use helpers::{semi, ident, string, prefix};
+use tokenizer::{TokenStream, Token};
+
+
+fn parse_zone<'a>(val: &str)
+    -> Result<(String, Size), Error<Token<'a>, Token<'a>>>
+{
+    let mut pair = val.splitn(2, ':');
+    match (pair.next(), pair.next()) {
+        (Some(name), Some(size)) if !name.is_empty() && !size.is_empty() => {
+            let size = size.parse().map_err(Error::unexpected_message)?;
+            Ok((name.to_string(), size))
+        }
+        _ => Err(Error::unexpected_message(
+            "zone must be specified as zone=name:size")),
+    }
+}
+
+fn status<'a>() -> impl Parser<Output=u32, Input=TokenStream<'a>> {
+    string().and_then(|t| match t.value.parse() {
+        Ok(code @ 400..=599) => Ok(code),
+        _ => Err(Error::unexpected_message(
+            "status must be between 400 and 599")),
+    })
+}
+
+enum ZoneParts {
+    Zone(String, Size),
+    Rate(ast::Rate),
+    Sync,
+}
+
+fn limit_req_zone<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    use self::ZoneParts::*;
+
+    ident("limit_req_zone")
+    .with(value())
+    .and(many::<Vec<_>, _>(choice((
+        prefix("zone=").and_then(parse_zone)
+            .map(|(zone, size)| Zone(zone, size)),
+        prefix("rate=").and_then(|val| {
+            val.parse::<ast::Rate>().map_err(Error::unexpected_message)
+        }).map(Rate),
+        ident("sync").map(|_| Sync),
+    ))))
+    .skip(semi())
+    .and_then(|(key, items)| {
+        let mut zone = None;
+        let mut rate = None;
+        let mut sync = false;
+        for item in items {
+            match item {
+                Zone(name, size) => zone = Some((name, size)),
+                Rate(r) => rate = Some(r),
+                Sync => sync = true,
+            }
+        }
+        match (zone, rate) {
+            (Some((zone, size)), Some(rate)) => {
+                Ok(Item::LimitReqZone(ast::LimitReqZone {
+                    key, zone, size, rate, sync,
+                }))
+            }
+            (None, _) => Err(Error::unexpected_message(
+                "zone must be specified as zone=name:size")),
+            (_, None) => Err(Error::unexpected_message(
+                "rate must be specified")),
+        }
+    })
+}
+
+enum ReqParts {
+    Burst(u32),
+    NoDelay,
+    Delay(u32),
+}
+
+fn limit_req<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    use self::ReqParts::*;
+
+    ident("limit_req")
+    .with(prefix("zone=").map(|v| v.to_string()))
+    .and(many::<Vec<_>, _>(choice((
+        prefix("burst=").and_then(|val| val.parse().map(Burst)),
+        ident("nodelay").map(|_| NoDelay),
+        prefix("delay=").and_then(|val| val.parse().map(Delay)),
+    ))))
+    .skip(semi())
+    .map(|(zone, items)| {
+        let mut req = ast::LimitReq { zone, burst: None, delay: None };
+        for item in items {
+            match item {
+                Burst(v) => req.burst = Some(v),
+                NoDelay => req.delay = Some(LimitReqDelay::NoDelay),
+                Delay(v) => req.delay = Some(LimitReqDelay::Delay(v)),
+            }
+        }
+        Item::LimitReq(req)
+    })
+}
+
+pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    choice((
+        limit_req_zone(),
+        limit_req(),
+        ident("limit_req_status").with(status()).skip(semi())
+            .map(Item::LimitReqStatus),
+        ident("limit_req_log_level")
+            .with(error_level().and_then(|level| match level {
+                | ErrorLevel::Info
+                | ErrorLevel::Notice
+                | ErrorLevel::Warn
+                | ErrorLevel::Error
+                => Ok(level),
+                _ => Err(Error::unexpected_message(
+                    "only info, notice, warn and error levels are allowed")),
+            }))
+            .skip(semi())
+            .map(Item::LimitReqLogLevel),
+        ident("limit_req_dry_run").with(bool()).skip(semi())
+            .map(Item::LimitReqDryRun),
+        ident("limit_conn_zone")
+            .with(value())
+            .and(prefix("zone=").and_then(parse_zone))
+            .skip(semi())
+            .map(|(key, (zone, size))| {
+                Item::LimitConnZone(ast::LimitConnZone { key, zone, size })
+            }),
+        ident("limit_conn")
+            .with(literal())
+            .and(string().and_then(|t| t.value.parse::<u32>()))
+            .skip(semi())
+            .map(|(zone, limit)| Item::LimitConn { zone, limit }),
+        ident("limit_conn_status").with(status()).skip(semi())
+            .map(Item::LimitConnStatus),
+        ident("limit_rate").with(typed()).skip(semi())
+            .map(Item::LimitRate),
+        ident("limit_rate_after").with(typed()).skip(semi())
+            .map(Item::LimitRateAfter),
+    ))
+}
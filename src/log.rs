@@ -7,6 +7,7 @@ use ast::{self, Item};
 use grammar::{value};
 use helpers::{semi, ident, string};
 use tokenizer::{TokenStream};
+use position::advance;
 use value::Value;
 
 
@@ -16,8 +17,8 @@ fn access_log<'a>()
     enum I {
         If(Value),
         Gzip(Option<u8>),
-        Flush(String),
-        Buffer(String),
+        Flush(ast::Duration),
+        Buffer(ast::Size),
     }
 
     ident("access_log")
@@ -28,15 +29,18 @@ fn access_log<'a>()
             .and(many::<Vec<_>, _>(
                 (position(), string()).and_then(|(pos, s)| {
                     if s.value.starts_with("if=") {
-                        Ok(I::If(Value::parse_str(pos, &s.value[3..])?))
+                        Ok(I::If(Value::parse_str(
+                            advance(pos, "if="), &s.value[3..])?))
                     } else if s.value == "gzip" {
                         Ok(I::Gzip(None))
                     } else if s.value.starts_with("gzip=") {
                         Ok(I::Gzip(Some(s.value[5..].parse()?)))
                     } else if s.value.starts_with("buffer=") {
-                        Ok(I::Buffer(s.value[7..].to_string()))
+                        s.value[7..].parse().map(I::Buffer)
+                            .map_err(Error::unexpected_message)
                     } else if s.value.starts_with("flush=") {
-                        Ok(I::Flush(s.value[6..].to_string()))
+                        s.value[6..].parse().map(I::Flush)
+                            .map_err(Error::unexpected_message)
                     } else {
                         Err(Error::unexpected_message(
                             format!("bad access_log param {:?}", s.value)))
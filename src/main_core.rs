@@ -0,0 +1,100 @@
+use combine::{many1, Parser};
+use combine::{choice, optional};
+use combine::error::StreamError;
+use combine::easy::Error;
+
+use ast::{self, Item};
+use grammar::{value, typed, bool, literal};
+use helpers::{semi, ident, string, prefix};
+use tokenizer::{TokenStream};
+
+
+fn worker_processes<'a>()
+    -> impl Parser<Output=Item, Input=TokenStream<'a>>
+{
+    use ast::WorkerProcesses;
+    ident("worker_processes")
+    .with(choice((
+        ident("auto").map(|_| WorkerProcesses::Auto),
+        string().and_then(|s| s.value.parse().map(WorkerProcesses::Exact)),
+    )))
+    .skip(semi())
+    .map(Item::WorkerProcesses)
+}
+
+fn worker_cpu_affinity<'a>()
+    -> impl Parser<Output=Item, Input=TokenStream<'a>>
+{
+    use ast::WorkerCpuAffinity;
+    ident("worker_cpu_affinity")
+    .with(choice((
+        ident("auto").with(optional(literal()))
+            .map(WorkerCpuAffinity::Auto),
+        many1(literal()).map(WorkerCpuAffinity::Masks),
+    )))
+    .skip(semi())
+    .map(Item::WorkerCpuAffinity)
+}
+
+fn thread_pool<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    ident("thread_pool")
+    .with(literal())
+    .and(prefix("threads=").and_then(|val| val.parse::<u32>()))
+    .and(optional(prefix("max_queue=").and_then(|val| val.parse::<u32>())))
+    .skip(semi())
+    .map(|((name, threads), max_queue)| {
+        Item::ThreadPool(ast::ThreadPool { name, threads, max_queue })
+    })
+}
+
+fn env<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    ident("env")
+    .with(literal())
+    .and_then(|text| {
+        let mut pair = text.splitn(2, '=');
+        let name = pair.next().unwrap_or("");
+        if name.is_empty() {
+            return Err(Error::unexpected_message(
+                "environment variable name must not be empty"));
+        }
+        Ok(Item::Env {
+            name: name.to_string(),
+            value: pair.next().map(|v| v.to_string()),
+        })
+    })
+    .skip(semi())
+}
+
+pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    choice((
+        ident("daemon").with(bool()).skip(semi())
+            .map(Item::Daemon),
+        ident("master_process").with(bool()).skip(semi())
+            .map(Item::MasterProcess),
+        worker_processes(),
+        ident("user")
+            .with(literal())
+            .and(optional(literal()))
+            .skip(semi())
+            .map(|(user, group)| Item::User { user, group }),
+        ident("pid").with(value()).skip(semi())
+            .map(Item::Pid),
+        ident("worker_rlimit_nofile").with(string())
+            .and_then(|t| t.value.parse().map(Item::WorkerRlimitNofile))
+            .skip(semi()),
+        worker_cpu_affinity(),
+        ident("worker_priority").with(string())
+            .and_then(|t| t.value.parse().map(Item::WorkerPriority))
+            .skip(semi()),
+        env(),
+        ident("load_module").with(value()).skip(semi())
+            .map(Item::LoadModule),
+        ident("pcre_jit").with(bool()).skip(semi())
+            .map(Item::PcreJit),
+        thread_pool(),
+        ident("timer_resolution").with(typed()).skip(semi())
+            .map(Item::TimerResolution),
+        ident("lock_file").with(value()).skip(semi())
+            .map(Item::LockFile),
+    ))
+}
@@ -1,17 +1,99 @@
 use std::fmt;
+use std::ops::Range;
+
+/// Identifier of the file a position belongs to
+///
+/// Files parsed directly get `FileId::default()`. When includes are
+/// expanded, every included file gets its own identifier which can be
+/// looked up in the [`SourceMap`](include/struct.SourceMap.html).
+#[derive(PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Default, Hash)]
+pub struct FileId(pub(crate) u32);
 
 /// Original position of element in source code
-#[derive(PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Default)]
+///
+/// The structure is non-exhaustive as more fields may be added, use
+/// `Pos::new` to construct one outside of the parser.
+#[derive(PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Default, Hash)]
+#[non_exhaustive]
 pub struct Pos {
+    /// File the element comes from
+    pub file: FileId,
     /// One-based line number
     pub line: usize,
     /// One-based column number
     pub column: usize,
+    /// Byte offset from the start of the file
+    pub offset: usize,
+}
+
+/// Range of the source text between two positions
+///
+/// The end is exclusive, i.e. it's the position just after the last
+/// character of the element.
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy, Default, Hash)]
+pub struct Span {
+    /// Position of the first character
+    pub start: Pos,
+    /// Position after the last character
+    pub end: Pos,
+}
+
+impl FileId {
+    /// Index of the file in the order files were loaded (main file is zero)
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Pos {
+    /// Position at the `line` and `column` (both one-based) which is
+    /// `offset` bytes from the start of the `file`
+    pub fn new(file: FileId, line: usize, column: usize, offset: usize)
+        -> Pos
+    {
+        Pos { file, line, column, offset }
+    }
+}
+
+impl Span {
+    /// Byte range of the element in the source text
+    ///
+    /// Can be used to slice the text the element was parsed from.
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
+/// Position after the `text` which starts at `pos`
+pub(crate) fn advance(mut pos: Pos, text: &str) -> Pos {
+    for ch in text.chars() {
+        if ch == '\n' {
+            pos.line += 1;
+            pos.column = 1;
+        } else if ch == '\t' {
+            // same as tokenizer does
+            pos.column += 8;
+        } else {
+            pos.column += 1;
+        }
+        pos.offset += ch.len_utf8();
+    }
+    pos
+}
+
+impl fmt::Debug for FileId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileId({})", self.0)
+    }
 }
 
 impl fmt::Debug for Pos {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Pos({}:{})", self.line, self.column)
+        if self.file == FileId::default() {
+            write!(f, "Pos({}:{})", self.line, self.column)
+        } else {
+            write!(f, "Pos(#{} {}:{})", self.file.0, self.line, self.column)
+        }
     }
 }
 
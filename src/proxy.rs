@@ -6,9 +6,83 @@ use combine::easy::Error;
 use ast::{self, Item};
 use helpers::{semi, ident, string};
 use tokenizer::TokenStream;
-use grammar::{value, bool, Code};
+use grammar::{value, typed, bool, Code};
 
 
+/// Arguments of `*_cache_valid` directives: optional codes and time
+pub fn cache_valid<'a>()
+    -> impl Parser<Output=ast::ProxyCacheValid, Input=TokenStream<'a>>
+{
+    many1(value())
+    .and_then(|mut v: Vec<_>| {
+        use ast::ProxyCacheValid::*;
+        use value::Part::*;
+        let time = v.pop().unwrap();
+        if v.len() == 0 {
+            return Ok(Normal(time));
+        }
+        let mut codes = Vec::new();
+        let items = v.len();
+        for item in v {
+            match &item.data[..] {
+                [Literal(x)] if x == "any" => {
+                    if items == 1 {
+                        return Ok(Any(time));
+                    } else {
+                        return Err(Error::unexpected_message(
+                            "`any` must be sole argument before time. \
+                             It's not allowed to combine `any` and \
+                             other codes"));
+                    }
+                }
+                [Literal(x)] => {
+                    match Code::parse(x) {
+                        Ok(code) => {
+                            codes.push(code.as_code())
+                        }
+                        Err(_) => {
+                            return Err(Error::unexpected_message(
+                                format!("invalid http code {:?}", x)));
+                        }
+                    }
+                }
+                _ => {
+                    return Err(Error::unexpected_message(
+                        "variables aren't allowed in list of codes"));
+                }
+            }
+        }
+        return Ok(Specific(codes, time));
+    })
+}
+
+/// Arguments of `*_next_upstream` directives
+/// Flags of the `*_next_upstream` directives, `error` is the message for
+/// an unknown flag
+pub fn next_upstream_flags<'a>(error: &'static str)
+    -> impl Parser<Output=Vec<ast::ProxyNextUpstreamFlag>,
+                   Input=TokenStream<'a>>
+{
+    many1(string().and_then(move |v| {
+        use ast::ProxyNextUpstreamFlag::*;
+        match v.value {
+            "error" => Ok(Error),
+            "timeout" => Ok(Timeout),
+            "invalid_header" => Ok(InvalidHeader),
+            "http_500" => Ok(Http500),
+            "http_502" => Ok(Http502),
+            "http_503" => Ok(Http503),
+            "http_504" => Ok(Http504),
+            "http_403" => Ok(Http403),
+            "http_404" => Ok(Http404),
+            "http_429" => Ok(Http429),
+            "non_idempotent" => Ok(NonIdempotent),
+            "off" => Ok(Off),
+            _ => Err(::combine::easy::Error::unexpected_message(error)),
+        }
+    }))
+}
+
 pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
     choice((
         ident("proxy_pass").with(value()).skip(semi())
@@ -22,52 +96,11 @@ pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
             .map(Item::ProxyCache),
         ident("proxy_cache_key").with(value()).skip(semi())
             .map(Item::ProxyCacheKey),
-        ident("proxy_cache_valid")
-            .with(many1(value()))
-            .and_then(|mut v: Vec<_>| {
-                use ast::ProxyCacheValid::*;
-                use value::Item::*;
-                let time = v.pop().unwrap();
-                if v.len() == 0 {
-                    return Ok(Normal(time));
-                }
-                let mut codes = Vec::new();
-                let items = v.len();
-                for item in v {
-                    match &item.data[..] {
-                        [Literal(x)] if x == "any" => {
-                            if items == 1 {
-                                return Ok(Any(time));
-                            } else {
-                                return Err(Error::unexpected_message(
-                                    "`any` must be sole argument before time. \
-                                     It's not allowed to combine `any` and \
-                                     other codes"));
-                            }
-                        }
-                        [Literal(x)] => {
-                            match Code::parse(x) {
-                                Ok(code) => {
-                                    codes.push(code.as_code())
-                                }
-                                Err(_) => {
-                                    return Err(Error::unexpected_message(
-                                        format!("invalid http code {:?}", x)));
-                                }
-                            }
-                        }
-                        _ => {
-                            return Err(Error::unexpected_message(
-                                "variables aren't allowed in list of codes"));
-                        }
-                    }
-                }
-                return Ok(Specific(codes, time));
-            })
-            .skip(semi()).map(Item::ProxyCacheValid),
-        ident("proxy_read_timeout").with(value()).skip(semi())
+        ident("proxy_cache_valid").with(cache_valid()).skip(semi())
+            .map(Item::ProxyCacheValid),
+        ident("proxy_read_timeout").with(typed()).skip(semi())
             .map(Item::ProxyReadTimeout),
-        ident("proxy_connect_timeout").with(value()).skip(semi())
+        ident("proxy_connect_timeout").with(typed()).skip(semi())
             .map(Item::ProxyConnectTimeout),
         ident("proxy_hide_header").with(value()).skip(semi())
             .map(Item::ProxyHideHeader),
@@ -98,30 +131,12 @@ pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
             .skip(semi())
             .map(Item::ProxyHttpVersion),
         ident("proxy_next_upstream")
-            .with(many1(string().and_then(|v| {
-                use ast::ProxyNextUpstreamFlag::*;
-                match v.value {
-                    "error" => Ok(Error),
-                    "timeout" => Ok(Timeout),
-                    "invalid_header" => Ok(InvalidHeader),
-                    "http_500" => Ok(Http500),
-                    "http_502" => Ok(Http502),
-                    "http_503" => Ok(Http503),
-                    "http_504" => Ok(Http504),
-                    "http_403" => Ok(Http403),
-                    "http_404" => Ok(Http404),
-                    "http_429" => Ok(Http429),
-                    "non_idempotent" => Ok(NonIdempotent),
-                    "off" => Ok(Off),
-                    _ => Err(::combine::easy::Error::unexpected_message(
-                        "invalid proxy upstream flag")),
-                }
-            })))
+            .with(next_upstream_flags("invalid proxy upstream flag"))
             .skip(semi())
             .map(Item::ProxyNextUpstream),
         ident("proxy_next_upstream_tries").with(value()).skip(semi())
             .map(Item::ProxyNextUpstreamTries),
-        ident("proxy_next_upstream_timeout").with(value()).skip(semi())
+        ident("proxy_next_upstream_timeout").with(typed()).skip(semi())
             .map(Item::ProxyNextUpstreamTimeout),
     ))
 }
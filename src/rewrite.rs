@@ -59,7 +59,7 @@ fn return_directive<'a>()
     -> impl Parser<Output=Item, Input=TokenStream<'a>>
 {
     use ast::Return::*;
-    use value::Item::*;
+    use value::Part::*;
 
     fn lit<'a, 'x>(val: &'a Value) -> Result<&'a str, Error<Token<'x>, Token<'x>>> {
         if val.data.is_empty() {
@@ -153,16 +153,19 @@ fn strip_close_paren<'a>(v: &'_ mut Vec<&'a str>)
     Ok(())
 }
 
-fn parse_unary<'a>(mut v: Vec<&str>, position: Pos)
+fn parse_unary<'a>(v: Vec<&str>, position: Pos)
     -> Result<ast::IfCondition, Error<Token<'a>, Token<'a>>>
 {
     use ast::IfCondition::*;
 
-    let oper = v.remove(0);
-    let right = Value::parse_str(position, v.remove(0))?;
-    if v.len() > 0 {
-        return Err(Error::unexpected_message("extra argument to condition"));
-    }
+    let (oper, right) = match v[..] {
+        [oper, right] => (oper, right),
+        [_] => return Err(Error::unexpected_message(
+                "missing argument to condition")),
+        _ => return Err(Error::unexpected_message(
+                "extra argument to condition")),
+    };
+    let right = Value::parse_str(position, right)?;
     match oper {
         "-d" => return Ok(DirExists(right)),
         "!-d" => return Ok(DirNotExists(right)),
@@ -238,6 +241,7 @@ pub fn directives<'a>()
         rewrite(),
         set(),
         return_directive(),
+        ident("break").skip(semi()).map(|_| Item::Break),
         if_directive(),
     ))
 }
@@ -0,0 +1,662 @@
+//! Dry run of the rewrite module directives
+//!
+//! Executes `rewrite`, `return`, `set`, `if` and `break` the way nginx
+//! does while processing a request, including the search of a new
+//! location after the URI is rewritten. Other directives are ignored.
+//! Conditions of `if` can also be checked on their own by
+//! [`check_condition`](fn.check_condition.html).
+//!
+//! This module is only available with the `simulation` feature (enabled by
+//! default).
+//!
+//! ```rust
+//! # use nginx_config::parse_directives_in;
+//! # use nginx_config::context::Context;
+//! use nginx_config::rewriting::{evaluate, Request, Outcome};
+//!
+//! let directives = parse_directives_in("
+//!     rewrite ^/old/(.*)$ /new/$1 permanent;
+//! ", Context::Server).unwrap();
+//! let result = evaluate(&directives, &Request::new("/old/page?a=1"))
+//!     .unwrap();
+//! assert_eq!(result.outcome, Outcome::Redirect {
+//!     code: 301,
+//!     url: "/new/page?a=1".into(),
+//! });
+//! ```
+use std::collections::HashMap;
+use std::fs;
+use std::hash::BuildHasher;
+
+use ast::{Directive, IfCondition, Item, Return, Rewrite, RewriteFlag};
+use routing::{compile, find_location, LocationMatch, RegexError};
+use value::{Part, Value};
+
+
+/// Maximum number of times the location is searched again after `rewrite`
+///
+/// Same as `NGX_HTTP_MAX_URI_CHANGES` in nginx.
+pub const MAX_URI_CHANGES: usize = 10;
+
+/// Request to evaluate the rewrite rules for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// Normalized URI (path) of the request
+    pub uri: String,
+    /// Query string without the question mark
+    pub args: Option<String>,
+    /// Values of other variables, like `host` or `http_user_agent`
+    ///
+    /// Names are without the dollar sign. Variables which are not here are
+    /// treated as empty.
+    pub variables: HashMap<String, String>,
+}
+
+/// How processing of the request ends
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// No `return` or redirect, the request is served by the location
+    Serve,
+    /// Redirect by `return` or `rewrite`
+    Redirect {
+        /// Status code (301, 302, 303, 307 or 308)
+        code: u32,
+        /// Value for the `Location` header
+        url: String,
+    },
+    /// Response by `return` with a non-redirect code
+    Response {
+        /// Status code
+        code: u32,
+        /// Response body, if specified
+        text: Option<String>,
+    },
+}
+
+/// Result of executing the rewrite directives
+#[derive(Debug, Clone)]
+pub struct Evaluation<'a> {
+    /// URI after all rewrites
+    pub uri: String,
+    /// Query string after all rewrites
+    pub args: Option<String>,
+    /// Location which handles the request
+    ///
+    /// This is `None` if no location matches the URI or if the request is
+    /// finished on the server level (by `return` or redirect).
+    pub location: Option<LocationMatch<'a>>,
+    /// Response or redirect, if any
+    pub outcome: Outcome,
+    /// Flag of the `rewrite` which stopped processing
+    ///
+    /// The `break` directive is reported as `RewriteFlag::Break` too.
+    pub flag: Option<RewriteFlag>,
+    /// Number of times the location was searched again after a rewrite
+    pub uri_changes: usize,
+    /// Variables including the ones changed by `set`
+    pub variables: HashMap<String, String>,
+}
+
+/// Error evaluating rewrite rules
+#[derive(Fail, Debug)]
+pub enum RewriteError {
+    /// Regular expression can't be simulated
+    #[fail(display="{}", _0)]
+    Regex(#[cause] RegexError),
+    /// The URI was rewritten more than `MAX_URI_CHANGES` times
+    ///
+    /// Nginx responds with 500 Internal Server Error in this case.
+    #[fail(display="rewrite or internal redirection cycle \
+                    while processing {:?}", _0)]
+    Cycle(String),
+}
+
+impl From<RegexError> for RewriteError {
+    fn from(e: RegexError) -> RewriteError {
+        RewriteError::Regex(e)
+    }
+}
+
+/// Source of variable values
+///
+/// Implemented for hash maps and for closures `Fn(&str) -> Option<String>`.
+pub trait Variables {
+    /// Returns the value of the variable, the name is without dollar sign
+    ///
+    /// Unknown variables are treated as empty strings.
+    fn get(&self, name: &str) -> Option<String>;
+}
+
+/// Filesystem checks for `-f`, `-d`, `-e` and `-x` conditions of `if`
+pub trait FileSystem {
+    /// Returns `true` if the path is a regular file (`-f`)
+    fn is_file(&self, path: &str) -> bool;
+    /// Returns `true` if the path is a directory (`-d`)
+    fn is_dir(&self, path: &str) -> bool;
+    /// Returns `true` if a file, a directory or a link exists (`-e`)
+    fn exists(&self, path: &str) -> bool;
+    /// Returns `true` if the path is executable (`-x`)
+    fn is_executable(&self, path: &str) -> bool;
+}
+
+/// Filesystem of this machine
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealFileSystem;
+
+/// Filesystem in memory, for tests
+///
+/// Parent directories of the added paths are created automatically.
+///
+/// ```rust
+/// use nginx_config::rewriting::{FakeFileSystem, FileSystem};
+///
+/// let fs = FakeFileSystem::new()
+///     .file("/var/www/index.html")
+///     .executable("/usr/bin/php");
+/// assert!(fs.is_file("/var/www/index.html"));
+/// assert!(fs.is_dir("/var/www/"));
+/// assert!(fs.is_executable("/usr/bin/php"));
+/// assert!(!fs.exists("/var/www/missing.html"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FakeFileSystem {
+    entries: HashMap<String, Entry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Entry {
+    File { executable: bool },
+    Dir,
+}
+
+/// Result of checking the condition of `if`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionResult {
+    /// Whether the condition holds and the block of `if` is executed
+    pub holds: bool,
+    /// Groups captured by the regular expression (`$0`, `$1`, ...)
+    ///
+    /// This is `Some` if the regex matched and has groups, even if the
+    /// condition is negated (`!~`). Captures replace the ones visible to
+    /// the subsequent `set` and `rewrite` directives (`$1`), otherwise the
+    /// previous captures are kept.
+    pub captures: Option<Vec<Option<String>>>,
+}
+
+/// Why executing the directives stopped
+enum Stop {
+    /// All directives are executed
+    End,
+    /// Stopped by the `rewrite` flag or the `break` directive
+    Flag(RewriteFlag),
+    /// Stopped by `return` or a redirect
+    Finish(Outcome, Option<RewriteFlag>),
+}
+
+struct State<'f> {
+    fs: &'f dyn FileSystem,
+    uri: String,
+    args: Option<String>,
+    variables: HashMap<String, String>,
+    captures: Vec<Option<String>>,
+    uri_changed: bool,
+}
+
+impl Request {
+    /// Makes a request from the URI with an optional query string
+    pub fn new(uri: &str) -> Request {
+        let (uri, args) = match uri.find('?') {
+            Some(idx) => (&uri[..idx], Some(uri[idx+1..].to_string())),
+            None => (uri, None),
+        };
+        Request {
+            uri: uri.to_string(),
+            args,
+            variables: HashMap::new(),
+        }
+    }
+    /// Adds a variable for the request
+    pub fn variable(mut self, name: &str, value: &str) -> Request {
+        self.variables.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+/// Executes rewrite directives of the server and locations for request
+///
+/// The `directives` are the contents of a `server` block. First, the
+/// directives on the server level are executed, then the location is
+/// found by [`find_location`](../routing/fn.find_location.html) and its
+/// directives are executed. If the URI was changed by `rewrite` (without
+/// the `break` flag), the location is searched again, up to
+/// `MAX_URI_CHANGES` times.
+///
+/// Besides the `variables` of the request, the `$uri`, `$args`,
+/// `$query_string`, `$is_args` and `$request_uri` variables and the
+/// captures of regular expressions (`$1`) are provided. The file checks in
+/// `if` (`-f`, `-d`, `-e`, `-x`) are done on the real filesystem, use
+/// [`evaluate_with`](fn.evaluate_with.html) to override that.
+pub fn evaluate<'a>(directives: &'a [Directive], request: &Request)
+    -> Result<Evaluation<'a>, RewriteError>
+{
+    evaluate_with(directives, request, &RealFileSystem)
+}
+
+/// Same as [`evaluate`](fn.evaluate.html) but with custom filesystem
+pub fn evaluate_with<'a>(directives: &'a [Directive], request: &Request,
+    fs: &dyn FileSystem)
+    -> Result<Evaluation<'a>, RewriteError>
+{
+    let mut variables = request.variables.clone();
+    variables.insert("request_uri".into(), match request.args {
+        Some(ref args) => format!("{}?{}", request.uri, args),
+        None => request.uri.clone(),
+    });
+    let mut state = State {
+        fs,
+        uri: request.uri.clone(),
+        args: request.args.clone(),
+        variables,
+        captures: Vec::new(),
+        uri_changed: false,
+    };
+    let mut flag = match execute(directives, &mut state)? {
+        Stop::Finish(outcome, flag) => {
+            return Ok(state.finish(None, outcome, flag, 0));
+        }
+        Stop::Flag(flag) => Some(flag),
+        Stop::End => None,
+    };
+    let mut uri_changes = 0;
+    loop {
+        let location = match find_location(directives, &state.uri)? {
+            Some(location) => location,
+            None => {
+                return Ok(state.finish(
+                    None, Outcome::Serve, flag, uri_changes));
+            }
+        };
+        if location.captures.len() > 1 {
+            state.captures = location.captures.clone();
+        }
+        state.uri_changed = false;
+        match execute(&location.location.directives, &mut state)? {
+            Stop::Finish(outcome, flag) => {
+                return Ok(state.finish(
+                    Some(location), outcome, flag, uri_changes));
+            }
+            Stop::Flag(f) => flag = Some(f),
+            Stop::End => {}
+        }
+        if !state.uri_changed {
+            return Ok(state.finish(
+                Some(location), Outcome::Serve, flag, uri_changes));
+        }
+        uri_changes += 1;
+        if uri_changes > MAX_URI_CHANGES {
+            return Err(RewriteError::Cycle(state.uri));
+        }
+    }
+}
+
+impl<'f> State<'f> {
+    fn finish(self, location: Option<LocationMatch>, outcome: Outcome,
+        flag: Option<RewriteFlag>, uri_changes: usize)
+        -> Evaluation
+    {
+        Evaluation {
+            uri: self.uri,
+            args: self.args,
+            location,
+            outcome,
+            flag,
+            uri_changes,
+            variables: self.variables,
+        }
+    }
+
+    /// Matches the regex and remembers captures on success
+    ///
+    /// As in nginx, captures are kept intact if regex has no groups.
+    fn regex_match(&mut self, pattern: &str, case_insensitive: bool,
+        text: &str)
+        -> Result<bool, RegexError>
+    {
+        let regex = compile(unquote(pattern), case_insensitive)?;
+        match regex.captures(text) {
+            Some(caps) if caps.len() > 1 => {
+                self.captures = caps.iter()
+                    .map(|m| m.map(|m| m.as_str().to_string()))
+                    .collect();
+                Ok(true)
+            }
+            Some(_) => Ok(true),
+            None => Ok(false),
+        }
+    }
+
+    fn condition(&mut self, condition: &IfCondition)
+        -> Result<bool, RegexError>
+    {
+        let result = check_condition(condition, self, self.fs)?;
+        if let Some(captures) = result.captures {
+            self.captures = captures;
+        }
+        Ok(result.holds)
+    }
+
+    fn rewrite(&mut self, rw: &Rewrite) -> Result<Option<Stop>, RegexError> {
+        use ast::RewriteFlag::*;
+
+        let uri = self.uri.clone();
+        if !self.regex_match(&rw.regex, false, &uri)? {
+            return Ok(None);
+        }
+        let mut target = expand(&rw.replacement, self);
+        let add_args = !target.ends_with('?');
+        if !add_args {
+            target.pop();
+        }
+        let redirect = match rw.flag {
+            Some(Redirect) | Some(Permanent) => true,
+            _ => target.starts_with("http://")
+                || target.starts_with("https://")
+                || matches!(rw.replacement.parts().next(),
+                            Some(Part::Variable(ref v)) if v == "scheme"),
+        };
+        if redirect {
+            let code = if rw.flag == Some(Permanent) { 301 } else { 302 };
+            if add_args {
+                if let Some(ref args) = self.args {
+                    target.push(if target.contains('?') { '&' } else { '?' });
+                    target.push_str(args);
+                }
+            }
+            let flag = rw.flag.clone().or(Some(Redirect));
+            return Ok(Some(Stop::Finish(
+                Outcome::Redirect { code, url: target }, flag)));
+        }
+        match target.find('?') {
+            Some(idx) => {
+                let mut args = target[idx+1..].to_string();
+                if add_args {
+                    if let Some(ref old) = self.args {
+                        args.push('&');
+                        args.push_str(old);
+                    }
+                }
+                self.args = Some(args);
+                target.truncate(idx);
+            }
+            None if !add_args => self.args = None,
+            None => {}
+        }
+        self.uri = target;
+        match rw.flag {
+            Some(Break) => {
+                self.uri_changed = false;
+                Ok(Some(Stop::Flag(Break)))
+            }
+            Some(Last) => {
+                self.uri_changed = true;
+                Ok(Some(Stop::Flag(Last)))
+            }
+            _ => {
+                self.uri_changed = true;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<'f> Variables for State<'f> {
+    fn get(&self, name: &str) -> Option<String> {
+        if let Ok(idx) = name.parse::<usize>() {
+            return self.captures.get(idx).cloned().and_then(|x| x);
+        }
+        match name {
+            "uri" | "document_uri" => Some(self.uri.clone()),
+            "args" | "query_string" => self.args.clone(),
+            "is_args" if self.args.is_some() => Some("?".into()),
+            "is_args" => None,
+            _ => self.variables.get(name).cloned(),
+        }
+    }
+}
+
+impl<S: BuildHasher> Variables for HashMap<String, String, S> {
+    fn get(&self, name: &str) -> Option<String> {
+        HashMap::get(self, name).cloned()
+    }
+}
+
+impl<F: Fn(&str) -> Option<String>> Variables for F {
+    fn get(&self, name: &str) -> Option<String> {
+        self(name)
+    }
+}
+
+impl FileSystem for RealFileSystem {
+    fn is_file(&self, path: &str) -> bool {
+        fs::metadata(path).map(|m| m.is_file()).unwrap_or(false)
+    }
+    fn is_dir(&self, path: &str) -> bool {
+        fs::metadata(path).map(|m| m.is_dir()).unwrap_or(false)
+    }
+    fn exists(&self, path: &str) -> bool {
+        fs::symlink_metadata(path).is_ok()
+    }
+    #[cfg(unix)]
+    fn is_executable(&self, path: &str) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+        // nginx checks only the owner's bit
+        fs::metadata(path)
+            .map(|m| m.permissions().mode() & 0o100 != 0)
+            .unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    fn is_executable(&self, path: &str) -> bool {
+        self.exists(path)
+    }
+}
+
+impl FakeFileSystem {
+    /// Makes an empty filesystem (only the root directory exists)
+    pub fn new() -> FakeFileSystem {
+        FakeFileSystem::default().dir("/")
+    }
+    /// Adds a regular file
+    pub fn file(self, path: &str) -> FakeFileSystem {
+        self.add(path, Entry::File { executable: false })
+    }
+    /// Adds an executable file
+    pub fn executable(self, path: &str) -> FakeFileSystem {
+        self.add(path, Entry::File { executable: true })
+    }
+    /// Adds a directory
+    pub fn dir(self, path: &str) -> FakeFileSystem {
+        self.add(path, Entry::Dir)
+    }
+    fn add(mut self, path: &str, entry: Entry) -> FakeFileSystem {
+        let path = normalize_path(path);
+        let mut parent = path;
+        while let Some(idx) = parent.rfind('/') {
+            parent = &parent[..idx];
+            let dir = if parent.is_empty() { "/" } else { parent };
+            self.entries.insert(dir.to_string(), Entry::Dir);
+        }
+        self.entries.insert(path.to_string(), entry);
+        self
+    }
+    fn entry(&self, path: &str) -> Option<Entry> {
+        self.entries.get(normalize_path(path)).cloned()
+    }
+}
+
+impl FileSystem for FakeFileSystem {
+    fn is_file(&self, path: &str) -> bool {
+        matches!(self.entry(path), Some(Entry::File { .. }))
+    }
+    fn is_dir(&self, path: &str) -> bool {
+        self.entry(path) == Some(Entry::Dir)
+    }
+    fn exists(&self, path: &str) -> bool {
+        self.entry(path).is_some()
+    }
+    fn is_executable(&self, path: &str) -> bool {
+        match self.entry(path) {
+            Some(Entry::File { executable }) => executable,
+            Some(Entry::Dir) => true,
+            None => false,
+        }
+    }
+}
+
+/// Checks the condition of `if`
+///
+/// Values are expanded using the `variables` (including the captures like
+/// `$1` if the caller provides them). The right side of `=` and `!=` may
+/// contain variables too. A variable is true if it's neither empty nor
+/// `0`. The file checks (`-f`, `-d`, `-e`, `-x`) are done with `fs`.
+///
+/// ```rust
+/// # use std::collections::HashMap;
+/// # use nginx_config::ast::IfCondition;
+/// use nginx_config::rewriting::{check_condition, FakeFileSystem};
+///
+/// let mut vars = HashMap::new();
+/// vars.insert("uri".to_string(), "/img/logo.PNG".to_string());
+/// let cond = IfCondition::RegEq("$uri".parse().unwrap(),
+///                               r"\.(png|gif)$".into(), false);
+/// let result = check_condition(&cond, &vars, &FakeFileSystem::new())
+///     .unwrap();
+/// assert!(result.holds);
+/// assert_eq!(result.captures.unwrap()[1].as_deref(), Some("PNG"));
+/// ```
+pub fn check_condition<V, F>(condition: &IfCondition, variables: &V, fs: &F)
+    -> Result<ConditionResult, RegexError>
+    where V: Variables + ?Sized,
+          F: FileSystem + ?Sized,
+{
+    use ast::IfCondition::*;
+    let mut captures = None;
+    let holds = match *condition {
+        NonEmpty(ref v) => {
+            let v = expand(v, variables);
+            !v.is_empty() && v != "0"
+        }
+        Eq(ref v, ref s) => expand(v, variables) == operand(s, variables),
+        Neq(ref v, ref s) => expand(v, variables) != operand(s, variables),
+        RegEq(ref v, ref re, case_sensitive)
+        | RegNeq(ref v, ref re, case_sensitive) => {
+            let regex = compile(unquote(re), !case_sensitive)?;
+            let text = expand(v, variables);
+            let matched = match regex.captures(&text) {
+                Some(caps) => {
+                    if caps.len() > 1 {
+                        captures = Some(caps.iter()
+                            .map(|m| m.map(|m| m.as_str().to_string()))
+                            .collect());
+                    }
+                    true
+                }
+                None => false,
+            };
+            matched == matches!(*condition, RegEq(..))
+        }
+        Exists(ref v) => fs.exists(&expand(v, variables)),
+        NotExists(ref v) => !fs.exists(&expand(v, variables)),
+        FileExists(ref v) => fs.is_file(&expand(v, variables)),
+        FileNotExists(ref v) => !fs.is_file(&expand(v, variables)),
+        DirExists(ref v) => fs.is_dir(&expand(v, variables)),
+        DirNotExists(ref v) => !fs.is_dir(&expand(v, variables)),
+        Executable(ref v) => fs.is_executable(&expand(v, variables)),
+        NotExecutable(ref v) => !fs.is_executable(&expand(v, variables)),
+    };
+    Ok(ConditionResult { holds, captures })
+}
+
+fn expand<V: Variables + ?Sized>(value: &Value, variables: &V) -> String {
+    let mut result = String::new();
+    for part in value.parts() {
+        match *part {
+            Part::Literal(ref text) => result.push_str(text),
+            Part::Variable(ref name) => {
+                if let Some(value) = variables.get(name) {
+                    result.push_str(&value);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Right side of `=` and `!=` in `if`, it may contain variables
+fn operand<V: Variables + ?Sized>(text: &str, variables: &V) -> String {
+    match text.parse::<Value>() {
+        Ok(value) => expand(&value, variables),
+        Err(_) => unquote(text).to_string(),
+    }
+}
+
+/// Strips trailing slashes, the same path with and without them is one
+fn normalize_path(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" if path.starts_with('/') => "/",
+        path => path,
+    }
+}
+
+fn execute(directives: &[Directive], state: &mut State)
+    -> Result<Stop, RegexError>
+{
+    for dir in directives {
+        match dir.item {
+            Item::Rewrite(ref rw) => {
+                if let Some(stop) = state.rewrite(rw)? {
+                    return Ok(stop);
+                }
+            }
+            Item::Return(Return::Redirect { code, ref url }) => {
+                return Ok(Stop::Finish(Outcome::Redirect {
+                    code: code.unwrap_or(302),
+                    url: expand(url, state),
+                }, None));
+            }
+            Item::Return(Return::Text { code, ref text }) => {
+                return Ok(Stop::Finish(Outcome::Response {
+                    code,
+                    text: text.as_ref().map(|t| expand(t, state)),
+                }, None));
+            }
+            Item::Set { ref variable, ref value } => {
+                let value = expand(value, state);
+                state.variables.insert(variable.clone(), value);
+            }
+            Item::Break => {
+                // same as `ngx_http_script_break_code`
+                state.uri_changed = false;
+                return Ok(Stop::Flag(RewriteFlag::Break));
+            }
+            Item::If(ref cond) if state.condition(&cond.condition)? => {
+                match execute(&cond.directives, state)? {
+                    Stop::End => {}
+                    stop => return Ok(stop),
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(Stop::End)
+}
+
+/// Strips quotes from the regex or string in `rewrite` or `if`
+fn unquote(text: &str) -> &str {
+    let bytes = text.as_bytes();
+    match bytes.first() {
+        Some(&q) if bytes.len() >= 2 && (q == b'"' || q == b'\'')
+            && bytes[bytes.len()-1] == q
+        => &text[1..text.len()-1],
+        _ => text,
+    }
+}
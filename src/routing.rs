@@ -0,0 +1,423 @@
+//! Simulation of how nginx routes a request
+//!
+//! [`find_server`] picks the `server` block by the address and host name
+//! and [`find_location`] picks the `location` inside it.
+//!
+//! This module is only available with the `simulation` feature (enabled by
+//! default).
+//!
+//! ```rust
+//! # use nginx_config::parse_directives_in;
+//! # use nginx_config::context::Context;
+//! # use nginx_config::ast::LocationPattern;
+//! use nginx_config::routing::find_location;
+//!
+//! let directives = parse_directives_in("
+//!     location / {}
+//!     location /images/ {}
+//!     location ~ \\.png$ {}
+//! ", Context::Server).unwrap();
+//! let found = find_location(&directives, "/images/logo.png")
+//!     .unwrap().unwrap();
+//! assert_eq!(found.location.pattern,
+//!            LocationPattern::Regex("\\.png$".into()));
+//! ```
+use std::net::{IpAddr, SocketAddr};
+
+use regex::{Regex, RegexBuilder};
+
+use ast::{Address, Directive, Http, Item, Listen, Location, LocationPattern};
+use ast::{Server, ServerName};
+
+
+/// Regular expression in the config isn't supported by the simulator
+///
+/// Nginx uses PCRE while the simulator uses the `regex` crate, so some
+/// constructs like look-around and back references can't be simulated.
+#[derive(Fail, Debug, Clone, PartialEq, Eq)]
+#[fail(display="can't compile regex {:?}: {}", pattern, message)]
+pub struct RegexError {
+    /// The regular expression as written in the config
+    pub pattern: String,
+    /// Description of the error
+    pub message: String,
+}
+
+/// Location that handles a request
+#[derive(Debug, Clone)]
+pub struct LocationMatch<'a> {
+    /// The innermost location which matched
+    pub location: &'a Location,
+    /// Locations containing the matched one, outermost first
+    pub parents: Vec<&'a Location>,
+    /// Groups captured by the regex location (`$0`, `$1`, ...)
+    ///
+    /// Empty when the location is not a regular expression.
+    pub captures: Vec<Option<String>>,
+}
+
+/// Server block that handles a request
+#[derive(Debug, Clone)]
+pub struct ServerMatch<'a> {
+    /// The selected server
+    pub server: &'a Server,
+    /// Whether the server was found by name, `false` means that no server
+    /// name matched and the default server for the address is used
+    pub by_name: bool,
+    /// Groups captured by the regex server name (`$0`, `$1`, ...)
+    ///
+    /// Empty when the name is not a regular expression.
+    pub captures: Vec<Option<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    NotFound,
+    /// Prefix location found, regular expressions may override it
+    Prefix,
+    /// Exact or regex match, search is finished
+    Done,
+}
+
+pub(crate) fn compile(pattern: &str, case_insensitive: bool)
+    -> Result<Regex, RegexError>
+{
+    RegexBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .build()
+        .map_err(|e| RegexError {
+            pattern: pattern.to_string(),
+            message: e.to_string(),
+        })
+}
+
+/// Finds a location nginx selects for the URI
+///
+/// The `directives` are the contents of a `server` block. The URI must be
+/// normalized (i.e. without the query string, with `%XX` sequences
+/// decoded and `.`/`..` segments resolved), that's what nginx matches
+/// locations against.
+///
+/// The algorithm is the same as in nginx:
+///
+/// 1. If there is an exact (`=`) location equal to the URI, it's used.
+/// 2. Otherwise the longest prefix location is found, and the same search
+///    is repeated in the locations nested in it.
+/// 3. Unless the longest prefix location is marked with `^~`, regular
+///    expression locations (`~` and `~*`) are checked in the order they
+///    appear in the config, the first matching one is used.
+/// 4. If no regular expression matches, the prefix location is used.
+///
+/// Named locations (`@name`) are never selected by this function. Returns
+/// `Ok(None)` if no location matches, in which case nginx handles the
+/// request on the server level.
+pub fn find_location<'a>(directives: &'a [Directive], uri: &str)
+    -> Result<Option<LocationMatch<'a>>, RegexError>
+{
+    let mut chain = Vec::new();
+    let mut captures = Vec::new();
+    search(directives, uri, &mut chain, &mut captures)?;
+    Ok(chain.pop().map(|location| LocationMatch {
+        location,
+        parents: chain,
+        captures,
+    }))
+}
+
+/// Finds a location nginx selects for the URI in the `server` block
+///
+/// Same as `find_location(&server.directives, uri)`.
+pub fn find_server_location<'a>(server: &'a Server, uri: &str)
+    -> Result<Option<LocationMatch<'a>>, RegexError>
+{
+    find_location(&server.directives, uri)
+}
+
+/// Finds a named location (`@name`) on the server level
+///
+/// This is where `try_files`, `error_page` and similar directives jump to.
+/// The `name` is without the `@` sign.
+pub fn find_named_location<'a>(directives: &'a [Directive], name: &str)
+    -> Option<&'a Location>
+{
+    locations(directives).find(|loc| match loc.pattern {
+        LocationPattern::Named(ref n) => n == name,
+        _ => false,
+    })
+}
+
+fn locations(directives: &[Directive]) -> impl Iterator<Item=&Location>
+{
+    directives.iter().filter_map(|d| match d.item {
+        Item::Location(ref loc) => Some(loc),
+        _ => None,
+    })
+}
+
+#[allow(clippy::unnecessary_map_or)]  // `is_none_or` needs rust 1.82
+fn search<'a>(directives: &'a [Directive], uri: &str,
+    chain: &mut Vec<&'a Location>, captures: &mut Vec<Option<String>>)
+    -> Result<Status, RegexError>
+{
+    use ast::LocationPattern::*;
+
+    let mut longest: Option<(&Location, usize)> = None;
+    for loc in locations(directives) {
+        match loc.pattern {
+            Exact(ref p) if p == uri => {
+                chain.push(loc);
+                return Ok(Status::Done);
+            }
+            Prefix(ref p) | FinalPrefix(ref p)
+                if uri.starts_with(&p[..])
+                && longest.map_or(true, |(_, len)| p.len() > len)
+            => longest = Some((loc, p.len())),
+            _ => {}
+        }
+    }
+
+    let base = chain.len();
+    let mut status = Status::NotFound;
+    let mut noregex = false;
+    if let Some((loc, _)) = longest {
+        chain.push(loc);
+        noregex = matches!(loc.pattern, FinalPrefix(_));
+        if search(&loc.directives, uri, chain, captures)? == Status::Done {
+            return Ok(Status::Done);
+        }
+        status = Status::Prefix;
+    }
+    if noregex {
+        return Ok(status);
+    }
+
+    for loc in locations(directives) {
+        let regex = match loc.pattern {
+            Regex(ref p) => compile(p, false)?,
+            RegexInsensitive(ref p) => compile(p, true)?,
+            _ => continue,
+        };
+        if let Some(caps) = regex.captures(uri) {
+            chain.truncate(base);
+            chain.push(loc);
+            *captures = caps.iter()
+                .map(|m| m.map(|m| m.as_str().to_string()))
+                .collect();
+            search(&loc.directives, uri, chain, captures)?;
+            return Ok(Status::Done);
+        }
+    }
+    Ok(status)
+}
+
+/// Finds a server block nginx selects for the connection and host name
+///
+/// The `local` is the address the connection was accepted on, i.e. an
+/// `Address::Ip` or `Address::Unix`. (`Address::Port` and
+/// `Address::StarPort` are treated as the unspecified IPv4 address with the
+/// port, so they only match wildcard listen directives.) The `host` is
+/// the value of the `Host` header, and `sni` is the server name sent in the
+/// TLS handshake.
+///
+/// The algorithm is the same as in nginx:
+///
+/// 1. Servers listening on the exact address and port of the connection
+///    are chosen. Only if there are none, servers listening on the
+///    wildcard address (`*:80`, `0.0.0.0:80`, `[::]:80`) for the port are
+///    used. A server without `listen` directives listens on `*:80`.
+/// 2. The default server among them is the one having `default_server`
+///    in the matching `listen`, or the first one otherwise.
+/// 3. The name is the `host` (without port and trailing dot), or the
+///    `sni` if there is no `host` and the `listen` has `ssl`. Among the
+///    servers from step 1, the name is looked up as: exact name, the
+///    longest wildcard name starting with an asterisk (`*.example.com`
+///    or `.example.com`), the longest wildcard name ending with an
+///    asterisk (`www.example.*`), and finally the first matching regular
+///    expression in the order they appear in the config.
+/// 4. If nothing matches, the default server is used.
+///
+/// Returns `Ok(None)` if no server listens on the address.
+pub fn find_server<'a>(http: &'a Http, local: &Address,
+    host: Option<&str>, sni: Option<&str>)
+    -> Result<Option<ServerMatch<'a>>, RegexError>
+{
+    let default_listen = [Listen::new(Address::StarPort(80))];
+    let mut exact = Vec::new();
+    let mut wildcard = Vec::new();
+    for dir in &http.directives {
+        let server = match dir.item {
+            Item::Server(ref server) => server,
+            _ => continue,
+        };
+        let mut listens = server.directives.iter()
+            .filter_map(|d| match d.item {
+                Item::Listen(ref lst) => Some(lst),
+                _ => None,
+            })
+            .peekable();
+        let listens: Vec<&Listen> = if listens.peek().is_none() {
+            default_listen.iter().collect()
+        } else {
+            listens.collect()
+        };
+        for lst in listens {
+            match listen_matches(&lst.address, local) {
+                Some(true) => exact.push((server, lst)),
+                Some(false) => wildcard.push((server, lst)),
+                None => {}
+            }
+        }
+    }
+    let candidates = if exact.is_empty() { wildcard } else { exact };
+    let default = match candidates.iter().find(|(_, l)| l.default_server)
+        .or_else(|| candidates.first())
+    {
+        Some(&(server, _)) => server,
+        None => return Ok(None),
+    };
+    let ssl = candidates.iter().any(|(_, l)| l.ssl);
+    let name = match (host, sni) {
+        (Some(host), _) => normalize_host(host),
+        (None, Some(sni)) if ssl => normalize_host(sni),
+        (None, _) => String::new(),
+    };
+    let mut servers = Vec::<&Server>::new();
+    for &(server, _) in &candidates {
+        if !servers.iter().any(|s| ::std::ptr::eq(*s, server)) {
+            servers.push(server);
+        }
+    }
+    Ok(Some(match find_by_name(&servers, &name)? {
+        Some((server, captures)) => ServerMatch {
+            server,
+            by_name: true,
+            captures,
+        },
+        None => ServerMatch {
+            server: default,
+            by_name: false,
+            captures: Vec::new(),
+        },
+    }))
+}
+
+/// Returns `Some(true)` on exact match and `Some(false)` for wildcard one
+fn listen_matches(listen: &Address, local: &Address) -> Option<bool> {
+    use ast::Address::*;
+    let local = match *local {
+        Ip(addr) => addr,
+        Port(port) | StarPort(port) => {
+            SocketAddr::new(IpAddr::from([0, 0, 0, 0]), port)
+        }
+        Unix(ref path) => return match *listen {
+            Unix(ref lpath) if lpath == path => Some(true),
+            _ => None,
+        },
+    };
+    match *listen {
+        Ip(addr) if addr == local => Some(true),
+        Ip(addr) if addr.ip().is_unspecified()
+            && addr.port() == local.port()
+            && addr.is_ipv4() == local.is_ipv4()
+        => Some(false),
+        Port(port) | StarPort(port)
+            if port == local.port() && local.is_ipv4()
+        => Some(false),
+        _ => None,
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    let host = if host.starts_with('[') {
+        host.find(']').map_or(host, |end| &host[..end+1])
+    } else {
+        host.split(':').next().unwrap_or(host)
+    };
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+type Captures = Vec<Option<String>>;
+
+#[allow(clippy::unnecessary_map_or)]  // `is_none_or` needs rust 1.82
+fn find_by_name<'a>(servers: &[&'a Server], name: &str)
+    -> Result<Option<(&'a Server, Captures)>, RegexError>
+{
+    use ast::ServerName::*;
+
+    let names = servers.iter().flat_map(|&server| {
+        server.directives.iter().filter_map(|d| match d.item {
+            Item::ServerName(ref names) => Some(names),
+            _ => None,
+        })
+        .flat_map(|names| names.iter())
+        .map(move |n| (server, n))
+    }).collect::<Vec<(&Server, &ServerName)>>();
+
+    if let Some(&(server, _)) = names.iter().find(|(_, n)| match **n {
+        Exact(ref n) => unquote(n).eq_ignore_ascii_case(name),
+        _ => false,
+    }) {
+        return Ok(Some((server, Vec::new())));
+    }
+    let mut best: Option<(&Server, usize)> = None;
+    for &(server, n) in &names {
+        let len = match *n {
+            StarSuffix(ref n) if has_domain_suffix(name, n) => n.len(),
+            Suffix(ref n)
+                if has_domain_suffix(name, n) || n.eq_ignore_ascii_case(name)
+            => n.len(),
+            _ => continue,
+        };
+        if best.map_or(true, |(_, blen)| len > blen) {
+            best = Some((server, len));
+        }
+    }
+    if best.is_none() {
+        for &(server, n) in &names {
+            match *n {
+                StarPrefix(ref n) if has_domain_prefix(name, n)
+                    && best.map_or(true, |(_, blen)| n.len() > blen)
+                => best = Some((server, n.len())),
+                _ => {}
+            }
+        }
+    }
+    if let Some((server, _)) = best {
+        return Ok(Some((server, Vec::new())));
+    }
+    for &(server, n) in &names {
+        if let Regex(ref pattern) = *n {
+            if let Some(caps) = compile(pattern, true)?.captures(name) {
+                let captures = caps.iter()
+                    .map(|m| m.map(|m| m.as_str().to_string()))
+                    .collect();
+                return Ok(Some((server, captures)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Empty name is written as `""` in the config
+fn unquote(name: &str) -> &str {
+    match name {
+        "\"\"" | "''" => "",
+        _ => name,
+    }
+}
+
+/// Checks `*.suffix` against the host, the star matches one or more labels
+fn has_domain_suffix(host: &str, suffix: &str) -> bool {
+    host.len() > suffix.len() + 1
+        && host.is_char_boundary(host.len() - suffix.len())
+        && host[host.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+        && host.as_bytes()[host.len() - suffix.len() - 1] == b'.'
+}
+
+/// Checks `prefix.*` against the host, the star matches one or more labels
+fn has_domain_prefix(host: &str, prefix: &str) -> bool {
+    host.len() > prefix.len() + 1
+        && host.is_char_boundary(prefix.len())
+        && host[..prefix.len()].eq_ignore_ascii_case(prefix)
+        && host.as_bytes()[prefix.len()] == b'.'
+}
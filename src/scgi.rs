@@ -0,0 +1,52 @@
+use combine::{Parser};
+use combine::{choice, optional};
+
+use ast::{Item};
+use helpers::{semi, ident};
+use tokenizer::TokenStream;
+use grammar::{value, typed, bool};
+use proxy::{cache_valid, next_upstream_flags};
+
+
+pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    choice((
+        ident("scgi_pass").with(value()).skip(semi())
+            .map(Item::ScgiPass),
+        ident("scgi_param").with(value()).and(value())
+            .and(optional(ident("if_not_empty")).map(|x| x.is_some()))
+            .skip(semi())
+            .map(|((name, value), if_not_empty)| {
+                Item::ScgiParam { name, value, if_not_empty }
+            }),
+        ident("scgi_buffering").with(bool()).skip(semi())
+            .map(Item::ScgiBuffering),
+        ident("scgi_buffer_size").with(typed()).skip(semi())
+            .map(Item::ScgiBufferSize),
+        ident("scgi_cache").with(value()).skip(semi())
+            .map(Item::ScgiCache),
+        ident("scgi_cache_key").with(value()).skip(semi())
+            .map(Item::ScgiCacheKey),
+        ident("scgi_cache_valid").with(cache_valid()).skip(semi())
+            .map(Item::ScgiCacheValid),
+        ident("scgi_connect_timeout").with(typed()).skip(semi())
+            .map(Item::ScgiConnectTimeout),
+        ident("scgi_read_timeout").with(typed()).skip(semi())
+            .map(Item::ScgiReadTimeout),
+        ident("scgi_send_timeout").with(typed()).skip(semi())
+            .map(Item::ScgiSendTimeout),
+        ident("scgi_next_upstream")
+            .with(next_upstream_flags("invalid scgi upstream flag"))
+            .skip(semi())
+            .map(Item::ScgiNextUpstream),
+        ident("scgi_next_upstream_tries").with(value()).skip(semi())
+            .map(Item::ScgiNextUpstreamTries),
+        ident("scgi_next_upstream_timeout").with(typed()).skip(semi())
+            .map(Item::ScgiNextUpstreamTimeout),
+        ident("scgi_intercept_errors").with(bool()).skip(semi())
+            .map(Item::ScgiInterceptErrors),
+        ident("scgi_hide_header").with(value()).skip(semi())
+            .map(Item::ScgiHideHeader),
+        ident("scgi_pass_header").with(value()).skip(semi())
+            .map(Item::ScgiPassHeader),
+    ))
+}
@@ -0,0 +1,122 @@
+use combine::{many1, Parser};
+use combine::{choice};
+use combine::error::StreamError;
+use combine::easy::Error;
+
+use ast::{Item, SslProtocol, SslSessionCache, SslCache, SslVerifyClient};
+use grammar::{value, typed, bool};
+use helpers::{semi, ident, string};
+use tokenizer::{TokenStream, Token};
+
+
+pub fn protocol<'a>() -> impl Parser<Output=SslProtocol, Input=TokenStream<'a>> {
+    use ast::SslProtocol::*;
+    choice((
+        ident("SSLv2").map(|_| SslV2),
+        ident("SSLv3").map(|_| SslV3),
+        ident("TLSv1").map(|_| TlsV1),
+        ident("TLSv1.1").map(|_| TlsV1_1),
+        ident("TLSv1.2").map(|_| TlsV1_2),
+        ident("TLSv1.3").map(|_| TlsV1_3),
+    ))
+}
+
+fn parse_cache<'a>(val: &str) -> Result<SslCache, Error<Token<'a>, Token<'a>>>
+{
+    if val == "builtin" {
+        return Ok(SslCache::Builtin(None));
+    }
+    if let Some(size) = val.strip_prefix("builtin:") {
+        return Ok(SslCache::Builtin(Some(size.parse()?)));
+    }
+    if let Some(rest) = val.strip_prefix("shared:") {
+        let mut pair = rest.splitn(2, ':');
+        match (pair.next(), pair.next()) {
+            (Some(name), Some(size)) if !name.is_empty() && !size.is_empty()
+            => {
+                return Ok(SslCache::Shared {
+                    name: name.to_string(),
+                    size: size.parse().map_err(Error::unexpected_message)?,
+                });
+            }
+            _ => {}
+        }
+        return Err(Error::unexpected_message(
+            "shared cache must be specified as shared:name:size"));
+    }
+    Err(Error::unexpected_message(
+        "session cache must be off, none, builtin or shared"))
+}
+
+fn session_cache<'a>()
+    -> impl Parser<Output=Item, Input=TokenStream<'a>>
+{
+    ident("ssl_session_cache")
+    .with(choice((
+        ident("off").map(|_| SslSessionCache::Off),
+        ident("none").map(|_| SslSessionCache::None),
+        many1(string().and_then(|t| parse_cache(t.value)))
+            .map(SslSessionCache::Caches),
+    )))
+    .skip(semi())
+    .map(Item::SslSessionCache)
+}
+
+fn verify_client<'a>()
+    -> impl Parser<Output=Item, Input=TokenStream<'a>>
+{
+    ident("ssl_verify_client")
+    .with(choice((
+        ident("on").map(|_| SslVerifyClient::On),
+        ident("off").map(|_| SslVerifyClient::Off),
+        ident("optional").map(|_| SslVerifyClient::Optional),
+        ident("optional_no_ca").map(|_| SslVerifyClient::OptionalNoCa),
+    )))
+    .skip(semi())
+    .map(Item::SslVerifyClient)
+}
+
+pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    choice((
+        ident("ssl_certificate").with(value()).skip(semi())
+            .map(Item::SslCertificate),
+        ident("ssl_certificate_key").with(value()).skip(semi())
+            .map(Item::SslCertificateKey),
+        ident("ssl_protocols").with(many1(protocol())).skip(semi())
+            .map(Item::SslProtocols),
+        ident("ssl_ciphers").with(value()).skip(semi())
+            .map(Item::SslCiphers),
+        ident("ssl_prefer_server_ciphers").with(bool()).skip(semi())
+            .map(Item::SslPreferServerCiphers),
+        session_cache(),
+        ident("ssl_session_timeout").with(typed()).skip(semi())
+            .map(Item::SslSessionTimeout),
+        ident("ssl_session_tickets").with(bool()).skip(semi())
+            .map(Item::SslSessionTickets),
+        ident("ssl_dhparam").with(value()).skip(semi())
+            .map(Item::SslDhparam),
+        ident("ssl_ecdh_curve").with(value()).skip(semi())
+            .map(Item::SslEcdhCurve),
+        ident("ssl_stapling").with(bool()).skip(semi())
+            .map(Item::SslStapling),
+        ident("ssl_stapling_verify").with(bool()).skip(semi())
+            .map(Item::SslStaplingVerify),
+        ident("ssl_stapling_file").with(value()).skip(semi())
+            .map(Item::SslStaplingFile),
+        ident("ssl_stapling_responder").with(value()).skip(semi())
+            .map(Item::SslStaplingResponder),
+        ident("ssl_trusted_certificate").with(value()).skip(semi())
+            .map(Item::SslTrustedCertificate),
+        ident("ssl_client_certificate").with(value()).skip(semi())
+            .map(Item::SslClientCertificate),
+        verify_client(),
+        ident("ssl_verify_depth").with(string())
+            .and_then(|t| t.value.parse().map(Item::SslVerifyDepth))
+            .skip(semi()),
+        ident("ssl_early_data").with(bool()).skip(semi())
+            .map(Item::SslEarlyData),
+        ident("ssl_conf_command").with(value()).and(value())
+            .skip(semi())
+            .map(|(name, value)| Item::SslConfCommand { name, value }),
+    ))
+}
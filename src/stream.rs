@@ -0,0 +1,23 @@
+use combine::{Parser};
+use combine::{choice};
+
+use ast::{self, Item};
+use grammar::{value, typed, bool, block};
+use helpers::{semi, ident};
+use tokenizer::{TokenStream};
+
+
+pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    choice((
+        ident("stream").with(block())
+            .map(|(position, directives)| {
+                Item::Stream(ast::Stream { position, directives })
+            }),
+        ident("proxy_timeout").with(typed()).skip(semi())
+            .map(Item::ProxyTimeout),
+        ident("proxy_responses").with(value()).skip(semi())
+            .map(Item::ProxyResponses),
+        ident("ssl_preread").with(bool()).skip(semi())
+            .map(Item::SslPreread),
+    ))
+}
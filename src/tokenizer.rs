@@ -5,7 +5,8 @@ use combine::error::{StreamError};
 use combine::stream::{Resetable};
 use combine::easy::{Error, Errors};
 
-use position::Pos;
+use error::InternalError;
+use position::{Pos, FileId};
 
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -27,13 +28,27 @@ pub struct TokenStream<'a> {
     buf: &'a str,
     position: Pos,
     off: usize,
-    next_state: Option<(usize, Token<'a>, usize, Pos)>,
+    // offsets of the last token consumed and the end of the one before it,
+    // whitespace and comments between tokens are kept in the gaps
+    tok_start: usize,
+    tok_end: usize,
+    gap_start: usize,
+    // position just after the last token consumed
+    tok_end_pos: Pos,
+    next_state: Option<(usize, Token<'a>, usize, usize, Pos, Pos)>,
+    // errors skipped over by the recovering parser, `None` unless enabled
+    recovered: Option<Vec<InternalError<'a>>>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Checkpoint {
     position: Pos,
     off: usize,
+    tok_start: usize,
+    tok_end: usize,
+    gap_start: usize,
+    tok_end_pos: Pos,
+    recovered: usize,
 }
 
 impl<'a> StreamOnce for TokenStream<'a> {
@@ -43,8 +58,12 @@ impl<'a> StreamOnce for TokenStream<'a> {
     type Error = Errors<Token<'a>, Token<'a>, Pos>;
 
     fn uncons(&mut self) -> Result<Self::Item, Error<Token<'a>, Token<'a>>> {
-        if let Some((at, tok, off, pos)) = self.next_state {
+        if let Some((at, tok, end, off, pos, end_pos)) = self.next_state {
             if at == self.off {
+                self.gap_start = self.tok_end;
+                self.tok_start = at;
+                self.tok_end = end;
+                self.tok_end_pos = end_pos;
                 self.off = off;
                 self.position = pos;
                 return Ok(tok);
@@ -53,16 +72,21 @@ impl<'a> StreamOnce for TokenStream<'a> {
         let old_pos = self.off;
         let (kind, len) = self.peek_token()?;
         let value = &self.buf[self.off-len..self.off];
+        self.gap_start = self.tok_end;
+        self.tok_start = old_pos;
+        self.tok_end = self.off;
+        self.tok_end_pos = self.position();
         self.skip_whitespace();
         let token = Token { kind, value };
-        self.next_state = Some((old_pos, token, self.off, self.position));
+        self.next_state = Some((old_pos, token, self.tok_end,
+                                self.off, self.position, self.tok_end_pos));
         Ok(token)
     }
 }
 
 impl<'a> Positioned for TokenStream<'a> {
     fn position(&self) -> Self::Position {
-        self.position
+        Pos { offset: self.off, .. self.position }
     }
 }
 
@@ -72,25 +96,64 @@ impl<'a> Resetable for TokenStream<'a> {
         Checkpoint {
             position: self.position,
             off: self.off,
+            tok_start: self.tok_start,
+            tok_end: self.tok_end,
+            gap_start: self.gap_start,
+            tok_end_pos: self.tok_end_pos,
+            recovered: self.recovered.as_ref().map_or(0, |v| v.len()),
         }
     }
     fn reset(&mut self, checkpoint: Checkpoint) {
         self.position = checkpoint.position;
         self.off = checkpoint.off;
+        self.tok_start = checkpoint.tok_start;
+        self.tok_end = checkpoint.tok_end;
+        self.gap_start = checkpoint.gap_start;
+        self.tok_end_pos = checkpoint.tok_end_pos;
+        if let Some(ref mut errors) = self.recovered {
+            errors.truncate(checkpoint.recovered);
+        }
     }
 }
 
 impl<'a> TokenStream<'a> {
     pub fn new(s: &str) -> TokenStream {
+        TokenStream::with_file(s, FileId::default())
+    }
+    pub fn with_file(s: &str, file: FileId) -> TokenStream<'_> {
         let mut me = TokenStream {
             buf: s,
-            position: Pos { line: 1, column: 1 },
+            position: Pos { file, line: 1, column: 1, offset: 0 },
             off: 0,
+            tok_start: 0,
+            tok_end: 0,
+            gap_start: 0,
+            tok_end_pos: Pos { file, line: 1, column: 1, offset: 0 },
             next_state: None,
+            recovered: None,
         };
         me.skip_whitespace();
         me
     }
+    /// Stream where block contents skip over invalid directives
+    ///
+    /// Errors are collected and can be fetched by `take_errors`.
+    pub fn recovering(s: &str) -> TokenStream<'_> {
+        let mut me = TokenStream::new(s);
+        me.recovered = Some(Vec::new());
+        me
+    }
+    pub(crate) fn is_recovering(&self) -> bool {
+        self.recovered.is_some()
+    }
+    pub(crate) fn record_error(&mut self, error: InternalError<'a>) {
+        self.recovered.as_mut()
+            .expect("recovering stream")
+            .push(error);
+    }
+    pub(crate) fn take_errors(&mut self) -> Vec<InternalError<'a>> {
+        self.recovered.as_mut().map(::std::mem::take).unwrap_or_default()
+    }
 
     fn peek_token(&mut self)
         -> Result<(Kind, usize), Error<Token<'a>, Token<'a>>>
@@ -120,16 +183,11 @@ impl<'a> TokenStream<'a> {
             }
             '"' | '\'' => {
                 let open_quote = cur_char;
-                let mut prev_char = cur_char;
+                let mut escaped = false;
                 let mut nchars = 1;
                 for (idx, cur_char) in iter {
                     nchars += 1;
                     match cur_char {
-                        x if x == open_quote && prev_char != '\\' => {
-                            self.position.column += nchars;
-                            self.off += idx+1;
-                            return Ok((String, idx+1));
-                        }
                         '\n' => {
                             return Err(
                                 Error::unexpected_message(
@@ -137,11 +195,15 @@ impl<'a> TokenStream<'a> {
                                 )
                             );
                         }
-                        _ => {
-
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        x if x == open_quote => {
+                            self.position.column += nchars;
+                            self.off += idx+1;
+                            return Ok((String, idx+1));
                         }
+                        _ => {}
                     }
-                    prev_char = cur_char;
                 }
                 Err(Error::unexpected_message("unterminated string value"))
             }
@@ -153,16 +215,26 @@ impl<'a> TokenStream<'a> {
                         '\r' | '\t' | '\n' => {
                             self.position.column += nchars;
                             self.off += idx;
-                            return Ok((String, nchars));
+                            return Ok((String, idx));
                         }
                         '{' if prev_char == '$' => {
-                            while let Some((_, cur_char)) = iter.next() {
-                                nchars += 1;
-                                if cur_char == '}' {
-                                    break;
+                            // unclosed brace is left as is for the value
+                            // parser to report, instead of swallowing the
+                            // rest of the file
+                            let mut look = iter.clone();
+                            let mut len = 0;
+                            while let Some((_, cur_char)) = look.next() {
+                                len += 1;
+                                match cur_char {
+                                    '}' => {
+                                        iter = look;
+                                        nchars += len;
+                                        break;
+                                    }
+                                    '\r' | '\n' => break,
+                                    _ => {}
                                 }
                             }
-                            // TODO(tailhook) validate end of file
                         }
                         ';' | '{' | '}' | ' ' |
                         '\"' | '\'' => {
@@ -170,7 +242,7 @@ impl<'a> TokenStream<'a> {
                             } else {
                                 self.position.column += nchars;
                                 self.off += idx;
-                                return Ok((String, nchars));
+                                return Ok((String, idx));
                             }
                         }
                         '\\' if prev_char == '\\' => {
@@ -186,7 +258,7 @@ impl<'a> TokenStream<'a> {
                 let len = self.buf.len() - self.off;
                 self.position.column += nchars;
                 self.off += len;
-                return Ok((String, nchars));
+                Ok((String, len))
             }
         }
     }
@@ -225,6 +297,72 @@ impl<'a> TokenStream<'a> {
         };
         self.off += idx;
     }
+
+    /// The whole text being tokenized
+    pub fn source(&self) -> &'a str {
+        self.buf
+    }
+
+    /// Byte offset of the next token
+    pub fn offset(&self) -> usize {
+        self.off
+    }
+
+    /// Position just after the last token consumed
+    pub fn last_token_end(&self) -> Pos {
+        self.tok_end_pos
+    }
+
+    /// Byte offsets of the start and the end of the last token consumed
+    pub fn last_token(&self) -> (usize, usize) {
+        (self.tok_start, self.tok_end)
+    }
+
+    fn ends_directive(token: &str) -> bool {
+        token == ";" || token == "}"
+    }
+
+    /// Comments on the lines between the last token and the next one
+    ///
+    /// A comment on the same line as the semicolon or closing brace of
+    /// the previous directive is skipped, it's a trailing comment.
+    pub fn leading_comments(&self) -> Vec<String> {
+        let skip_first = TokenStream::ends_directive(
+            &self.buf[self.tok_start..self.tok_end]);
+        comments(&self.buf[self.tok_end..self.off], skip_first)
+    }
+
+    /// Comment on the same line right after the last token
+    pub fn trailing_comment(&self) -> Option<String> {
+        if !TokenStream::ends_directive(
+            &self.buf[self.tok_start..self.tok_end])
+        {
+            return None;
+        }
+        let gap = &self.buf[self.tok_end..self.off];
+        let line = gap.split('\n').next().unwrap_or("").trim_start();
+        line.strip_prefix('#').map(|text| text.trim_end().to_string())
+    }
+
+    /// Comments on the lines before the last token if it's a closing brace
+    pub fn block_end_comments(&self) -> Vec<String> {
+        if &self.buf[self.tok_start..self.tok_end] != "}" {
+            return Vec::new();
+        }
+        let before = &self.buf[..self.gap_start];
+        let skip_first = before.ends_with(';') || before.ends_with('}');
+        comments(&self.buf[self.gap_start..self.tok_start], skip_first)
+    }
+}
+
+// The gap between tokens consists only of whitespace and comments, so every
+// line of it is either blank or has a single comment till the end of line
+fn comments(gap: &str, skip_first: bool) -> Vec<String> {
+    gap.split('\n').enumerate()
+        .filter(|&(idx, _)| idx > 0 || !skip_first)
+        .filter_map(|(_, line)| line.trim_start().strip_prefix('#'))
+        .map(|text| text.trim_end().to_string())
+        .collect()
 }
 
 impl<'a> fmt::Display for Token<'a> {
@@ -241,6 +379,8 @@ mod test {
 
     use combine::{StreamOnce, Positioned};
 
+    type Text = ::std::string::String;
+
     fn tok_str(s: &str) -> Vec<&str> {
         let mut r = Vec::new();
         let mut s = TokenStream::new(s);
@@ -293,4 +433,31 @@ mod test {
         assert_eq!(tok_typ("proxy_pass http://${a b};"),
                    [String, String, Semicolon]);
     }
+
+    fn tok_comments(s: &str) -> Vec<(Vec<Text>, Option<Text>)> {
+        let mut r = Vec::new();
+        let mut s = TokenStream::new(s);
+        r.push((s.leading_comments(), None));
+        while s.uncons().is_ok() {
+            r.push((s.leading_comments(), s.trailing_comment()));
+        }
+        return r;
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(tok_comments("# a\nx; # b\n# c\ny;"), [
+            (vec![" a".into()], None),
+            (vec![], None),
+            (vec![" c".into()], Some(" b".into())),
+            (vec![], None),
+            (vec![], None),
+        ]);
+        assert_eq!(tok_comments("x { # a\n}"), [
+            (vec![], None),
+            (vec![], None),
+            (vec![" a".into()], None),
+            (vec![], None),
+        ]);
+    }
 }
@@ -0,0 +1,230 @@
+//! Sizes, time intervals and rates in nginx notation
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::time;
+
+use ast::{Rate, RateUnit};
+
+
+pub(crate) const KILOBYTE: u64 = 1024;
+pub(crate) const MEGABYTE: u64 = 1024 * KILOBYTE;
+pub(crate) const GIGABYTE: u64 = 1024 * MEGABYTE;
+
+pub(crate) const SECOND: u64 = 1000;
+pub(crate) const MINUTE: u64 = 60 * SECOND;
+pub(crate) const HOUR: u64 = 60 * MINUTE;
+pub(crate) const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+const MONTH: u64 = 30 * DAY;
+const YEAR: u64 = 365 * DAY;
+
+/// Size in bytes, like `512k` or `10m`
+///
+/// Parsed sizes are displayed the same way they are written in the source.
+/// Constructed ones use the largest unit which represents the size exactly.
+/// Sizes are compared by the number of bytes only.
+#[derive(Debug, Clone)]
+pub struct Size {
+    bytes: u64,
+    text: Option<String>,
+}
+
+/// Time interval, like `30s` or `1h30m`
+///
+/// Stored with millisecond precision (same as nginx does). Parsed intervals
+/// are displayed the same way they are written in the source, constructed
+/// ones as a sequence of days, hours, minutes, seconds and milliseconds.
+/// Intervals are compared by the number of milliseconds only.
+#[derive(Debug, Clone)]
+pub struct Duration {
+    millis: u64,
+    text: Option<String>,
+}
+
+impl Size {
+    /// Makes a size of the given number of bytes
+    pub fn from_bytes(bytes: u64) -> Size {
+        Size { bytes, text: None }
+    }
+    /// Number of bytes
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+    /// Text of the size as written in the config (for parsed values)
+    pub(crate) fn text(&self) -> Option<&str> {
+        self.text.as_ref().map(|x| &x[..])
+    }
+}
+
+impl Duration {
+    /// Makes an interval of the given number of milliseconds
+    pub fn from_millis(millis: u64) -> Duration {
+        Duration { millis, text: None }
+    }
+    /// Makes an interval of the given number of seconds
+    pub fn from_secs(secs: u64) -> Duration {
+        Duration::from_millis(secs.saturating_mul(SECOND))
+    }
+    /// Number of milliseconds
+    pub fn millis(&self) -> u64 {
+        self.millis
+    }
+    /// Text of the interval as written in the config (for parsed values)
+    pub(crate) fn text(&self) -> Option<&str> {
+        self.text.as_ref().map(|x| &x[..])
+    }
+    /// Converts to the standard library type
+    pub fn to_std(&self) -> time::Duration {
+        time::Duration::from_millis(self.millis)
+    }
+}
+
+impl From<Duration> for time::Duration {
+    fn from(d: Duration) -> time::Duration {
+        d.to_std()
+    }
+}
+
+impl PartialEq for Size {
+    fn eq(&self, other: &Size) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl Eq for Size {}
+
+impl PartialOrd for Size {
+    fn partial_cmp(&self, other: &Size) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Size {
+    fn cmp(&self, other: &Size) -> Ordering {
+        self.bytes.cmp(&other.bytes)
+    }
+}
+
+impl Hash for Size {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bytes.hash(state)
+    }
+}
+
+impl PartialEq for Duration {
+    fn eq(&self, other: &Duration) -> bool {
+        self.millis == other.millis
+    }
+}
+
+impl Eq for Duration {}
+
+impl PartialOrd for Duration {
+    fn partial_cmp(&self, other: &Duration) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Duration {
+    fn cmp(&self, other: &Duration) -> Ordering {
+        self.millis.cmp(&other.millis)
+    }
+}
+
+impl Hash for Duration {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.millis.hash(state)
+    }
+}
+
+impl Rate {
+    /// Number of requests per second (fractional for `r/m` rates)
+    pub fn per_second(&self) -> f64 {
+        match self.unit {
+            RateUnit::PerSecond => f64::from(self.requests),
+            RateUnit::PerMinute => f64::from(self.requests) / 60.,
+        }
+    }
+}
+
+/// Parses unsigned number, `None` if it's empty, has a sign or overflows
+fn number(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+impl FromStr for Size {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Size, String> {
+        let (num, scale) = match s.as_bytes().last() {
+            Some(b'k') | Some(b'K') => (&s[..s.len()-1], KILOBYTE),
+            Some(b'm') | Some(b'M') => (&s[..s.len()-1], MEGABYTE),
+            Some(b'g') | Some(b'G') => (&s[..s.len()-1], GIGABYTE),
+            _ => (s, 1),
+        };
+        let bytes = number(num).and_then(|n| n.checked_mul(scale))
+            .ok_or_else(|| format!("invalid size {:?}", s))?;
+        Ok(Size { bytes, text: Some(s.to_string()) })
+    }
+}
+
+impl FromStr for Duration {
+    type Err = String;
+    /// Parses the time the same way as nginx does: units go in descending
+    /// order, each at most once, and a number without unit means seconds
+    fn from_str(s: &str) -> Result<Duration, String> {
+        let invalid = || format!("invalid time interval {:?}", s);
+        let units: &[(&str, u64)] = &[
+            ("y", YEAR), ("M", MONTH), ("w", WEEK), ("d", DAY),
+            ("h", HOUR), ("ms", 1), ("m", MINUTE), ("s", SECOND),
+        ];
+        let mut rest = s;
+        let mut last_scale = u64::MAX;
+        let mut total: u64 = 0;
+        while !rest.is_empty() {
+            let digits = rest.bytes().take_while(|b| b.is_ascii_digit())
+                .count();
+            let num = number(&rest[..digits]).ok_or_else(invalid)?;
+            rest = &rest[digits..];
+            let scale = if rest.is_empty() {
+                SECOND
+            } else {
+                let &(unit, scale) = units.iter()
+                    .find(|&&(unit, _)| rest.starts_with(unit))
+                    .ok_or_else(invalid)?;
+                rest = &rest[unit.len()..];
+                scale
+            };
+            if scale >= last_scale {
+                return Err(invalid());
+            }
+            last_scale = scale;
+            total = num.checked_mul(scale)
+                .and_then(|v| v.checked_add(total))
+                .ok_or_else(invalid)?;
+        }
+        if last_scale == u64::MAX {
+            return Err(invalid());
+        }
+        Ok(Duration { millis: total, text: Some(s.to_string()) })
+    }
+}
+
+impl FromStr for Rate {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Rate, String> {
+        let (num, unit) = if let Some(num) = s.strip_suffix("r/s") {
+            (num, RateUnit::PerSecond)
+        } else if let Some(num) = s.strip_suffix("r/m") {
+            (num, RateUnit::PerMinute)
+        } else {
+            return Err(String::from("rate must be in r/s or r/m"));
+        };
+        let requests = num.parse()
+            .map_err(|_| format!("invalid rate {:?}", s))?;
+        Ok(Rate { requests, unit })
+    }
+}
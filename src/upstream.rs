@@ -0,0 +1,128 @@
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+
+use combine::{Parser};
+use combine::{choice, many, optional};
+use combine::error::StreamError;
+use combine::easy::Error;
+
+use ast::{self, Item, UpstreamAddress, RandomMethod, Duration, Size};
+use grammar::{value, block, raw, literal};
+use helpers::{semi, ident, string, prefix};
+use tokenizer::{TokenStream, Token};
+
+
+fn parse_address<'a>(val: &str)
+    -> Result<UpstreamAddress, Error<Token<'a>, Token<'a>>>
+{
+    if let Some(path) = val.strip_prefix("unix:") {
+        return Ok(UpstreamAddress::Unix(PathBuf::from(path)));
+    }
+    if let Ok(addr) = val.parse::<SocketAddr>() {
+        return Ok(UpstreamAddress::Ip(addr.ip(), Some(addr.port())));
+    }
+    let bare = val.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = bare.parse::<IpAddr>() {
+        return Ok(UpstreamAddress::Ip(ip, None));
+    }
+    let mut pair = val.rsplitn(2, ':');
+    let (port, host) = (pair.next(), pair.next());
+    match (host, port) {
+        (Some(host), Some(port)) => {
+            let port = port.parse()
+                .map_err(|_| Error::unexpected_message("invalid port"))?;
+            Ok(UpstreamAddress::Name(host.to_string(), Some(port)))
+        }
+        _ => Ok(UpstreamAddress::Name(val.to_string(), None)),
+    }
+}
+
+enum ServerParts {
+    Weight(u32),
+    MaxConns(u32),
+    MaxFails(u32),
+    FailTimeout(Duration),
+    Backup,
+    Down,
+    Resolve,
+    Service(String),
+    Other(String),
+}
+
+pub fn server<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    use self::ServerParts::*;
+
+    literal().and_then(|val| parse_address(&val))
+    .and(many::<Vec<_>, _>(choice((
+        prefix("weight=").and_then(|val| val.parse().map(Weight)),
+        prefix("max_conns=").and_then(|val| val.parse().map(MaxConns)),
+        prefix("max_fails=").and_then(|val| val.parse().map(MaxFails)),
+        prefix("fail_timeout=").and_then(|val| {
+            val.parse().map(FailTimeout).map_err(Error::unexpected_message)
+        }),
+        ident("backup").map(|_| Backup),
+        ident("down").map(|_| Down),
+        ident("resolve").map(|_| Resolve),
+        prefix("service=").map(|val| Service(val.to_string())),
+        literal().map(Other),
+    ))))
+    .skip(semi())
+    .map(|(address, items)| {
+        let mut srv = ast::UpstreamServer::new(address);
+        for item in items {
+            match item {
+                Weight(v) => srv.weight = Some(v),
+                MaxConns(v) => srv.max_conns = Some(v),
+                MaxFails(v) => srv.max_fails = Some(v),
+                FailTimeout(v) => srv.fail_timeout = Some(v),
+                Backup => srv.backup = true,
+                Down => srv.down = true,
+                Resolve => srv.resolve = true,
+                Service(v) => srv.service = Some(v),
+                Other(v) => srv.other.push(v),
+            }
+        }
+        Item::UpstreamServer(srv)
+    })
+}
+
+fn random<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    ident("random")
+    .with(optional(ident("two").with(optional(choice((
+        ident("least_conn").map(|_| RandomMethod::LeastConn),
+        ident("least_time=header").map(|_| RandomMethod::LeastTimeHeader),
+        ident("least_time=last_byte")
+            .map(|_| RandomMethod::LeastTimeLastByte),
+    ))))))
+    .skip(semi())
+    .map(|two| match two {
+        Some(method) => Item::Random { two: true, method },
+        None => Item::Random { two: false, method: None },
+    })
+}
+
+pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    choice((
+        ident("upstream").with(raw()).and(block())
+            .map(|(name, (position, directives))| {
+                Item::Upstream(ast::Upstream { position, name, directives })
+            }),
+        ident("least_conn").skip(semi()).map(|_| Item::LeastConn),
+        ident("ip_hash").skip(semi()).map(|_| Item::IpHash),
+        ident("hash").with(value())
+            .and(optional(ident("consistent")).map(|x| x.is_some()))
+            .skip(semi())
+            .map(|(key, consistent)| Item::Hash { key, consistent }),
+        random(),
+        ident("keepalive").with(string())
+            .and_then(|t| t.value.parse().map(Item::Keepalive))
+            .skip(semi()),
+        ident("keepalive_requests").with(value()).skip(semi())
+            .map(Item::KeepaliveRequests),
+        ident("zone").with(raw()).and(optional(string().and_then(|t| {
+                t.value.parse::<Size>().map_err(Error::unexpected_message)
+            })))
+            .skip(semi())
+            .map(|(name, size)| Item::Zone { name, size }),
+    ))
+}
@@ -0,0 +1,52 @@
+use combine::{Parser};
+use combine::{choice, optional};
+
+use ast::{Item};
+use helpers::{semi, ident};
+use tokenizer::TokenStream;
+use grammar::{value, typed, bool};
+use proxy::{cache_valid, next_upstream_flags};
+
+
+pub fn directives<'a>() -> impl Parser<Output=Item, Input=TokenStream<'a>> {
+    choice((
+        ident("uwsgi_pass").with(value()).skip(semi())
+            .map(Item::UwsgiPass),
+        ident("uwsgi_param").with(value()).and(value())
+            .and(optional(ident("if_not_empty")).map(|x| x.is_some()))
+            .skip(semi())
+            .map(|((name, value), if_not_empty)| {
+                Item::UwsgiParam { name, value, if_not_empty }
+            }),
+        ident("uwsgi_buffering").with(bool()).skip(semi())
+            .map(Item::UwsgiBuffering),
+        ident("uwsgi_buffer_size").with(typed()).skip(semi())
+            .map(Item::UwsgiBufferSize),
+        ident("uwsgi_cache").with(value()).skip(semi())
+            .map(Item::UwsgiCache),
+        ident("uwsgi_cache_key").with(value()).skip(semi())
+            .map(Item::UwsgiCacheKey),
+        ident("uwsgi_cache_valid").with(cache_valid()).skip(semi())
+            .map(Item::UwsgiCacheValid),
+        ident("uwsgi_connect_timeout").with(typed()).skip(semi())
+            .map(Item::UwsgiConnectTimeout),
+        ident("uwsgi_read_timeout").with(typed()).skip(semi())
+            .map(Item::UwsgiReadTimeout),
+        ident("uwsgi_send_timeout").with(typed()).skip(semi())
+            .map(Item::UwsgiSendTimeout),
+        ident("uwsgi_next_upstream")
+            .with(next_upstream_flags("invalid uwsgi upstream flag"))
+            .skip(semi())
+            .map(Item::UwsgiNextUpstream),
+        ident("uwsgi_next_upstream_tries").with(value()).skip(semi())
+            .map(Item::UwsgiNextUpstreamTries),
+        ident("uwsgi_next_upstream_timeout").with(typed()).skip(semi())
+            .map(Item::UwsgiNextUpstreamTimeout),
+        ident("uwsgi_intercept_errors").with(bool()).skip(semi())
+            .map(Item::UwsgiInterceptErrors),
+        ident("uwsgi_hide_header").with(value()).skip(semi())
+            .map(Item::UwsgiHideHeader),
+        ident("uwsgi_pass_header").with(value()).skip(semi())
+            .map(Item::UwsgiPassHeader),
+    ))
+}
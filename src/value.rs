@@ -1,11 +1,17 @@
+//! Values of directive arguments which may contain variables
+//!
+//! Values are usually reached through the [`ast`](../ast/index.html)
+//! types, this module also provides [`Part`](enum.Part.html) to inspect
+//! what they consist of.
 use std::mem;
+use std::slice;
 use std::str::FromStr;
 
 use combine::easy::Error;
 use combine::error::StreamError;
 
 use format::{Displayable, Formatter};
-use position::Pos;
+use position::{Pos, Span, advance};
 use tokenizer::Token;
 
 /// Generic string value
@@ -16,13 +22,16 @@ use tokenizer::Token;
 /// quotes when parsing
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Value {
-    position: Pos,
-    pub(crate) data: Vec<Item>,
+    span: Span,
+    pub(crate) data: Vec<Part>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) enum Item {
+/// Part of the value: either a literal text or a variable reference
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Part {
+    /// Literal text (with quotes and escapes removed)
     Literal(String),
+    /// Variable name without the dollar sign and braces
     Variable(String),
 }
 
@@ -43,13 +52,137 @@ impl Value {
         } else {
             Value::scan_raw(token)?
         };
-        Ok(Value { position, data })
+        let span = Span { start: position, end: advance(position, token) };
+        Ok(Value { span, data })
+    }
+
+    /// Parses a value, keeping the token as a plain literal if it's not
+    /// a valid expression (e.g. a regular expression ending with `$`)
+    pub(crate) fn parse_lenient<'a>(position: Pos, tok: Token<'a>) -> Value {
+        Value::parse(position, tok).unwrap_or_else(|_| {
+            let mut text = tok.value;
+            if text.len() >= 2 &&
+                (text.starts_with('"') || text.starts_with('\''))
+            {
+                text = &text[1..text.len()-1];
+            }
+            Value {
+                span: Span {
+                    start: position,
+                    end: advance(position, tok.value),
+                },
+                data: vec![Part::Literal(text.to_string())],
+            }
+        })
+    }
+
+    /// Position of the start of the value in the source
+    pub fn position(&self) -> Pos {
+        self.span.start
+    }
+
+    /// Range of the source text the value was parsed from (including
+    /// quotes, if any)
+    ///
+    /// Values which are not parsed from a config (e.g. made by `FromStr`)
+    /// have an empty span at line zero.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Makes a value consisting of the literal text
+    ///
+    /// Nginx has no escape for the dollar sign, so a `$` in the text is
+    /// written as is: `Value::literal("$x")` is formatted as `$x` and is
+    /// parsed back as a variable.
+    pub fn literal<S: Into<String>>(text: S) -> Value {
+        Value::from_parts(vec![Part::Literal(text.into())])
+    }
+
+    /// Makes a value which is a single variable reference
+    ///
+    /// The name is without the dollar sign, e.g. `Value::variable("host")`.
+    pub fn variable<S: Into<String>>(name: S) -> Value {
+        Value::from_parts(vec![Part::Variable(name.into())])
+    }
+
+    /// Makes a value from the sequence of parts
+    ///
+    /// Adjacent literals are joined and empty literals are dropped, so the
+    /// value is the same as if it was parsed from the config. Variable
+    /// names are not validated.
+    ///
+    /// Literals are not checked for the `$` sign either, see
+    /// `Value::literal` for how it's formatted.
+    pub fn from_parts<I: IntoIterator<Item=Part>>(parts: I) -> Value {
+        let mut data: Vec<Part> = Vec::new();
+        for part in parts {
+            match (data.last_mut(), part) {
+                (_, Part::Literal(ref text)) if text.is_empty() => {}
+                (Some(&mut Part::Literal(ref mut prev)),
+                 Part::Literal(ref text)) => prev.push_str(text),
+                (_, part) => data.push(part),
+            }
+        }
+        Value { span: Span::default(), data }
+    }
+
+    /// Literal and variable parts of the value in order
+    pub fn parts(&self) -> slice::Iter<'_, Part> {
+        self.data.iter()
+    }
+
+    /// Returns `true` if the value contains no variables
+    pub fn is_literal(&self) -> bool {
+        self.as_literal().is_some()
+    }
+
+    /// Names of the variables referenced in the value
+    pub fn variables(&self) -> impl Iterator<Item=&str> {
+        self.data.iter().filter_map(|part| match *part {
+            Part::Variable(ref name) => Some(&name[..]),
+            Part::Literal(_) => None,
+        })
+    }
+
+    /// Returns the text of the value if it contains no variables
+    pub fn as_literal(&self) -> Option<&str> {
+        match self.data[..] {
+            [] => Some(""),
+            [Part::Literal(ref x)] => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Removes the literal text at the start of the value if it's there
+    pub(crate) fn strip_literal_prefix(&mut self, prefix: &str) -> bool {
+        let rest = match self.data.first() {
+            Some(Part::Literal(ref x)) => match x.strip_prefix(prefix) {
+                Some(rest) => rest.to_string(),
+                None => return false,
+            },
+            _ => return false,
+        };
+        if rest.is_empty() {
+            self.data.remove(0);
+        } else {
+            self.data[0] = Part::Literal(rest);
+        }
+        true
+    }
+
+    /// Adds the literal text at the start of the value
+    pub(crate) fn prepend_literal(&mut self, text: &str) {
+        match self.data.first_mut() {
+            Some(Part::Literal(ref mut x)) => x.insert_str(0, text),
+            _ => self.data.insert(0, Part::Literal(text.to_string())),
+        }
     }
 
     fn scan_raw<'a>(value: &str)
-        -> Result<Vec<Item>, Error<Token<'a>, Token<'a>>>
+        -> Result<Vec<Part>, Error<Token<'a>, Token<'a>>>
     {
-        use self::Item::*;
+        use self::Part::*;
         let mut buf = Vec::new();
         let mut chiter = value.char_indices().peekable();
         let mut prev_char = ' ';  // any having no special meaning
@@ -82,11 +215,20 @@ impl Value {
                                 };
                             }
                             let now = chiter.peek().map(|&(idx, _)| idx)
-                                .unwrap();
+                                .ok_or_else(|| Error::expected("}".into()))?;
+                            if now == vstart + 1 {
+                                return Err(Error::unexpected_message(
+                                    "empty variable name"));
+                            }
                             buf.push(Variable(
                                 value[vstart+1..now].to_string()));
                             cur_slice = now+1;
                         }
+                        '1'..='9' => {
+                            // regex capture, it's always a single digit
+                            buf.push(Variable(fchar.to_string()));
+                            cur_slice = vstart + 1;
+                        }
                         'a'...'z' | 'A'...'Z' | '_' | '0'...'9' => {
                             while let Some(&(_, c)) = chiter.peek() {
                                 match c {
@@ -119,22 +261,35 @@ impl Value {
     }
 
     fn scan_quoted<'a>(quote: char, value: &str)
-        -> Result<Vec<Item>, Error<Token<'a>, Token<'a>>>
+        -> Result<Vec<Part>, Error<Token<'a>, Token<'a>>>
     {
-        use self::Item::*;
+        use self::Part::*;
         let mut buf = Vec::new();
         let mut chiter = value.char_indices().peekable();
         chiter.next(); // skip quote
-        let mut prev_char = ' ';  // any having no special meaning
         let mut cur_slice = String::new();
         while let Some((idx, cur_char)) = chiter.next() {
             match cur_char {
-                _ if prev_char == '\\' => {
-                    cur_slice.push(cur_char);
-                    continue;
+                '\\' => {
+                    let (_, next) = chiter.next()
+                        .ok_or_else(|| {
+                            Error::unexpected_message("unclosed quote")
+                        })?;
+                    // same set of escapes that nginx itself unescapes,
+                    // anything else is kept verbatim with the backslash
+                    match next {
+                        '"' | '\'' | '\\' => cur_slice.push(next),
+                        'n' => cur_slice.push('\n'),
+                        't' => cur_slice.push('\t'),
+                        'r' => cur_slice.push('\r'),
+                        _ => {
+                            cur_slice.push('\\');
+                            cur_slice.push(next);
+                        }
+                    }
                 }
                 '"' | '\'' if cur_char == quote => {
-                    if cur_slice.len() > 0 {
+                    if !cur_slice.is_empty() {
                         buf.push(Literal(cur_slice));
                     }
                     if idx + 1 != value.len() {
@@ -147,18 +302,36 @@ impl Value {
                     return Ok(buf);
                 }
                 '$' => {
-                    let vstart = idx + 1;
-                    if cur_slice.len() > 0 {
-                        buf.push(Literal(
-                            mem::replace(&mut cur_slice, String::new())));
+                    if !cur_slice.is_empty() {
+                        buf.push(Literal(mem::take(&mut cur_slice)));
                     }
-                    let fchar = chiter.next().map(|(_, c)| c)
+                    let (fidx, fchar) = chiter.next()
                         .ok_or_else(|| Error::unexpected_message(
                             "bare $ in expression"))?;
                     match fchar {
                         '{' => {
-                            unimplemented!();
+                            while let Some(&(_, c)) = chiter.peek() {
+                                match c {
+                                    'a'..='z' | 'A'..='Z' | '_' | '0'..='9'
+                                    => chiter.next(),
+                                    _ => break,
+                                };
+                            }
+                            match chiter.next() {
+                                Some((now, '}')) if now > fidx + 1 => {
+                                    buf.push(Variable(
+                                        value[fidx+1..now].to_string()));
+                                }
+                                Some((_, '}')) => {
+                                    return Err(Error::unexpected_message(
+                                        "empty variable name"));
+                                }
+                                _ => {
+                                    return Err(Error::expected("}".into()));
+                                }
+                            }
                         }
+                        '1'..='9' => buf.push(Variable(fchar.to_string())),
                         'a'...'z' | 'A'...'Z' | '_' | '0'...'9' => {
                             while let Some(&(_, c)) = chiter.peek() {
                                 match c {
@@ -172,7 +345,7 @@ impl Value {
                                     Error::unexpected_message("unclosed quote")
                                 })?;
                             buf.push(Variable(
-                                value[vstart..now].to_string()));
+                                value[fidx..now].to_string()));
                         }
                         _ => {
                             return Err(Error::unexpected_message(
@@ -183,29 +356,37 @@ impl Value {
                 }
                 _ => cur_slice.push(cur_char),
             }
-            prev_char = cur_char;
         }
-        return Err(Error::unexpected_message("unclosed quote"));
+        Err(Error::unexpected_message("unclosed quote"))
     }
 }
 
 impl FromStr for Value {
     type Err = String;
     fn from_str(s: &str) -> Result<Value, String> {
-        Value::parse_str(Pos { line: 0, column: 0 }, s)
+        Value::parse_str(Pos::default(), s)
+        .map(|value| Value { span: Span::default(), .. value })
         .map_err(|e| e.to_string())
     }
 }
 
 impl Value {
-    fn has_specials(&self) -> bool {
-        use self::Item::*;
-        for item in &self.data {
+    fn needs_quotes(&self) -> bool {
+        use self::Part::*;
+        for (index, item) in self.data.iter().enumerate() {
             match *item {
                 Literal(ref x) => {
+                    // unquoted `#` starts a comment and a trailing
+                    // backslash would escape whatever follows it
+                    if (index == 0 && x.starts_with('#')) ||
+                        x.ends_with('\\')
+                    {
+                        return true;
+                    }
                     for c in x.chars() {
                         match c {
-                            ' ' | ';' | '\r' | '\n' | '\t' | '{' | '}' => {
+                            ' ' | ';' | '\r' | '\n' | '\t' | '{' | '}' |
+                            '"' | '\'' => {
                                 return true;
                             }
                             _ => {}
@@ -215,7 +396,7 @@ impl Value {
                 Variable(_) => {}
             }
         }
-        return false;
+        false
     }
 
     /// Replace variable references in this string with literal values
@@ -223,7 +404,7 @@ impl Value {
         where F: FnMut(&str) -> Option<S>,
               S: AsRef<str> + Into<String> + 'a,
     {
-        use self::Item::*;
+        use self::Part::*;
         // TODO(tailhook) join literal blocks
         for item in &mut self.data {
             let new_value = match *item {
@@ -238,54 +419,77 @@ impl Value {
     }
 }
 
-fn next_alphanum(data: &Vec<Item>, index: usize) -> bool {
-    use self::Item::*;
+fn needs_braces(data: &[Part], index: usize) -> bool {
+    match data[index] {
+        // captures like `$1` are always a single digit, so `${12}` needs
+        // braces and `$1` followed by an alphanumeric char doesn't
+        Part::Variable(ref name)
+            if name.starts_with(|c| matches!(c, '1'..='9'))
+        => name.len() > 1,
+        _ => next_alphanum(data, index),
+    }
+}
+
+fn next_alphanum(data: &[Part], index: usize) -> bool {
+    use self::Part::*;
     data.get(index+1).and_then(|item| {
         match item {
             Literal(s) => Some(s),
             Variable(_) => None,
         }
     }).and_then(|s| {
-        s.chars().next().map(|c| c.is_alphanumeric())
+        s.chars().next().map(|c| c.is_alphanumeric() || c == '_')
     }).unwrap_or(false)
 }
 
+fn write_escaped(f: &mut Formatter, text: &str) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => f.write("\\\""),
+            '\n' => f.write("\\n"),
+            '\t' => f.write("\\t"),
+            '\r' => f.write("\\r"),
+            // other backslashes are kept by the parser as is (e.g. in
+            // regular expressions), so only escape the ambiguous ones
+            '\\' => match chars.peek() {
+                None | Some(&'"') | Some(&'\'') | Some(&'\\') |
+                Some(&'n') | Some(&'t') | Some(&'r') |
+                Some(&'\n') | Some(&'\t') | Some(&'\r') => f.write("\\\\"),
+                Some(_) => f.write("\\"),
+            },
+            _ => {
+                let mut buf = [0; 4];
+                f.write(c.encode_utf8(&mut buf));
+            }
+        }
+    }
+}
+
 impl Displayable for Value {
     fn display(&self, f: &mut Formatter) {
-        use self::Item::*;
-        if self.data.is_empty() || self.has_specials() {
+        use self::Part::*;
+        let quoted = self.data.is_empty() || self.needs_quotes();
+        if quoted {
             f.write("\"");
-            for (index, item) in self.data.iter().enumerate() {
-                match *item {
-                    // TODO(tailhook) escape special chars
-                    Literal(ref v) => f.write(v),
-                    Variable(ref v) if next_alphanum(&self.data, index) => {
-                        f.write("${");
-                        f.write(v);
-                        f.write("}");
-                    }
-                    Variable(ref v) => {
-                        f.write("$");
-                        f.write(v);
-                    }
+        }
+        for (index, item) in self.data.iter().enumerate() {
+            match *item {
+                Literal(ref v) if quoted => write_escaped(f, v),
+                Literal(ref v) => f.write(v),
+                Variable(ref v) if needs_braces(&self.data, index) => {
+                    f.write("${");
+                    f.write(v);
+                    f.write("}");
                 }
-            }
-            f.write("\"");
-        } else {
-            for (index, item) in self.data.iter().enumerate() {
-                match *item {
-                    Literal(ref v) => f.write(v),
-                    Variable(ref v) if next_alphanum(&self.data, index) => {
-                        f.write("${");
-                        f.write(v);
-                        f.write("}");
-                    }
-                    Variable(ref v) => {
-                        f.write("$");
-                        f.write(v);
-                    }
+                Variable(ref v) => {
+                    f.write("$");
+                    f.write(v);
                 }
             }
         }
+        if quoted {
+            f.write("\"");
+        }
     }
 }
@@ -0,0 +1,56 @@
+extern crate nginx_config;
+
+use nginx_config::{parse_main, parse_directives, parse_directives_in};
+use nginx_config::context::{Context, allowed_contexts};
+
+
+#[test]
+fn directives_in_http() {
+    let dirs = parse_directives_in("\
+        gzip on;
+        server {
+            listen 80;
+            location / {
+                if ($slow) {
+                    limit_rate 10k;
+                }
+            }
+        }
+    ", Context::Http).unwrap();
+    assert_eq!(dirs.len(), 2);
+}
+
+#[test]
+fn directives_in_wrong_context() {
+    let err = parse_directives_in("listen 80;", Context::Http).unwrap_err();
+    assert_eq!(err.to_string(), "parse error: Parse error at 1:1\n\
+        directive \"listen\" is not allowed in http context\n");
+    // no context means no validation
+    assert!(parse_directives("listen 80;").is_ok());
+}
+
+#[test]
+fn if_in_server() {
+    parse_main("http { server { if ($x) { rewrite ^ /x; } } }").unwrap();
+    let err = parse_main("http { server { if ($x) { root /x; } } }")
+        .unwrap_err();
+    assert_eq!(err.to_string(), "parse error: Parse error at 1:27\n\
+        directive \"root\" is not allowed in if in server context\n");
+}
+
+#[test]
+fn stream_upstream() {
+    parse_main("stream { upstream x { server 127.0.0.1:53; } }").unwrap();
+    let err = parse_main("stream { upstream x { keepalive 10; } }")
+        .unwrap_err();
+    assert_eq!(err.to_string(), "parse error: Parse error at 1:23\n\
+        directive \"keepalive\" is not allowed in stream upstream context\n");
+}
+
+#[test]
+fn unknown_anywhere() {
+    let dirs = parse_directives("some_module_directive x;").unwrap();
+    assert_eq!(allowed_contexts(&dirs[0].item), None);
+    parse_main("http { server { some_module_directive x { y; } } }")
+        .unwrap();
+}
@@ -0,0 +1,99 @@
+extern crate nginx_config;
+#[cfg(test)] #[macro_use] extern crate pretty_assertions;
+
+use nginx_config::ast::{Item, Directive};
+use nginx_config::cst::Document;
+use nginx_config::visitors::visit_mutable;
+
+const CONFIG: &str = "\
+# main config
+worker_processes  4;   # tuned
+
+http {
+	server {
+		listen 80;
+		server_name   'example.com';
+
+		location /  {   # root
+		    root   /public ;
+		}
+		location /api {
+		    proxy_pass http://localhost:8000;
+		}
+	}
+}
+";
+
+fn modify<F: FnMut(&mut Directive)>(text: &str, f: F) -> String {
+    let doc = Document::parse(text).unwrap();
+    let mut main = doc.ast().clone();
+    visit_mutable(&mut main.directives, f);
+    doc.render(&main)
+}
+
+#[test]
+fn unchanged() {
+    let doc = Document::parse(CONFIG).unwrap();
+    assert_eq!(doc.render(doc.ast()), CONFIG);
+}
+
+#[test]
+fn node_text() {
+    let doc = Document::parse(CONFIG).unwrap();
+    let listen = doc.ast().all_directives()
+        .find(|d| d.item.directive_name() == "server_name").unwrap();
+    let node = doc.node_at(listen.position).unwrap();
+    assert_eq!(doc.text(node), "server_name   'example.com';");
+    let tokens = node.tokens().iter()
+        .map(|r| &doc.source()[r.clone()])
+        .collect::<Vec<_>>();
+    assert_eq!(tokens, ["server_name", "'example.com'"]);
+}
+
+#[test]
+fn change_nested() {
+    assert_eq!(modify(CONFIG, |dir| {
+        if let Item::ProxyPass(ref mut val) = dir.item {
+            *val = "http://localhost:9000".parse().unwrap();
+        }
+    }), CONFIG.replace("localhost:8000", "localhost:9000"));
+}
+
+#[test]
+fn change_top_level() {
+    assert_eq!(modify(CONFIG, |dir| {
+        if let Item::WorkerProcesses(_) = dir.item {
+            dir.item = Item::WorkerProcesses(
+                nginx_config::ast::WorkerProcesses::Auto);
+        }
+    }), CONFIG.replace("worker_processes  4;", "worker_processes auto;"));
+}
+
+#[test]
+fn remove_and_insert() {
+    let doc = Document::parse(CONFIG).unwrap();
+    let mut main = doc.ast().clone();
+    visit_mutable(&mut main.directives, |dir| {
+        if let Some(children) = dir.item.children_mut() {
+            children.retain(|d| d.item.directive_name() != "location");
+        }
+        if let Item::Server(ref mut srv) = dir.item {
+            let mut new = srv.directives[0].clone();
+            new.position = Default::default();
+            new.item = Item::Root("/www".parse().unwrap());
+            srv.directives.push(new);
+        }
+    });
+    assert_eq!(doc.render(&main), "\
+# main config
+worker_processes  4;   # tuned
+
+http {
+	server {
+		listen 80;
+		server_name   'example.com';
+		root /www;
+	}
+}
+");
+}
@@ -0,0 +1,89 @@
+extern crate nginx_config;
+#[cfg(test)] #[macro_use] extern crate pretty_assertions;
+
+use nginx_config::{parse_main, Pos, FileId};
+use nginx_config::diagnostic::{Diagnostic, Severity};
+
+
+fn render(text: &str) -> String {
+    let err = parse_main(text).unwrap_err();
+    Diagnostic::from(&err).render(text)
+}
+
+#[cfg(feature="fuzzy_errors")]
+#[test]
+fn suggestion() {
+    assert_eq!(render("http {\n    gzip_static onn;\n}\n"), "\
+error: Unexpected `onn`
+ --> 2:17
+  |
+2 |     gzip_static onn;
+  |                 ^^^
+  |
+  = note: Expected `on`, `off` or `always`
+  = help: did you mean `on`?
+");
+}
+
+#[test]
+fn context() {
+    assert_eq!(render("http {\n    user www-data;\n}\n"), "\
+error: directive \"user\" is not allowed in http context
+ --> 2:5
+  |
+2 |     user www-data;
+  |     ^^^^
+");
+}
+
+#[test]
+fn end_of_file() {
+    assert_eq!(render("http {\n    server {\n"), "\
+error: Unexpected `end of input`
+ --> 3:1
+  |
+  = note: Expected `}`
+");
+}
+
+#[test]
+fn tabs_and_wide_gutter() {
+    let text = "\n\n\n\n\n\n\n\n\nhttp {\n\tgzip maybe;\n}\n";
+    assert_eq!(render(text), "\
+error: Unexpected `maybe`
+  --> 11:14
+   |
+11 | \tgzip maybe;
+   | \t     ^^^^^
+   |
+   = note: Expected `on` or `off`
+");
+}
+
+#[test]
+fn custom_warning() {
+    let file = FileId::default();
+    let start = Pos::new(file, 1, 1, 0);
+    let end = Pos::new(file, 1, 14, 13);
+    let mut diag = Diagnostic::new(Severity::Warning,
+        "server_tokens should be off", start, end);
+    diag.path("nginx.conf").note("version is exposed in headers");
+    assert_eq!(diag.render("server_tokens on;\n"), "\
+warning: server_tokens should be off
+ --> nginx.conf:1:1
+  |
+1 | server_tokens on;
+  | ^^^^^^^^^^^^^
+  |
+  = note: version is exposed in headers
+");
+}
+
+#[test]
+fn color() {
+    let text = "daemon maybe;";
+    let err = parse_main(text).unwrap_err();
+    let output = Diagnostic::from(&err).color(true).render(text);
+    assert!(output.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: Unexpected"));
+    assert!(output.contains("\x1b[1;31m^^^^^\x1b[0m"));
+}
@@ -5,7 +5,7 @@ extern crate regex;
 use std::io::Read;
 use std::fs::File;
 
-use nginx_config::parse_main;
+use nginx_config::{parse_main, ErrorKind};
 
 
 fn test_error(filename: &str) {
@@ -23,10 +23,109 @@ fn test_error(filename: &str) {
     assert_eq!(err_text, expected);
 }
 
-#[test] fn invalid_directive() { test_error("invalid_directive"); }
-#[test] fn invalid_directive_in_block() {
-    test_error("invalid_directive_in_block");
+// "did you mean" options are only listed with `fuzzy_errors`
+#[cfg(feature="fuzzy_errors")]
+#[test] fn invalid_option() { test_error("invalid_option"); }
+#[test] fn invalid_option_in_block() {
+    test_error("invalid_option_in_block");
 }
-#[test] fn invalid_directive_with_newline() {
-    test_error("invalid_directive_with_newline");
+#[cfg(feature="fuzzy_errors")]
+#[test] fn invalid_option_with_newline() {
+    test_error("invalid_option_with_newline");
+}
+#[test] fn nested_main_directive() { test_error("nested_main_directive"); }
+#[test] fn location_at_top_level() { test_error("location_at_top_level"); }
+#[test] fn proxy_pass_in_upstream() { test_error("proxy_pass_in_upstream"); }
+#[test] fn udp_listen_in_http() { test_error("udp_listen_in_http"); }
+#[test] fn http_listen_in_stream() { test_error("http_listen_in_stream"); }
+#[test] fn invalid_session_cache() { test_error("invalid_session_cache"); }
+#[test] fn invalid_grpc_scheme() { test_error("invalid_grpc_scheme"); }
+#[test] fn invalid_rate() { test_error("invalid_rate"); }
+
+fn span(text: &str) -> (String, String) {
+    let (start, end) = parse_main(text).unwrap_err().span();
+    (start.to_string(), end.to_string())
+}
+
+#[cfg(feature="fuzzy_errors")]
+#[test]
+fn invalid_value() {
+    let err = parse_main("http {\n    gzip_static onn;\n}").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidValue);
+    assert_eq!(err.directive(), Some("gzip_static"));
+    assert_eq!(err.suggestions(), &["on".to_string()]);
+    assert_eq!(span("http {\n    gzip_static onn;\n}"),
+               ("2:17".into(), "2:20".into()));
+}
+
+#[test]
+fn invalid_value_kind() {
+    let err = parse_main("daemon maybe;").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidValue);
+    assert_eq!(err.directive(), Some("daemon"));
+    assert_eq!(span("daemon maybe;"), ("1:8".into(), "1:13".into()));
+    // rejected by validation of the value
+    let err = parse_main("http { client_max_body_size 10X; }").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidValue);
+    assert_eq!(err.directive(), Some("client_max_body_size"));
+    // rejected by `FromStr` of a number
+    let err = parse_main("worker_processes x;").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidValue);
+    assert_eq!(err.directive(), Some("worker_processes"));
+}
+
+#[test]
+fn unknown_directive() {
+    // unknown directives are kept as is, so there is no error kind for them
+    assert!(parse_main("http { no_such_directive 1; }").is_ok());
+}
+
+#[test]
+fn argument_count() {
+    let err = parse_main("daemon;").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ArgumentCount);
+    assert_eq!(err.directive(), Some("daemon"));
+    let err = parse_main("daemon on off;").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ArgumentCount);
+    assert_eq!(span("daemon on off;"), ("1:11".into(), "1:14".into()));
+}
+
+#[test]
+fn unterminated_string() {
+    let err = parse_main("pid \"/run/nginx.pid;\n").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnterminatedString);
+    assert_eq!(err.directive(), Some("pid"));
+}
+
+#[test]
+fn unexpected_block() {
+    let err = parse_main("daemon on {}").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedBlock);
+    assert_eq!(err.directive(), Some("daemon"));
+}
+
+#[test]
+fn unexpected_eof() {
+    let err = parse_main("http {\n    server {\n").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(err.directive(), Some("server"));
+}
+
+#[test]
+fn context_error() {
+    let err = parse_main("http {\n    user www-data;\n}").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Context);
+    assert_eq!(err.directive(), Some("user"));
+    assert_eq!(span("http {\n    user www-data;\n}"),
+               ("2:5".into(), "2:9".into()));
+}
+
+#[test]
+fn next_upstream_flag_messages() {
+    let err = parse_main("http { proxy_next_upstream bad; }").unwrap_err();
+    assert!(err.to_string().contains("invalid proxy upstream flag"),
+            "{}", err);
+    let err = parse_main("http { fastcgi_next_upstream bad; }").unwrap_err();
+    assert!(err.to_string().contains("invalid fastcgi upstream flag"),
+            "{}", err);
 }
@@ -0,0 +1,274 @@
+extern crate nginx_config;
+#[cfg(test)] #[macro_use] extern crate pretty_assertions;
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use nginx_config::ErrorKind;
+use nginx_config::include::{Loader, IncludeError, FsLoader};
+use nginx_config::include::parse_file_with_includes;
+use nginx_config::include::parse_file_with_source_map;
+#[cfg(feature="fuzzy_errors")]
+use nginx_config::diagnostic::Diagnostic;
+
+
+struct Memory(BTreeMap<PathBuf, &'static str>);
+
+impl Loader for Memory {
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        self.0.get(path).map(|x| x.to_string())
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<String>> {
+        let mut names = self.0.keys()
+            .filter_map(|p| p.strip_prefix(path).ok())
+            .filter_map(|p| p.components().next())
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.dedup();
+        // loaders are not required to return sorted names
+        names.reverse();
+        Ok(names)
+    }
+}
+
+fn memory(files: &[(&str, &'static str)]) -> Memory {
+    Memory(files.iter().map(|&(p, t)| (PathBuf::from(p), t)).collect())
+}
+
+fn resolve(files: &[(&str, &'static str)]) -> Result<String, IncludeError> {
+    parse_file_with_includes("/etc/nginx/nginx.conf", &memory(files))
+        .map(|main| main.to_string())
+}
+
+#[test]
+fn single_file() {
+    assert_eq!(resolve(&[
+        ("/etc/nginx/nginx.conf", "include mime.types;\nhttp { gzip on; }"),
+        ("/etc/nginx/mime.types", "types { text/html html; }"),
+    ]).unwrap(), "\
+types {
+    text/html html;
+}
+
+http {
+    gzip on;
+}
+");
+}
+
+#[test]
+fn glob_sorted() {
+    assert_eq!(resolve(&[
+        ("/etc/nginx/nginx.conf", "http { include sites-*/*.conf; }"),
+        ("/etc/nginx/sites-b/1.conf", "server { listen 81; }"),
+        ("/etc/nginx/sites-a/2.conf", "server { listen 80; }"),
+        ("/etc/nginx/sites-a/.hidden.conf", "server { listen 82; }"),
+        ("/etc/nginx/sites-a/3.txt", "server { listen 83; }"),
+    ]).unwrap(), "\
+http {
+    server {
+        listen 80;
+    }
+
+    server {
+        listen 81;
+    }
+}
+");
+}
+
+#[test]
+fn nested_and_absolute() {
+    assert_eq!(resolve(&[
+        ("/etc/nginx/nginx.conf", "http { include conf.d/*.conf; }"),
+        ("/etc/nginx/conf.d/a.conf",
+         "server { include /usr/share/nginx/common; }"),
+        ("/usr/share/nginx/common", "include snippets/gzip;"),
+        ("/etc/nginx/snippets/gzip", "gzip on;"),
+    ]).unwrap(), "\
+http {
+    server {
+        gzip on;
+    }
+}
+");
+}
+
+#[test]
+fn glob_without_matches() {
+    assert_eq!(resolve(&[
+        ("/etc/nginx/nginx.conf", "include modules/*.conf;\ndaemon off;"),
+    ]).unwrap(), "daemon off;\n");
+}
+
+#[test]
+fn empty_and_comment_only_files() {
+    assert_eq!(resolve(&[
+        ("/etc/nginx/nginx.conf", "\
+http {
+    include conf.d/*.conf;
+}
+include extra.conf;
+daemon off;
+"),
+        ("/etc/nginx/conf.d/a.conf", ""),
+        ("/etc/nginx/conf.d/b.conf", "# only a comment\n"),
+        ("/etc/nginx/conf.d/c.conf", "gzip on;\n# after gzip\n"),
+        ("/etc/nginx/extra.conf", "# disabled\n# daemon on;\n"),
+    ]).unwrap(), "\
+http {
+    # only a comment
+    gzip on;
+    # after gzip
+}
+# disabled
+# daemon on;
+daemon off;
+");
+}
+
+#[test]
+fn comments_at_end_of_main() {
+    assert_eq!(resolve(&[
+        ("/etc/nginx/nginx.conf", "daemon off;\n# include\ninclude a.conf;"),
+        ("/etc/nginx/a.conf", "# nothing here\n"),
+    ]).unwrap(), "daemon off;\n# include\n# nothing here\n");
+}
+
+#[test]
+fn cycle() {
+    let err = resolve(&[
+        ("/etc/nginx/nginx.conf", "http { include a.conf; }"),
+        ("/etc/nginx/a.conf", "include b.conf;"),
+        ("/etc/nginx/b.conf", "include a.conf;"),
+    ]).unwrap_err();
+    match err {
+        IncludeError::Cycle { chain } => assert_eq!(chain, [
+            "/etc/nginx/nginx.conf",
+            "/etc/nginx/a.conf",
+            "/etc/nginx/b.conf",
+            "/etc/nginx/a.conf",
+        ].iter().map(PathBuf::from).collect::<Vec<_>>()),
+        e => panic!("unexpected error {}", e),
+    }
+}
+
+#[test]
+fn missing_file() {
+    let err = resolve(&[
+        ("/etc/nginx/nginx.conf", "include missing.conf;"),
+    ]).unwrap_err();
+    assert_eq!(err.to_string(),
+        "error reading \"/etc/nginx/missing.conf\": entity not found");
+}
+
+#[test]
+fn variables() {
+    let err = resolve(&[
+        ("/etc/nginx/nginx.conf", "\nhttp {\n    include $x.conf;\n}"),
+    ]).unwrap_err();
+    assert_eq!(err.to_string(), "\"/etc/nginx/nginx.conf\", 3:5: \
+        variables are not allowed in include path");
+}
+
+#[test]
+fn wrong_context() {
+    let err = resolve(&[
+        ("/etc/nginx/nginx.conf", "http {\n    include conf.d/*.conf;\n}"),
+        ("/etc/nginx/conf.d/a.conf", "gzip on;\nlocation / {}"),
+    ]).unwrap_err();
+    match err {
+        IncludeError::Parse { ref path, ref error, .. } => {
+            assert_eq!(path, Path::new("/etc/nginx/conf.d/a.conf"));
+            assert_eq!(error.kind(), ErrorKind::Context);
+            assert_eq!(error.directive(), Some("location"));
+        }
+        ref e => panic!("unexpected error {}", e),
+    }
+    // same file is fine in the server context
+    assert!(resolve(&[
+        ("/etc/nginx/nginx.conf", "http { server { include a.conf; } }"),
+        ("/etc/nginx/a.conf", "gzip on;\nlocation / {}"),
+    ]).is_ok());
+    // nested blocks are checked too
+    assert!(resolve(&[
+        ("/etc/nginx/nginx.conf", "http { include a.conf; }"),
+        ("/etc/nginx/a.conf", "server { user nobody; }"),
+    ]).is_err());
+}
+
+#[test]
+fn filesystem() {
+    let main = parse_file_with_includes("tests/includes/nginx.conf",
+                                        &FsLoader).unwrap();
+    assert_eq!(main.to_string(), "\
+http {
+    server {
+        listen 80;
+    }
+    gzip on;
+}
+");
+}
+
+#[test]
+fn source_map() {
+    let fs = memory(&[
+        ("/etc/nginx/nginx.conf", "\
+daemon off;
+http {
+    include conf.d/*.conf;
+}"),
+        ("/etc/nginx/conf.d/api.conf", "\
+server {
+    include snippets/gzip.conf;
+    listen 80;
+}"),
+        ("/etc/nginx/snippets/gzip.conf", "\n\n  gzip on;"),
+    ]);
+    let (main, map) = parse_file_with_source_map(
+        "/etc/nginx/nginx.conf", &fs).unwrap();
+    assert_eq!(map.len(), 3);
+    let described = main.all_directives()
+        .map(|d| map.describe(d.position))
+        .collect::<Vec<_>>();
+    assert_eq!(described, [
+        "nginx.conf:1:1",
+        "nginx.conf:2:1",
+        "conf.d/api.conf:1:1 (included from nginx.conf:3)",
+        "snippets/gzip.conf:3:3 (included from conf.d/api.conf:2) \
+            (included from nginx.conf:3)",
+        "conf.d/api.conf:3:5 (included from nginx.conf:3)",
+    ]);
+    let gzip = main.all_directives()
+        .find(|d| d.item.directive_name() == "gzip").unwrap();
+    assert_eq!(map.path(gzip.position.file),
+               Path::new("/etc/nginx/snippets/gzip.conf"));
+    assert_eq!(map.include_chain(gzip.position.file).len(), 2);
+}
+
+#[cfg(feature="fuzzy_errors")]
+#[test]
+fn parse_error_diagnostic() {
+    let loader = memory(&[
+        ("/etc/nginx/nginx.conf", "http {\n    include conf.d/*.conf;\n}"),
+        ("/etc/nginx/conf.d/api.conf", "server {\n    listen 80 bad;\n}"),
+    ]);
+    let err = parse_file_with_includes("/etc/nginx/nginx.conf", &loader)
+        .unwrap_err();
+    let diag = Diagnostic::from_include_error(&err).unwrap();
+    let source = loader.read_file(Path::new("/etc/nginx/conf.d/api.conf"))
+        .unwrap();
+    assert_eq!(diag.render(&source), "\
+error: Unexpected `bad`
+ --> /etc/nginx/conf.d/api.conf:2:15
+  |
+2 |     listen 80 bad;
+  |               ^^^
+  |
+  = note: Expected `one of 16 options`
+  = note: included from /etc/nginx/nginx.conf:2
+");
+}
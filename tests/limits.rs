@@ -0,0 +1,33 @@
+extern crate nginx_config;
+#[cfg(test)] #[macro_use] extern crate pretty_assertions;
+
+use nginx_config::parse_main;
+use nginx_config::ast::Item;
+
+
+#[test]
+fn quoted_zone_names() {
+    let ast = parse_main(r#"
+        http {
+            limit_conn_zone $binary_remote_addr "zone=addr:10m";
+            limit_req_zone $binary_remote_addr 'zone=one:10m' rate=1r/s;
+            server {
+                limit_conn "addr" 1;
+                limit_req "zone=one" burst=5;
+            }
+        }
+    "#).unwrap();
+    let mut declared = Vec::new();
+    let mut used = Vec::new();
+    for dir in ast.all_directives() {
+        match dir.item {
+            Item::LimitConnZone(ref z) => declared.push(z.zone.clone()),
+            Item::LimitReqZone(ref z) => declared.push(z.zone.clone()),
+            Item::LimitConn { ref zone, .. } => used.push(zone.clone()),
+            Item::LimitReq(ref r) => used.push(r.zone.clone()),
+            _ => {}
+        }
+    }
+    assert_eq!(declared, vec!["addr", "one"]);
+    assert_eq!(used, declared);
+}
@@ -0,0 +1,62 @@
+extern crate nginx_config;
+#[cfg(test)] #[macro_use] extern crate pretty_assertions;
+
+use nginx_config::parse_directives_in;
+use nginx_config::ast::{Item, ThreadPool, WorkerCpuAffinity};
+use nginx_config::context::Context;
+
+fn parse_item(text: &str) -> Item {
+    parse_directives_in(text, Context::Main).unwrap()
+        .into_iter().next().unwrap().item
+}
+
+#[test]
+fn quoted_user() {
+    assert_eq!(parse_item(r#"user "nginx";"#), Item::User {
+        user: "nginx".into(),
+        group: None,
+    });
+    assert_eq!(parse_item(r#"user 'www' "www-data";"#), Item::User {
+        user: "www".into(),
+        group: Some("www-data".into()),
+    });
+}
+
+#[test]
+fn quoted_env() {
+    assert_eq!(parse_item(r#"env "FOO=bar";"#), Item::Env {
+        name: "FOO".into(),
+        value: Some("bar".into()),
+    });
+    assert_eq!(parse_item(r#"env 'TZ';"#), Item::Env {
+        name: "TZ".into(),
+        value: None,
+    });
+    assert_eq!(parse_item(r#"env "A=b c";"#).to_string(),
+               "env \"A=b c\";\n");
+}
+
+#[test]
+fn quoted_worker_cpu_affinity() {
+    assert_eq!(parse_item(r#"worker_cpu_affinity "0001" '0010';"#),
+        Item::WorkerCpuAffinity(WorkerCpuAffinity::Masks(
+            vec!["0001".into(), "0010".into()])));
+    assert_eq!(parse_item(r#"worker_cpu_affinity auto "0101";"#),
+        Item::WorkerCpuAffinity(WorkerCpuAffinity::Auto(
+            Some("0101".into()))));
+}
+
+#[test]
+fn quoted_thread_pool() {
+    assert_eq!(parse_item(r#"thread_pool "default" threads=32;"#),
+        Item::ThreadPool(ThreadPool {
+            name: "default".into(),
+            threads: 32,
+            max_queue: None,
+        }));
+}
+
+#[test]
+fn variables_rejected() {
+    assert!(parse_directives_in("user $user;", Context::Main).is_err());
+}
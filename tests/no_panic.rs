@@ -0,0 +1,91 @@
+//! Randomized checks that the parser never panics
+//!
+//! Inputs are token soups made of nginx-ish words and mutated copies of the
+//! configs in `tests/configs`. The `fuzz` directory has the same checks as
+//! cargo-fuzz targets for longer runs.
+extern crate nginx_config;
+#[macro_use] extern crate proptest;
+
+mod strategies;
+
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use nginx_config::{parse_main, parse_directives_in, parse_main_recovering};
+use nginx_config::ast::Main;
+use nginx_config::context::Context::*;
+use proptest::test_runner::Config;
+
+
+fn check_roundtrip(ast: &Main, input: &str) {
+    let text = ast.to_string();
+    let again = parse_main(&text).unwrap_or_else(|e| {
+        panic!("input {:?} formatted as {:?}: {}", input, text, e)
+    });
+    assert_eq!(again.to_string(), text, "input: {:?}", input);
+}
+
+fn check(input: &str) {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        if let Ok(ast) = parse_main(input) {
+            check_roundtrip(&ast, input);
+        }
+        parse_main_recovering(input);
+        for &ctx in &[Http, Server, Location, Upstream, Stream] {
+            if let Ok(directives) = parse_directives_in(input, ctx) {
+                let ast = Main { directives, end_comments: Vec::new() };
+                let text = ast.to_string();
+                let again = parse_directives_in(&text, ctx)
+                    .unwrap_or_else(|e| {
+                        panic!("input {:?} formatted as {:?}: {}",
+                               input, text, e)
+                    });
+                let again = Main { directives: again,
+                                   end_comments: Vec::new() };
+                assert_eq!(again.to_string(), text, "input: {:?}", input);
+            }
+        }
+    }));
+    if result.is_err() {
+        panic!("parser panicked on {:?}", input);
+    }
+}
+
+fn configs() -> Vec<String> {
+    let mut configs = fs::read_dir("tests/configs").unwrap()
+        .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect::<Vec<_>>();
+    configs.sort();
+    configs
+}
+
+proptest! {
+    #![proptest_config(Config::with_cases(1000))]
+
+    #[test]
+    fn token_soups(input in strategies::token_soup()) {
+        check(&input);
+    }
+
+    #[test]
+    fn mutated_configs(input in strategies::mutated(configs())) {
+        check(&input);
+    }
+}
+
+#[test]
+fn known_crashers() {
+    for input in &[
+        "",
+        "}",
+        "\"",
+        "'\\",
+        "return 301 $;",
+        "set $x \"${\";",
+        "index ${};",
+        "location ~ ${x {}",
+        "http { server { listen *:; } }",
+    ] {
+        check(input);
+    }
+}
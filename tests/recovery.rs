@@ -0,0 +1,95 @@
+extern crate nginx_config;
+#[cfg(test)] #[macro_use] extern crate pretty_assertions;
+
+use nginx_config::{parse_main, parse_main_recovering};
+use nginx_config::parse_directives_recovering;
+
+
+fn errors(text: &str) -> Vec<String> {
+    let (_, errors) = parse_main_recovering(text);
+    errors.iter().map(|e| e.to_string()).collect()
+}
+
+#[test]
+fn valid_config() {
+    let text = "\
+        worker_processes 4;
+        http {
+            server {
+                listen 80;
+            }
+        }
+        # end
+    ";
+    let (main, errors) = parse_main_recovering(text);
+    assert!(errors.is_empty());
+    assert_eq!(main.to_string(), parse_main(text).unwrap().to_string());
+}
+
+#[test]
+fn skips_bad_directives() {
+    let (main, errors) = parse_main_recovering("\
+        http {
+            gzip maybe;
+            server {
+                listen 80 extra_option;
+                root /var/www;
+            }
+            gzip_static nope;
+            etag on;
+        }
+        daemon on;
+    ");
+    assert_eq!(errors.len(), 3);
+    assert_eq!(main.to_string(), "\
+        http {\n    \
+            server {\n        \
+                root /var/www;\n    \
+            }\n    \
+            etag on;\n\
+        }\n\
+        daemon on;\n");
+}
+
+#[test]
+fn error_positions() {
+    let positions = errors("\
+gzip on;
+http {
+    gzip maybe;
+    location / {
+        root /x;
+    }
+}
+}
+worker_processes many;
+")
+        .iter()
+        .map(|e| e.lines().next().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(positions, vec![
+        "parse error: Parse error at 1:1",
+        "parse error: Parse error at 3:10",
+        "parse error: Parse error at 4:5",
+        "parse error: Parse error at 8:1",
+        "parse error: Parse error at 9:18",
+    ]);
+}
+
+#[test]
+fn skips_bad_blocks() {
+    let (dirs, errors) = parse_directives_recovering("\
+        location ~ { root /x; }
+        location / { alias /y; }
+    ");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(dirs.len(), 1);
+    assert_eq!(dirs[0].to_string(), "location / {\n    alias /y;\n}\n");
+}
+
+#[test]
+fn unclosed_block() {
+    let (main, errors) = parse_main_recovering("daemon on;\nhttp {\n");
+    assert_eq!(main.directives.len(), 1);
+    assert_eq!(errors.len(), 1);
+}
@@ -0,0 +1,367 @@
+#![cfg(feature="simulation")]
+extern crate nginx_config;
+
+use nginx_config::parse_directives_in;
+use std::collections::HashMap;
+
+use nginx_config::ast::{Directive, IfCondition, Item, RewriteFlag};
+use nginx_config::context::Context;
+use nginx_config::rewriting::{evaluate, Evaluation, Outcome, Request};
+use nginx_config::rewriting::{RewriteError, evaluate_with, check_condition};
+use nginx_config::rewriting::{FakeFileSystem, FileSystem, ConditionResult};
+
+
+fn parse(text: &str) -> Vec<Directive> {
+    parse_directives_in(text, Context::Server).unwrap()
+}
+
+fn run<'a>(dirs: &'a [Directive], uri: &str) -> Evaluation<'a> {
+    evaluate(dirs, &Request::new(uri)).unwrap()
+}
+
+fn location(ev: &Evaluation) -> Option<String> {
+    ev.location.as_ref().map(|m| m.location.pattern.to_string())
+}
+
+#[test]
+fn server_level() {
+    let dirs = parse(r"
+        rewrite ^/old/(.*)$ /new/$1;
+        location /new/ {}
+        location / {}
+    ");
+    let ev = run(&dirs, "/old/x");
+    assert_eq!(ev.uri, "/new/x");
+    assert_eq!(ev.outcome, Outcome::Serve);
+    assert_eq!(ev.flag, None);
+    assert_eq!(location(&ev).as_deref(), Some("/new/"));
+    assert_eq!(ev.uri_changes, 0);
+}
+
+#[test]
+fn last_and_break() {
+    let dirs = parse(r"
+        location /a/ {
+            rewrite ^/a/(.*)$ /b/$1 last;
+            return 404;
+        }
+        location /b/ {
+            rewrite ^/b/(.*)$ /c/$1 break;
+            return 403;
+        }
+        location /c/ {
+            return 200 c;
+        }
+    ");
+    let ev = run(&dirs, "/a/x");
+    assert_eq!(ev.uri, "/c/x");
+    assert_eq!(ev.outcome, Outcome::Serve);
+    assert_eq!(ev.flag, Some(RewriteFlag::Break));
+    assert_eq!(location(&ev).as_deref(), Some("/b/"));
+    assert_eq!(ev.uri_changes, 1);
+
+    let ev = run(&dirs, "/c/x");
+    assert_eq!(ev.outcome, Outcome::Response {
+        code: 200, text: Some("c".into()) });
+    assert_eq!(ev.flag, None);
+}
+
+#[test]
+fn break_directive_keeps_location() {
+    let dirs = parse(r"
+        location / {
+            rewrite ^/a$ /b;
+            break;
+        }
+        location /b {
+            return 404;
+        }
+    ");
+    let ev = run(&dirs, "/a");
+    assert_eq!(ev.uri, "/b");
+    assert_eq!(ev.outcome, Outcome::Serve);
+    assert_eq!(ev.flag, Some(RewriteFlag::Break));
+    assert_eq!(location(&ev).as_deref(), Some("/"));
+    assert_eq!(ev.uri_changes, 0);
+}
+
+#[test]
+fn rewrite_without_flag_rematches() {
+    let dirs = parse(r"
+        location /a/ {
+            rewrite ^/a/(.*)$ /b/$1;
+            set $seen yes;
+        }
+        location /b/ {}
+    ");
+    let ev = run(&dirs, "/a/x");
+    assert_eq!(ev.uri, "/b/x");
+    assert_eq!(location(&ev).as_deref(), Some("/b/"));
+    assert_eq!(ev.variables.get("seen").map(|x| &x[..]), Some("yes"));
+    assert_eq!(ev.uri_changes, 1);
+}
+
+#[test]
+fn redirects() {
+    let dirs = parse(r"
+        rewrite ^/perm$ /new permanent;
+        rewrite ^/temp$ /new? redirect;
+        rewrite ^/abs/(.*)$ https://example.com/$1;
+        rewrite ^/args$ /new?x=1 permanent;
+        location /ret {
+            return 301 https://$host$request_uri;
+        }
+    ");
+    let ev = run(&dirs, "/perm?a=1");
+    assert_eq!(ev.outcome,
+               Outcome::Redirect { code: 301, url: "/new?a=1".into() });
+    assert_eq!(ev.flag, Some(RewriteFlag::Permanent));
+    assert!(ev.location.is_none());
+    assert_eq!(run(&dirs, "/temp?a=1").outcome,
+               Outcome::Redirect { code: 302, url: "/new".into() });
+    let ev = run(&dirs, "/abs/x");
+    assert_eq!(ev.outcome,
+        Outcome::Redirect { code: 302, url: "https://example.com/x".into() });
+    assert_eq!(ev.flag, Some(RewriteFlag::Redirect));
+    assert_eq!(run(&dirs, "/args?a=1").outcome,
+               Outcome::Redirect { code: 301, url: "/new?x=1&a=1".into() });
+
+    let req = Request::new("/ret?q").variable("host", "example.org");
+    let ev = evaluate(&dirs, &req).unwrap();
+    assert_eq!(ev.outcome, Outcome::Redirect {
+        code: 301, url: "https://example.org/ret?q".into() });
+    assert_eq!(ev.flag, None);
+    assert_eq!(location(&ev).as_deref(), Some("/ret"));
+}
+
+#[test]
+fn args() {
+    let dirs = parse(r"
+        location /a {
+            rewrite ^/a(.*)$ /b?from=$1 break;
+        }
+        location /c {
+            rewrite ^ /d? break;
+        }
+    ");
+    let ev = run(&dirs, "/a1?x=y");
+    assert_eq!(ev.uri, "/b");
+    assert_eq!(ev.args.as_deref(), Some("from=1&x=y"));
+    let ev = run(&dirs, "/c?x=y");
+    assert_eq!(ev.uri, "/d");
+    assert_eq!(ev.args, None);
+}
+
+#[test]
+fn set_and_if() {
+    let dirs = parse(r#"
+        set $flag "";
+        if ($http_user_agent ~* (bot|crawler)) {
+            set $flag $1;
+        }
+        if ($flag = "") {
+            break;
+        }
+        if ($flag) {
+            return 403 "no $flag";
+        }
+        location / {
+            return 200;
+        }
+    "#);
+    let req = Request::new("/").variable("http_user_agent", "SomeBOT/1.0");
+    let ev = evaluate(&dirs, &req).unwrap();
+    assert_eq!(ev.outcome, Outcome::Response {
+        code: 403, text: Some("no BOT".into()) });
+    assert_eq!(ev.variables.get("flag").map(|x| &x[..]), Some("BOT"));
+
+    let req = Request::new("/").variable("http_user_agent", "Mozilla");
+    let ev = evaluate(&dirs, &req).unwrap();
+    // `break` stops only directives on the server level
+    assert_eq!(ev.outcome, Outcome::Response { code: 200, text: None });
+    assert_eq!(ev.flag, None);
+}
+
+#[test]
+fn location_captures() {
+    let dirs = parse(r#"
+        location ~ ^/user/(\d+)$ {
+            rewrite ^ /profile?id=$1 break;
+        }
+    "#);
+    // regex without groups keeps captures of the location
+    let ev = run(&dirs, "/user/15");
+    assert_eq!(ev.uri, "/profile");
+    assert_eq!(ev.args.as_deref(), Some("id=15"));
+}
+
+#[test]
+fn quoted_regex() {
+    let dirs = parse(r#"
+        location / {
+            rewrite "^/(\d{4})/(\d{2})$" /archive?y=$1&m=$2 break;
+        }
+    "#);
+    let ev = run(&dirs, "/2020/01");
+    assert_eq!(ev.uri, "/archive");
+    assert_eq!(ev.args.as_deref(), Some("y=2020&m=01"));
+}
+
+#[test]
+fn cycle() {
+    let dirs = parse(r"
+        location /a { rewrite ^ /b last; }
+        location /b { rewrite ^ /a last; }
+    ");
+    match evaluate(&dirs, &Request::new("/a")) {
+        Err(RewriteError::Cycle(ref uri)) => assert_eq!(uri, "/b"),
+        res => panic!("unexpected {:?}", res),
+    }
+
+    let dirs = parse(r#"
+        location ~ ^/(\d+)$ { rewrite ^/(\d+)$ /$1x last; }
+        location ~ "^/\d+x{10}$" { return 204; }
+        location / { return 200; }
+    "#);
+    let ev = run(&dirs, "/1");
+    assert_eq!(ev.uri_changes, 1);
+    let dirs = parse(r"
+        location /x { rewrite ^/x(.*)$ /x$1x last; }
+    ");
+    match evaluate(&dirs, &Request::new("/x")) {
+        Err(RewriteError::Cycle(ref uri)) => assert_eq!(uri, "/xxxxxxxxxxxx"),
+        res => panic!("unexpected {:?}", res),
+    }
+}
+
+#[test]
+fn break_directive_roundtrip() {
+    let dirs = parse("if ($x) { break; }");
+    assert_eq!(dirs[0].to_string(), "if ($x) {\n    break;\n}\n");
+}
+
+fn condition(text: &str) -> IfCondition {
+    let dirs = parse(&format!("if {} {{}}", text));
+    match dirs[0].item {
+        Item::If(ref cond) => cond.condition.clone(),
+        ref item => panic!("unexpected {:?}", item),
+    }
+}
+
+fn check(text: &str, vars: &[(&str, &str)]) -> ConditionResult {
+    let vars = vars.iter()
+        .map(|&(k, v)| (k.to_string(), v.to_string()))
+        .collect::<HashMap<_, _>>();
+    let fs = FakeFileSystem::new()
+        .file("/www/index.html")
+        .executable("/www/cgi/run")
+        .dir("/www/empty/");
+    check_condition(&condition(text), &vars, &fs).unwrap()
+}
+
+fn holds(text: &str, vars: &[(&str, &str)]) -> bool {
+    check(text, vars).holds
+}
+
+#[test]
+fn conditions() {
+    assert!(holds("($x)", &[("x", "yes")]));
+    assert!(!holds("($x)", &[("x", "0")]));
+    assert!(!holds("($x)", &[]));
+    assert!(holds("($x = abc)", &[("x", "abc")]));
+    assert!(holds(r#"($x = "a b")"#, &[("x", "a b")]));
+    assert!(holds("($x = '')", &[]));
+    assert!(holds("($x != abc)", &[("x", "abd")]));
+    assert!(holds("($x = $y)", &[("x", "1"), ("y", "1")]));
+    assert!(!holds("($x = $y)", &[("x", "1"), ("y", "2")]));
+    assert!(holds("($x ~ ^ab)", &[("x", "abc")]));
+    assert!(!holds("($x ~ ^ab)", &[("x", "ABC")]));
+    assert!(holds("($x ~* ^ab)", &[("x", "ABC")]));
+    assert!(holds("($x !~ ^ab)", &[("x", "ABC")]));
+    assert!(!holds("($x !~* ^ab)", &[("x", "ABC")]));
+}
+
+#[test]
+fn file_conditions() {
+    let root = [("root", "/www")];
+    assert!(holds("(-f $root/index.html)", &root));
+    assert!(!holds("(-f $root/empty)", &root));
+    assert!(holds("(!-f $root/missing)", &root));
+    assert!(holds("(-d $root/empty)", &root));
+    assert!(holds("(-d $root/cgi/)", &root));
+    assert!(!holds("(!-d $root)", &root));
+    assert!(holds("(-e $root/index.html)", &root));
+    assert!(holds("(-e /)", &root));
+    assert!(holds("(!-e $root/missing)", &root));
+    assert!(holds("(-x $root/cgi/run)", &root));
+    assert!(!holds("(-x $root/index.html)", &root));
+    assert!(holds("(!-x $root/missing)", &root));
+}
+
+#[test]
+fn condition_captures() {
+    let res = check(r"($x ~ ^/(\w+)/(\d+)?)", &[("x", "/user/x")]);
+    assert!(res.holds);
+    assert_eq!(res.captures, Some(vec![
+        Some("/user/".into()), Some("user".into()), None]));
+    // negated match still captures
+    let res = check(r"($x !~ ^/(\w+))", &[("x", "/user")]);
+    assert!(!res.holds);
+    assert_eq!(res.captures.unwrap()[1].as_deref(), Some("user"));
+    // regex without groups doesn't replace captures
+    let res = check(r"($x ~ ^/)", &[("x", "/user")]);
+    assert_eq!(res, ConditionResult { holds: true, captures: None });
+    let res = check(r"($x ~ ^/(\w+))", &[("x", "nope")]);
+    assert_eq!(res, ConditionResult { holds: false, captures: None });
+}
+
+#[test]
+fn closure_variables() {
+    let lookup = |name: &str| match name {
+        "host" => Some(String::from("example.com")),
+        _ => None,
+    };
+    let cond = condition(r"($host ~ ^(www\.)?example\.com$)");
+    assert!(check_condition(&cond, &lookup, &FakeFileSystem::new())
+            .unwrap().holds);
+}
+
+#[test]
+fn fake_filesystem() {
+    let fs = FakeFileSystem::new().file("/a/b/c.txt");
+    assert!(fs.is_dir("/a") && fs.is_dir("/a/b/") && fs.is_dir("/"));
+    assert!(fs.is_file("/a/b/c.txt") && !fs.is_dir("/a/b/c.txt"));
+    assert!(!fs.exists("/a/c"));
+    assert!(fs.is_executable("/a/b") && !fs.is_executable("/a/b/c.txt"));
+}
+
+#[test]
+fn engine_with_filesystem() {
+    let dirs = parse(r#"
+        root /www;
+        location / {
+            if (!-e $document_root$uri) {
+                rewrite ^/(.*)$ /index.php?q=$1 last;
+            }
+        }
+        location ~ ^/(.+)\.php$ {
+            if ($args ~ ^q=(.*)$) {
+                set $page $1;
+            }
+            return 200 "$1 $page";
+        }
+    "#);
+    let fs = FakeFileSystem::new().file("/www/style.css");
+    let req = Request::new("/style.css").variable("document_root", "/www");
+    let ev = evaluate_with(&dirs, &req, &fs).unwrap();
+    assert_eq!(ev.outcome, Outcome::Serve);
+    assert_eq!(ev.uri, "/style.css");
+
+    let req = Request::new("/about").variable("document_root", "/www");
+    let ev = evaluate_with(&dirs, &req, &fs).unwrap();
+    assert_eq!(ev.uri, "/index.php");
+    assert_eq!(ev.uri_changes, 1);
+    // captures of `if` replace the ones of the location
+    assert_eq!(ev.outcome, Outcome::Response {
+        code: 200, text: Some("about about".into()) });
+}
@@ -4,7 +4,9 @@ extern crate nginx_config;
 use std::io::Read;
 use std::fs::File;
 
-use nginx_config::parse_main;
+use nginx_config::{parse_main, parse_directives_in};
+use nginx_config::ast::Main;
+use nginx_config::context::Context::{self, *};
 
 fn roundtrip(filename: &str) {
     let mut buf = String::with_capacity(1024);
@@ -15,45 +17,100 @@ fn roundtrip(filename: &str) {
     assert_eq!(ast.to_string(), buf);
 }
 
+/// Same as `roundtrip` but for a snippet that belongs into some block
+fn roundtrip_in(filename: &str, context: Context) {
+    let mut buf = String::with_capacity(1024);
+    let path = format!("tests/configs/{}.conf", filename);
+    let mut f = File::open(&path).unwrap();
+    f.read_to_string(&mut buf).unwrap();
+    let directives = parse_directives_in(&buf, context).unwrap();
+    let ast = Main { directives, end_comments: Vec::new() };
+    assert_eq!(ast.to_string(), buf);
+}
+
+fn read(path: &str) -> String {
+    let mut buf = String::with_capacity(1024);
+    File::open(path).unwrap().read_to_string(&mut buf).unwrap();
+    buf
+}
+
+/// For configs which aren't in canonical format: formatting gives the text
+/// in `tests/formatted` and keeps every comment of the original
+fn formatted(filename: &str) {
+    let source = read(&format!("tests/configs/{}.conf", filename));
+    let expected = read(&format!("tests/formatted/{}.conf", filename));
+    let text = parse_main(&source).unwrap().to_string();
+    assert_eq!(text, expected);
+    let comments = |text: &str| {
+        text.lines()
+            .filter_map(|line| line.find('#').map(|i| line[i..].to_string()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(comments(&text), comments(&source));
+    assert_eq!(parse_main(&text).unwrap().to_string(), text);
+}
+
 #[test] fn minimal() { roundtrip("minimal"); }
 #[test] fn master_process() { roundtrip("master_process"); }
 #[test] fn worker_processes_auto() { roundtrip("worker_processes_auto"); }
 #[test] fn worker_processes_7() { roundtrip("worker_processes_7"); }
 #[test] fn worker_processes_13() { roundtrip("worker_processes_13"); }
 #[test] fn http() { roundtrip("http"); }
-#[test] fn listen() { roundtrip("listen"); }
-#[test] fn proxy() { roundtrip("proxy"); }
-#[test] fn location() { roundtrip("location"); }
-#[test] fn two_locations() { roundtrip("two_locations"); }
-#[test] fn gzip() { roundtrip("gzip"); }
-#[test] fn gzip_proxied() { roundtrip("gzip_proxied"); }
-#[test] fn add_header() { roundtrip("add_header"); }
-#[test] fn root() { roundtrip("root"); }
-#[test] fn alias() { roundtrip("alias"); }
-#[test] fn client_max_body_size() { roundtrip("client_max_body_size"); }
+#[test] fn listen() { roundtrip_in("listen", Server); }
+#[test] fn proxy() { roundtrip_in("proxy", Location); }
+#[test] fn location() { roundtrip_in("location", Server); }
+#[test] fn two_locations() { roundtrip_in("two_locations", Server); }
+#[test] fn gzip() { roundtrip_in("gzip", Http); }
+#[test] fn gzip_proxied() { roundtrip_in("gzip_proxied", Http); }
+#[test] fn add_header() { roundtrip_in("add_header", Location); }
+#[test] fn root() { roundtrip_in("root", Location); }
+#[test] fn alias() { roundtrip_in("alias", Location); }
+#[test] fn client_max_body_size() { roundtrip_in("client_max_body_size", Server); }
 #[test] fn openresty() { roundtrip("openresty"); }
 #[test] fn include() { roundtrip("include"); }
-#[test] fn map() { roundtrip("map"); }
-#[test] fn error_pages() { roundtrip("error_pages"); }
-#[test] fn returns() { roundtrip("return"); }
-#[test] fn ssl() { roundtrip("ssl"); }
-#[test] fn rewrite() { roundtrip("rewrite"); }
-#[test] fn try_files() { roundtrip("try_files"); }
-#[test] fn empty_gif() { roundtrip("empty_gif"); }
-#[test] fn internal() { roundtrip("internal"); }
-#[test] fn expires() { roundtrip("expires"); }
-#[test] fn ifs() { roundtrip("ifs"); }
-#[test] fn allow_deny() { roundtrip("allow_deny"); }
-#[test] fn etag() { roundtrip("etag"); }
-#[test] fn recursive_error_pages() { roundtrip("recursive_error_pages"); }
-#[test] fn chunked() { roundtrip("chunked"); }
-#[test] fn keep_alive_timeout() { roundtrip("keep_alive_timeout"); }
-#[test] fn server_tokens() { roundtrip("server_tokens"); }
-#[test] fn default_type() { roundtrip("default_type"); }
-#[test] fn access_log() { roundtrip("access_log"); }
-#[test] fn limit_except() { roundtrip("limit_except"); }
-#[test] fn real_ip() { roundtrip("real_ip"); }
-#[test] fn error_log() { roundtrip("error_log"); }
-#[test] fn index() { roundtrip("index"); }
-// not working yet
-//#[test] fn few_locations() { roundtrip("few_locations"); }
+#[test] fn map() { roundtrip_in("map", Http); }
+#[test] fn map_comments() { roundtrip_in("map_comments", Http); }
+#[test] fn error_pages() { roundtrip_in("error_pages", Server); }
+#[test] fn returns() { roundtrip_in("return", Location); }
+#[test] fn ssl() { roundtrip_in("ssl", Server); }
+#[test] fn rewrite() { roundtrip_in("rewrite", Server); }
+#[test] fn try_files() { roundtrip_in("try_files", Location); }
+#[test] fn empty_gif() { roundtrip_in("empty_gif", Location); }
+#[test] fn internal() { roundtrip_in("internal", Location); }
+#[test] fn expires() { roundtrip_in("expires", Location); }
+#[test] fn ifs() { roundtrip_in("ifs", Server); }
+#[test] fn allow_deny() { roundtrip_in("allow_deny", Location); }
+#[test] fn etag() { roundtrip_in("etag", Http); }
+#[test] fn recursive_error_pages() { roundtrip_in("recursive_error_pages", Server); }
+#[test] fn chunked() { roundtrip_in("chunked", Http); }
+#[test] fn keep_alive_timeout() { roundtrip_in("keep_alive_timeout", Http); }
+#[test] fn keepalive_requests() { roundtrip("keepalive_requests"); }
+#[test] fn server_tokens() { roundtrip_in("server_tokens", Http); }
+#[test] fn default_type() { roundtrip_in("default_type", Http); }
+#[test] fn access_log() { roundtrip_in("access_log", Http); }
+#[test] fn limit_except() { roundtrip_in("limit_except", Location); }
+#[test] fn real_ip() { roundtrip_in("real_ip", Http); }
+#[test] fn error_log() { roundtrip_in("error_log", Http); }
+#[test] fn index() { roundtrip_in("index", Http); }
+#[test] fn unknown() { roundtrip("unknown"); }
+#[test] fn comments() { roundtrip("comments"); }
+#[test] fn few_locations() { formatted("few_locations"); }
+#[test] fn upstream() { roundtrip("upstream"); }
+#[test] fn main() { roundtrip("main"); }
+#[test] fn stream() { roundtrip("stream"); }
+#[test] fn ssl_full() { roundtrip_in("ssl_full", Server); }
+#[test] fn fastcgi() { roundtrip_in("fastcgi", Location); }
+#[test] fn uwsgi_scgi() { roundtrip_in("uwsgi_scgi", Location); }
+#[test] fn grpc() { roundtrip_in("grpc", Location); }
+#[test] fn limits() { roundtrip("limits"); }
+
+#[test]
+fn end_comments_of_snippet() {
+    let text = "daemon on;\n# after the last directive\n";
+    let directives = parse_directives_in(text, Context::Main).unwrap();
+    let ast = Main { directives, end_comments: Vec::new() };
+    // no directive to attach the comment to
+    assert_eq!(ast.to_string(), "daemon on;\n");
+    assert_eq!(parse_main(text).unwrap().end_comments,
+               vec![" after the last directive"]);
+}
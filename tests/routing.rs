@@ -0,0 +1,271 @@
+#![cfg(feature="simulation")]
+extern crate nginx_config;
+
+use nginx_config::parse_directives_in;
+use nginx_config::ast::{Address, Directive, Http, Item, LocationPattern};
+use nginx_config::context::Context;
+use nginx_config::routing::{find_location, find_named_location};
+use nginx_config::routing::find_server;
+
+
+fn parse(text: &str) -> Vec<Directive> {
+    parse_directives_in(text, Context::Server).unwrap()
+}
+
+fn route(dirs: &[Directive], uri: &str) -> Option<String> {
+    find_location(dirs, uri).unwrap().map(|m| {
+        let mut names = m.parents.iter()
+            .map(|l| l.pattern.to_string())
+            .collect::<Vec<_>>();
+        names.push(m.location.pattern.to_string());
+        names.join(" > ")
+    })
+}
+
+const CONFIG: &str = r#"
+    location = / {}
+    location / {}
+    location /documents/ {}
+    location ^~ /images/ {}
+    location ~* \.(gif|jpg|jpeg)$ {}
+    location @fallback {}
+"#;
+
+#[test]
+fn nginx_docs_example() {
+    let dirs = parse(CONFIG);
+    assert_eq!(route(&dirs, "/").as_deref(), Some("= /"));
+    assert_eq!(route(&dirs, "/index.html").as_deref(), Some("/"));
+    assert_eq!(route(&dirs, "/documents/document.html").as_deref(),
+               Some("/documents/"));
+    assert_eq!(route(&dirs, "/images/1.gif").as_deref(), Some("^~ /images/"));
+    assert_eq!(route(&dirs, "/documents/1.JPG").as_deref(),
+               Some(r"~* \.(gif|jpg|jpeg)$"));
+}
+
+#[test]
+fn no_match() {
+    let dirs = parse("location /a {} location = /b {}");
+    assert_eq!(route(&dirs, "/b/c"), None);
+    assert_eq!(route(&dirs, "/abc").as_deref(), Some("/a"));
+}
+
+#[test]
+fn longest_prefix_wins() {
+    let dirs = parse("location /a/b {} location /a {} location /a/bc {}");
+    assert_eq!(route(&dirs, "/a/bcd").as_deref(), Some("/a/bc"));
+    assert_eq!(route(&dirs, "/a/b/c").as_deref(), Some("/a/b"));
+}
+
+#[test]
+fn regexes_in_order() {
+    let dirs = parse(r"
+        location ~ ^/x {}
+        location ~ ^/x/y {}
+        location ~ \.PHP$ {}
+    ");
+    assert_eq!(route(&dirs, "/x/y").as_deref(), Some("~ ^/x"));
+    assert_eq!(route(&dirs, "/a.php"), None);
+    assert_eq!(route(&dirs, "/a.PHP").as_deref(), Some(r"~ \.PHP$"));
+}
+
+#[test]
+fn nested() {
+    let dirs = parse(r"
+        location /api/ {
+            location /api/v1/ {
+                location ~ \.json$ {}
+            }
+            location ~ ^/api/(\w+)/(\d+)$ {}
+        }
+        location ~ ^/api/static {}
+    ");
+    assert_eq!(route(&dirs, "/api/v1/x.json").as_deref(),
+               Some(r"/api/ > /api/v1/ > ~ \.json$"));
+    assert_eq!(route(&dirs, "/api/v1/x").as_deref(),
+               Some("/api/ > /api/v1/"));
+    // nested regex matched first, so the outer one isn't checked
+    assert_eq!(route(&dirs, "/api/static/x").as_deref(),
+               Some("~ ^/api/static"));
+    let m = find_location(&dirs, "/api/user/12").unwrap().unwrap();
+    assert_eq!(m.parents.len(), 1);
+    assert_eq!(m.captures, vec![
+        Some("/api/user/12".to_string()),
+        Some("user".to_string()),
+        Some("12".to_string()),
+    ]);
+}
+
+#[test]
+fn final_prefix_in_nested() {
+    let dirs = parse(r"
+        location /a/ {
+            location ^~ /a/b/ {}
+        }
+        location ~ ^/a/b {}
+    ");
+    // `^~` inside doesn't stop outer regexes
+    assert_eq!(route(&dirs, "/a/b/c").as_deref(), Some("~ ^/a/b"));
+}
+
+#[test]
+fn named() {
+    let dirs = parse(CONFIG);
+    let loc = find_named_location(&dirs, "fallback").unwrap();
+    assert_eq!(loc.pattern, LocationPattern::Named("fallback".into()));
+    assert!(find_named_location(&dirs, "other").is_none());
+    assert_eq!(route(&dirs, "@fallback"), None);
+}
+
+#[test]
+fn named_location_roundtrip() {
+    let dirs = parse("location @backend { return 502; }");
+    match dirs[0].item {
+        Item::Location(ref loc) => {
+            assert_eq!(loc.pattern,
+                       LocationPattern::Named("backend".into()));
+        }
+        ref item => panic!("unexpected {:?}", item),
+    }
+    assert!(dirs[0].to_string().starts_with("location @backend {"));
+}
+
+#[test]
+fn unsupported_regex() {
+    let dirs = parse(r"location ~ ^/(?!api) {}");
+    let err = find_location(&dirs, "/x").unwrap_err();
+    assert_eq!(err.pattern, "^/(?!api)");
+}
+
+fn http(text: &str) -> Http {
+    match parse_directives_in(text, Context::Main).unwrap()
+        .into_iter().next().unwrap().item
+    {
+        Item::Http(http) => http,
+        item => panic!("unexpected {:?}", item),
+    }
+}
+
+fn ip(addr: &str) -> Address {
+    Address::Ip(addr.parse().unwrap())
+}
+
+fn server(http: &Http, local: &str, host: Option<&str>, sni: Option<&str>)
+    -> Option<(String, bool)>
+{
+    find_server(http, &ip(local), host, sni).unwrap().map(|m| {
+        let name = m.server.directives.iter()
+            .find(|d| matches!(d.item, Item::Return(..)))
+            .map(|d| d.to_string().trim().to_string())
+            .unwrap_or_default();
+        (name, m.by_name)
+    })
+}
+
+fn by_host(http: &Http, host: &str) -> String {
+    server(http, "10.0.0.1:80", Some(host), None).unwrap().0
+}
+
+#[test]
+fn server_names() {
+    let http = http(r#"http {
+        server { server_name example.com; return 200 exact; }
+        server { server_name *.example.com; return 200 star; }
+        server { server_name .example.org www.example.*; return 200 mixed; }
+        server { server_name *.www.example.com; return 200 longer; }
+        server { server_name mail.*; return 200 mail; }
+        server { server_name ~^(?P<sub>\w+)\.example\.net$; return 200 re; }
+        server { server_name ~^www\.; return 200 www; }
+    }"#);
+    assert_eq!(by_host(&http, "example.com"), "return 200 exact;");
+    assert_eq!(by_host(&http, "EXAMPLE.com.:8080"), "return 200 exact;");
+    assert_eq!(by_host(&http, "a.example.com"), "return 200 star;");
+    assert_eq!(by_host(&http, "a.b.example.com"), "return 200 star;");
+    assert_eq!(by_host(&http, "a.www.example.com"), "return 200 longer;");
+    assert_eq!(by_host(&http, "example.org"), "return 200 mixed;");
+    assert_eq!(by_host(&http, "x.example.org"), "return 200 mixed;");
+    // wildcard at the start wins over the one at the end
+    assert_eq!(by_host(&http, "www.example.com"), "return 200 star;");
+    assert_eq!(by_host(&http, "www.example.net"), "return 200 mixed;");
+    assert_eq!(by_host(&http, "mail.example.net"), "return 200 mail;");
+    assert_eq!(by_host(&http, "foo.example.net"), "return 200 re;");
+    assert_eq!(by_host(&http, "www.other.com"), "return 200 www;");
+    assert_eq!(server(&http, "10.0.0.1:80", Some("other.com"), None),
+               Some(("return 200 exact;".into(), false)));
+
+    let m = find_server(&http, &ip("10.0.0.1:80"),
+                        Some("Foo.example.net"), None)
+        .unwrap().unwrap();
+    assert_eq!(m.captures, vec![
+        Some("foo.example.net".to_string()),
+        Some("foo".to_string()),
+    ]);
+}
+
+#[test]
+fn default_server() {
+    let http = http(r#"http {
+        server { listen 80; server_name a.com; return 200 a; }
+        server { listen 80 default_server; server_name ""; return 200 b; }
+        server { listen 8080; return 200 c; }
+        server { listen 127.0.0.1:8080; return 200 d; }
+        server { listen [::]:8080 default_server; return 200 e; }
+    }"#);
+    assert_eq!(server(&http, "10.0.0.1:80", Some("x.com"), None),
+               Some(("return 200 b;".into(), false)));
+    assert_eq!(server(&http, "10.0.0.1:80", Some("a.com"), None),
+               Some(("return 200 a;".into(), true)));
+    // empty name matches requests without the host
+    assert_eq!(server(&http, "10.0.0.1:80", None, None),
+               Some(("return 200 b;".into(), true)));
+    assert_eq!(server(&http, "10.0.0.1:8080", None, None),
+               Some(("return 200 c;".into(), false)));
+    // exact address takes precedence over the wildcard one
+    assert_eq!(server(&http, "127.0.0.1:8080", Some("a.com"), None),
+               Some(("return 200 d;".into(), false)));
+    assert_eq!(server(&http, "[::1]:8080", None, None),
+               Some(("return 200 e;".into(), false)));
+    assert_eq!(server(&http, "10.0.0.1:443", None, None), None);
+}
+
+#[test]
+fn implicit_listen() {
+    let http = http(r#"http {
+        server { server_name a.com; return 200 a; }
+        server { listen 81; server_name b.com; return 200 b; }
+    }"#);
+    assert_eq!(server(&http, "10.0.0.1:80", Some("b.com"), None),
+               Some(("return 200 a;".into(), false)));
+    assert_eq!(server(&http, "[::1]:80", None, None), None);
+}
+
+#[test]
+fn sni() {
+    let http = http(r#"http {
+        server { listen 443 ssl; server_name a.com; return 200 a; }
+        server { listen 443 ssl; server_name b.com; return 200 b; }
+        server { listen 80; server_name a.com; return 200 c; }
+        server { listen 80; server_name b.com; return 200 d; }
+    }"#);
+    assert_eq!(server(&http, "10.0.0.1:443", None, Some("b.com")),
+               Some(("return 200 b;".into(), true)));
+    // host header takes precedence
+    assert_eq!(server(&http, "10.0.0.1:443", Some("a.com"), Some("b.com")),
+               Some(("return 200 a;".into(), true)));
+    // SNI is ignored without ssl
+    assert_eq!(server(&http, "10.0.0.1:80", None, Some("b.com")),
+               Some(("return 200 c;".into(), false)));
+}
+
+#[test]
+fn unix_socket() {
+    let http = http(r#"http {
+        server { listen 80; return 200 a; }
+        server { listen unix:/run/nginx.sock; return 200 b; }
+    }"#);
+    let m = find_server(&http, &Address::Unix("/run/nginx.sock".into()),
+                        None, None).unwrap().unwrap();
+    assert_eq!(m.server.directives[1].to_string().trim(), "return 200 b;");
+    assert!(find_server(&http, &Address::Unix("/tmp/x".into()), None, None)
+            .unwrap().is_none());
+}
@@ -1,10 +1,13 @@
 extern crate nginx_config;
 #[cfg(test)] #[macro_use] extern crate pretty_assertions;
 
-use nginx_config::parse_main;
+use nginx_config::parse_directives_in;
+use nginx_config::ast::Main;
+use nginx_config::context::Context;
 
 fn roundtrip(value: &str) {
-    let ast = parse_main(&value).unwrap();
+    let directives = parse_directives_in(&value, Context::Server).unwrap();
+    let ast = Main { directives, end_comments: Vec::new() };
     assert_eq!(ast.to_string(), value);
 }
 
@@ -0,0 +1,59 @@
+extern crate nginx_config;
+
+use nginx_config::parse_main;
+use nginx_config::ast::Item;
+
+
+const CONFIG: &str = "\
+http {
+    server {
+        root \"/var/www\";  # docs
+    }
+}
+";
+
+#[test]
+fn directive_spans() {
+    let main = parse_main(CONFIG).unwrap();
+    let dirs = main.all_directives().collect::<Vec<_>>();
+    let texts = dirs.iter()
+        .map(|d| &CONFIG[d.span.range()])
+        .collect::<Vec<_>>();
+    assert_eq!(texts, vec![
+        &CONFIG[..CONFIG.len()-1],
+        "server {\n        root \"/var/www\";  # docs\n    }",
+        "root \"/var/www\";",
+    ]);
+    let root = dirs[2];
+    assert_eq!(root.span.start, root.position);
+    assert_eq!(root.span.start.to_string(), "3:9");
+    assert_eq!(root.span.end.to_string(), "3:25");
+}
+
+#[test]
+fn value_span() {
+    let main = parse_main(CONFIG).unwrap();
+    let root = main.all_directives().nth(2).unwrap();
+    match root.item {
+        Item::Root(ref value) => {
+            assert_eq!(&CONFIG[value.span().range()], "\"/var/www\"");
+            assert_eq!(value.position().to_string(), "3:14");
+        }
+        ref item => panic!("unexpected {:?}", item),
+    }
+}
+
+#[test]
+fn block_braces() {
+    let main = parse_main(CONFIG).unwrap();
+    match main.directives[0].item {
+        Item::Http(ref http) => {
+            let (open, close) = http.position;
+            assert_eq!(&CONFIG[open.offset..open.offset+1], "{");
+            assert_eq!(&CONFIG[close.offset-1..close.offset], "}");
+            assert_eq!(open.to_string(), "1:6");
+            assert_eq!(close.to_string(), "5:2");
+        }
+        ref item => panic!("unexpected {:?}", item),
+    }
+}
@@ -0,0 +1,105 @@
+//! Proptest strategies shared by the randomized tests
+#![allow(dead_code)] // every test uses only some of the strategies
+
+use proptest::prelude::*;
+use proptest::sample::{select, Index};
+
+use nginx_config::ast::Value;
+use nginx_config::value::Part;
+
+
+pub const WORDS: &[&str] = &[
+    "http", "server", "location", "if", "events", "stream", "upstream",
+    "mail", "map", "types", "limit_except", "listen", "server_name", "root",
+    "alias", "return", "rewrite", "error_page", "try_files", "set",
+    "proxy_pass", "proxy_set_header", "proxy_read_timeout", "add_header",
+    "access_log", "error_log", "gzip", "gzip_proxied", "expires", "allow",
+    "deny", "include", "user", "worker_processes", "keepalive_timeout",
+    "client_max_body_size", "limit_req_zone", "limit_req", "real_ip_header",
+    "set_real_ip_from", "ssl_protocols", "fastcgi_pass", "grpc_pass",
+    "index", "etag", "default_type", "server_tokens", "hash", "random",
+    "zone", "keepalive", "default_server", "on", "off", "any", "all",
+    "unix:/tmp/sock", "127.0.0.1:80", "[::1]:8080", "*:80", "*.example.com",
+    "~", "~*", "=", "^~", "@fallback", "=404", "301", "10s", "1m", "512k",
+    "$host", "${host}", "$", "${", "${}", "$1", "\"", "'", "\\", "\"a b\"",
+    "'$uri'", "\"${x}_y\"", "(", ")", "!", "!=", "-f", "!-d", "$arg_x=1",
+    "if=$loggable", "gzip=9", "buffer=32k", "flush=", "weight=", "last",
+    "break", "redirect", "permanent", "http://", "https://[::1]", "#",
+    "# comment", "ж", "\u{0}", "\t", "\r",
+];
+
+/// Characters of the literal parts of values
+///
+/// There is no `$` here: nginx has no escape for the dollar sign, so a
+/// literal `$name` is formatted the same as a variable (see
+/// `Value::literal`) and can't roundtrip.
+const LITERAL_CHARS: &[char] = &[
+    'a', 'z', '0', '_', '-', '/', '.', ':', '#', '^', '~', '=',
+    ' ', '\t', '\n', '\r', ';', '{', '}', '"', '\'', '\\', 'ж',
+];
+
+const VARIABLES: &[&str] = &["host", "uri", "arg_x", "1", "http_x_real_ip"];
+
+pub fn part() -> impl Strategy<Value=Part> {
+    prop_oneof![
+        2 => prop::collection::vec(select(LITERAL_CHARS), 0..6)
+            .prop_map(|chars| Part::Literal(chars.into_iter().collect())),
+        1 => select(VARIABLES).prop_map(|name| Part::Variable(name.into())),
+    ]
+}
+
+pub fn parts() -> impl Strategy<Value=Vec<Part>> {
+    prop::collection::vec(part(), 0..5)
+}
+
+/// Nginx-ish words, braces and semicolons in random order
+pub fn token_soup() -> impl Strategy<Value=String> {
+    let piece = prop_oneof![
+        1 => Just(" {\n".to_string()),
+        1 => Just("}\n".to_string()),
+        2 => Just(";\n".to_string()),
+        5 => select(WORDS).prop_map(|word| format!("{} ", word)),
+        1 => parts().prop_map(|parts| {
+            format!("{} ", Value::from_parts(parts))
+        }),
+    ];
+    prop::collection::vec(piece, 0..40)
+        .prop_map(|pieces| pieces.concat())
+}
+
+#[derive(Debug, Clone)]
+enum Mutation {
+    Remove(Index),
+    Truncate(Index),
+    Insert(Index, &'static str),
+}
+
+/// One of the `texts` with a few characters removed or words inserted
+pub fn mutated(texts: Vec<String>) -> impl Strategy<Value=String> {
+    let mutation = prop_oneof![
+        any::<Index>().prop_map(Mutation::Remove),
+        any::<Index>().prop_map(Mutation::Truncate),
+        (any::<Index>(), select(WORDS))
+            .prop_map(|(idx, word)| Mutation::Insert(idx, word)),
+    ];
+    (select(texts), prop::collection::vec(mutation, 1..5))
+        .prop_map(|(text, mutations)| {
+            let mut chars = text.chars().collect::<Vec<_>>();
+            for mutation in mutations {
+                match mutation {
+                    Mutation::Remove(idx) if !chars.is_empty() => {
+                        chars.remove(idx.index(chars.len()));
+                    }
+                    Mutation::Truncate(idx) if !chars.is_empty() => {
+                        chars.truncate(idx.index(chars.len()));
+                    }
+                    Mutation::Remove(_) | Mutation::Truncate(_) => {}
+                    Mutation::Insert(idx, word) => {
+                        let idx = idx.index(chars.len() + 1);
+                        chars.splice(idx..idx, word.chars());
+                    }
+                }
+            }
+            chars.into_iter().collect()
+        })
+}
@@ -0,0 +1,188 @@
+extern crate nginx_config;
+
+use std::time;
+
+use nginx_config::parse_directives_in;
+use nginx_config::ast::{Item, Size, Duration, Rate, RateUnit, Typed};
+use nginx_config::context::Context;
+
+fn parse_item(text: &str) -> Item {
+    parse_directives_in(text, Context::Location).unwrap()
+        .into_iter().next().unwrap().item
+}
+
+#[test]
+fn sizes() {
+    assert_eq!("512".parse(), Ok(Size::from_bytes(512)));
+    assert_eq!("512k".parse(), Ok(Size::from_bytes(512 << 10)));
+    assert_eq!("10M".parse(), Ok(Size::from_bytes(10 << 20)));
+    assert_eq!("2g".parse(), Ok(Size::from_bytes(2 << 30)));
+    assert!("".parse::<Size>().is_err());
+    assert!("k".parse::<Size>().is_err());
+    assert!("-1k".parse::<Size>().is_err());
+    assert!("1kb".parse::<Size>().is_err());
+    assert!("99999999999999999999".parse::<Size>().is_err());
+    assert!("99999999999g".parse::<Size>().is_err());
+}
+
+#[test]
+fn size_display() {
+    assert_eq!(Size::from_bytes(0).to_string(), "0");
+    assert_eq!(Size::from_bytes(1000).to_string(), "1000");
+    assert_eq!(Size::from_bytes(1536).to_string(), "1536");
+    assert_eq!(Size::from_bytes(2048 << 10).to_string(), "2m");
+    assert_eq!(Size::from_bytes(100 << 20).to_string(), "100m");
+    assert_eq!(Size::from_bytes(1024 << 20).to_string(), "1g");
+}
+
+#[test]
+fn size_spelling_kept() {
+    let size = "100M".parse::<Size>().unwrap();
+    assert_eq!(size.to_string(), "100M");
+    assert_eq!(size, Size::from_bytes(100 << 20));
+    assert_eq!(size.bytes(), 100 << 20);
+    assert_eq!("2048k".parse::<Size>().unwrap().to_string(), "2048k");
+}
+
+#[test]
+fn durations() {
+    assert_eq!("30".parse(), Ok(Duration::from_secs(30)));
+    assert_eq!("30s".parse(), Ok(Duration::from_secs(30)));
+    assert_eq!("500ms".parse(), Ok(Duration::from_millis(500)));
+    assert_eq!("1h30m".parse(), Ok(Duration::from_secs(5400)));
+    assert_eq!("1m30".parse(), Ok(Duration::from_secs(90)));
+    assert_eq!("1d12h".parse(), Ok(Duration::from_secs(36 * 3600)));
+    assert_eq!("2w".parse(), Ok(Duration::from_secs(14 * 86400)));
+    assert_eq!("1M".parse(), Ok(Duration::from_secs(30 * 86400)));
+    assert_eq!("1y".parse(), Ok(Duration::from_secs(365 * 86400)));
+    assert_eq!("1s500ms".parse(), Ok(Duration::from_millis(1500)));
+    assert!("".parse::<Duration>().is_err());
+    assert!("s".parse::<Duration>().is_err());
+    assert!("30m1h".parse::<Duration>().is_err());
+    assert!("1m1m".parse::<Duration>().is_err());
+    assert!("1s30".parse::<Duration>().is_err());
+    assert!("5x".parse::<Duration>().is_err());
+    assert!("99999999999999y".parse::<Duration>().is_err());
+    let std: time::Duration = "1m".parse::<Duration>().unwrap().into();
+    assert_eq!(std, time::Duration::from_secs(60));
+}
+
+#[test]
+fn duration_display() {
+    assert_eq!(Duration::from_millis(0).to_string(), "0");
+    assert_eq!(Duration::from_millis(250).to_string(), "250ms");
+    assert_eq!(Duration::from_secs(90).to_string(), "1m30s");
+    assert_eq!(Duration::from_secs(3600).to_string(), "1h");
+    assert_eq!(Duration::from_secs(7 * 86400).to_string(), "7d");
+    assert_eq!(Duration::from_millis(86_401_005).to_string(), "1d1s5ms");
+}
+
+#[test]
+fn duration_spelling_kept() {
+    let duration = "60s".parse::<Duration>().unwrap();
+    assert_eq!(duration.to_string(), "60s");
+    assert_eq!(duration, Duration::from_secs(60));
+    assert_eq!(duration.millis(), 60_000);
+    assert_eq!(Duration::from_secs(60).to_string(), "1m");
+    assert_eq!("1w".parse::<Duration>().unwrap().to_string(), "1w");
+}
+
+#[test]
+fn canonical_directives() {
+    let item = parse_item("client_max_body_size 100M;");
+    assert_eq!(item.to_string(), "client_max_body_size 100M;\n");
+    assert_eq!(item, Item::ClientMaxBodySize(
+        Typed::Parsed(Size::from_bytes(100 << 20))));
+    let item = Item::ClientMaxBodySize(
+        Typed::Parsed(Size::from_bytes(100 << 20)));
+    assert_eq!(item.to_string(), "client_max_body_size 100m;\n");
+    let item = Item::ProxyReadTimeout(Typed::Parsed(Duration::from_secs(60)));
+    assert_eq!(item.to_string(), "proxy_read_timeout 1m;\n");
+    assert_eq!(parse_item("proxy_read_timeout 60s;").to_string(),
+               "proxy_read_timeout 60s;\n");
+}
+
+#[test]
+fn rates() {
+    let rate: Rate = "10r/s".parse().unwrap();
+    assert_eq!(rate, Rate { requests: 10, unit: RateUnit::PerSecond });
+    assert_eq!(rate.per_second(), 10.);
+    let rate: Rate = "30r/m".parse().unwrap();
+    assert_eq!(rate.per_second(), 0.5);
+    assert_eq!(rate.to_string(), "30r/m");
+    assert!("10r/h".parse::<Rate>().is_err());
+    assert!("r/s".parse::<Rate>().is_err());
+}
+
+#[test]
+fn typed_directives() {
+    assert_eq!(parse_item("client_max_body_size 10m;"),
+        Item::ClientMaxBodySize(Typed::Parsed(Size::from_bytes(10 << 20))));
+    assert_eq!(parse_item("proxy_read_timeout 1h30m;"),
+        Item::ProxyReadTimeout(Typed::Parsed(Duration::from_secs(5400))));
+    assert_eq!(parse_item("keepalive_timeout 75s 60;"),
+        Item::KeepaliveTimeout(
+            Typed::Parsed(Duration::from_secs(75)),
+            Some(Typed::Parsed(Duration::from_secs(60)))));
+    match parse_item("access_log /log fmt buffer=32k flush=5m;") {
+        Item::AccessLog(nginx_config::ast::AccessLog::On(opt)) => {
+            assert_eq!(opt.buffer, Some(Size::from_bytes(32 << 10)));
+            assert_eq!(opt.flush, Some(Duration::from_secs(300)));
+        }
+        item => panic!("unexpected {:?}", item),
+    }
+}
+
+#[test]
+fn variables_kept() {
+    let item = parse_item("limit_rate $rate;");
+    match item {
+        Item::LimitRate(Typed::Variables(ref v)) => {
+            assert_eq!(v.variables().collect::<Vec<_>>(), vec!["rate"]);
+        }
+        ref item => panic!("unexpected {:?}", item),
+    }
+    assert_eq!(item.to_string(), "limit_rate $rate;\n");
+}
+
+#[test]
+fn invalid() {
+    let err = parse_directives_in("client_max_body_size 10mb;",
+                                  Context::Http).unwrap_err();
+    assert!(err.to_string().contains("invalid size \"10mb\""), "{}", err);
+    let err = parse_directives_in("proxy_read_timeout 1m1h;",
+                                  Context::Http).unwrap_err();
+    assert!(err.to_string().contains("invalid time interval"), "{}", err);
+}
+
+#[test]
+fn limit_req_zone_options() {
+    let parse = |text| {
+        parse_directives_in(text, Context::Http)
+            .map(|d| d.into_iter().next().unwrap().item.to_string())
+    };
+    let canonical = "limit_req_zone $a zone=one:10m rate=1r/s sync;\n";
+    assert_eq!(parse("limit_req_zone $a zone=one:10m rate=1r/s sync;")
+               .unwrap(), canonical);
+    assert_eq!(parse("limit_req_zone $a rate=1r/s zone=one:10m sync;")
+               .unwrap(), canonical);
+    assert_eq!(parse("limit_req_zone $a sync rate=1r/s zone=one:10m;")
+               .unwrap(), canonical);
+    assert!(parse("limit_req_zone $a zone=one:10m;").is_err());
+    assert!(parse("limit_req_zone $a rate=1r/s;").is_err());
+}
+
+#[test]
+fn limit_status() {
+    assert_eq!(parse_item("limit_req_status 429;"),
+               Item::LimitReqStatus(429));
+    assert_eq!(parse_item("limit_conn_status 599;"),
+               Item::LimitConnStatus(599));
+    for text in &["limit_req_status 200;", "limit_conn_status 399;",
+                  "limit_req_status 301;", "limit_conn_status 600;"]
+    {
+        let err = parse_directives_in(text, Context::Location).unwrap_err();
+        assert!(err.to_string().contains("between 400 and 599"),
+                "{}: {}", text, err);
+    }
+}
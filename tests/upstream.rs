@@ -0,0 +1,67 @@
+extern crate nginx_config;
+#[cfg(test)] #[macro_use] extern crate pretty_assertions;
+
+use std::path::PathBuf;
+
+use nginx_config::parse_directives_in;
+use nginx_config::ast::{Item, UpstreamAddress, UpstreamServer};
+use nginx_config::context::Context;
+
+fn parse_server(text: &str) -> UpstreamServer {
+    let dir = parse_directives_in(text, Context::Upstream).unwrap()
+        .into_iter().next().unwrap();
+    match dir.item {
+        Item::UpstreamServer(srv) => srv,
+        item => panic!("unexpected {:?}", item),
+    }
+}
+
+fn roundtrip(text: &str) -> String {
+    let dir = parse_directives_in(text, Context::Upstream).unwrap()
+        .into_iter().next().unwrap();
+    dir.to_string()
+}
+
+#[test]
+fn quoted_address() {
+    let srv = parse_server(r#"server "127.0.0.1:80" weight=2;"#);
+    assert_eq!(srv.address,
+               UpstreamAddress::Ip("127.0.0.1".parse().unwrap(), Some(80)));
+    assert_eq!(srv.weight, Some(2));
+    let srv = parse_server(r#"server 'backend.local:8080';"#);
+    assert_eq!(srv.address,
+               UpstreamAddress::Name("backend.local".into(), Some(8080)));
+}
+
+#[test]
+fn ipv6_address() {
+    let srv = parse_server("server [::1]:80;");
+    assert_eq!(srv.address,
+               UpstreamAddress::Ip("::1".parse().unwrap(), Some(80)));
+    let srv = parse_server("server [::1];");
+    assert_eq!(srv.address,
+               UpstreamAddress::Ip("::1".parse().unwrap(), None));
+    assert_eq!(roundtrip("server [::1]:80;"), "server [::1]:80;\n");
+}
+
+#[test]
+fn unix_address() {
+    let srv = parse_server("server unix:/tmp/backend.sock;");
+    assert_eq!(srv.address,
+               UpstreamAddress::Unix(PathBuf::from("/tmp/backend.sock")));
+    let srv = parse_server(r#"server "unix:/tmp/my backend.sock";"#);
+    assert_eq!(srv.address,
+               UpstreamAddress::Unix(PathBuf::from("/tmp/my backend.sock")));
+    assert_eq!(roundtrip(r#"server "unix:/tmp/my backend.sock";"#),
+               "server \"unix:/tmp/my backend.sock\";\n");
+}
+
+#[test]
+fn unknown_parameters() {
+    let srv = parse_server(
+        "server 10.0.0.1:80 slow_start=30s weight=5 route=a;");
+    assert_eq!(srv.weight, Some(5));
+    assert_eq!(srv.other, vec!["slow_start=30s", "route=a"]);
+    assert_eq!(roundtrip("server 10.0.0.1:80 slow_start=30s weight=5;"),
+               "server 10.0.0.1:80 weight=5 slow_start=30s;\n");
+}
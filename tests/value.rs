@@ -0,0 +1,128 @@
+extern crate nginx_config;
+#[macro_use] extern crate proptest;
+
+mod strategies;
+
+use nginx_config::ast::Value;
+use nginx_config::value::Part;
+use proptest::test_runner::Config;
+
+
+#[test]
+fn parts() {
+    let value: Value = "http://${host}:8080$uri".parse().unwrap();
+    assert_eq!(value.parts().cloned().collect::<Vec<_>>(), vec![
+        Part::Literal("http://".into()),
+        Part::Variable("host".into()),
+        Part::Literal(":8080".into()),
+        Part::Variable("uri".into()),
+    ]);
+    assert_eq!(value.variables().collect::<Vec<_>>(), vec!["host", "uri"]);
+    assert!(!value.is_literal());
+    assert_eq!(value.as_literal(), None);
+}
+
+#[test]
+fn literal() {
+    let value: Value = "/var/www".parse().unwrap();
+    assert!(value.is_literal());
+    assert_eq!(value.as_literal(), Some("/var/www"));
+    assert_eq!(value, Value::literal("/var/www"));
+    assert_eq!(Value::literal("a b").to_string(), "\"a b\"");
+}
+
+#[test]
+fn from_parts() {
+    let value = Value::from_parts(vec![
+        Part::Literal("/data/".into()),
+        Part::Literal("".into()),
+        Part::Variable("host".into()),
+        Part::Literal("".into()),
+        Part::Literal("_".into()),
+        Part::Literal("files".into()),
+    ]);
+    assert_eq!(value.to_string(), "/data/${host}_files");
+    assert_eq!(value, "/data/${host}_files".parse().unwrap());
+    assert_eq!(Value::variable("uri").to_string(), "$uri");
+}
+
+#[test]
+fn braced_variable_in_quotes() {
+    let value: Value = "\"${host}\"".parse().unwrap();
+    assert_eq!(value, Value::variable("host"));
+    let value: Value = "\"${host}_1 $uri\"".parse().unwrap();
+    assert_eq!(value.parts().cloned().collect::<Vec<_>>(), vec![
+        Part::Variable("host".into()),
+        Part::Literal("_1 ".into()),
+        Part::Variable("uri".into()),
+    ]);
+    assert_eq!(value.to_string(), "\"${host}_1 $uri\"");
+    assert!("\"${host\"".parse::<Value>().is_err());
+    assert!("\"${}\"".parse::<Value>().is_err());
+}
+
+#[test]
+fn escapes() {
+    let value: Value = r#""a \"b\" \\ \n\t\r \' \.""#.parse().unwrap();
+    assert_eq!(value.as_literal(), Some("a \"b\" \\ \n\t\r ' \\."));
+    // a backslash is only escaped where it's ambiguous
+    assert_eq!(value.to_string(), r#""a \"b\" \ \n\t\r ' \.""#);
+    let value: Value = r#"'it\'s'"#.parse().unwrap();
+    assert_eq!(value.as_literal(), Some("it's"));
+    assert_eq!(value.to_string(), r#""it's""#);
+}
+
+#[test]
+fn quoting() {
+    assert_eq!(Value::literal("").to_string(), "\"\"");
+    assert_eq!(Value::literal("#x").to_string(), "\"#x\"");
+    assert_eq!(Value::literal("a#x").to_string(), "a#x");
+    assert_eq!(Value::literal("^/\\d+$").to_string(), "^/\\d+$");
+    assert_eq!(Value::literal("dir\\").to_string(), "\"dir\\\\\"");
+    let value = Value::from_parts(vec![
+        Part::Literal("a\\".into()),
+        Part::Variable("uri".into()),
+    ]);
+    assert_eq!(value.to_string(), "\"a\\\\$uri\"");
+    assert_eq!(value, value.to_string().parse().unwrap());
+}
+
+proptest! {
+    #![proptest_config(Config::with_cases(10000))]
+
+    #[test]
+    fn random_roundtrip(parts in strategies::parts()) {
+        let value = Value::from_parts(parts);
+        let text = value.to_string();
+        let parsed = text.parse::<Value>();
+        prop_assert!(parsed.is_ok(), "{:?} -> {:?}: {:?}",
+                     value, text, parsed);
+        prop_assert_eq!(parsed.unwrap(), value, "text: {:?}", text);
+    }
+}
+
+#[test]
+fn dollar_in_literal() {
+    // there is no way to escape a dollar sign in nginx
+    let value = Value::literal("$x");
+    assert_eq!(value.to_string(), "$x");
+    assert_eq!(value.to_string().parse::<Value>().unwrap(),
+               Value::variable("x"));
+}
+
+#[test]
+fn captures() {
+    let v: Value = "/$1x$2".parse().unwrap();
+    assert_eq!(v.parts().cloned().collect::<Vec<_>>(), vec![
+        Part::Literal("/".into()),
+        Part::Variable("1".into()),
+        Part::Literal("x".into()),
+        Part::Variable("2".into()),
+    ]);
+    assert_eq!(v.to_string(), "/$1x$2");
+    let v: Value = "\"$12 ${12}\"".parse().unwrap();
+    assert_eq!(v.variables().collect::<Vec<_>>(), vec!["1", "12"]);
+    assert_eq!(v.to_string(), "\"$12 ${12}\"");
+    let v: Value = "$0x".parse().unwrap();
+    assert_eq!(v.variables().collect::<Vec<_>>(), vec!["0x"]);
+}
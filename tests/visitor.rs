@@ -1,10 +1,17 @@
 extern crate nginx_config;
 #[cfg(test)] #[macro_use] extern crate pretty_assertions;
 
-use nginx_config::{parse_main, visitors};
+use nginx_config::{parse_directives_in, visitors};
+use nginx_config::ast::Main;
+use nginx_config::context::Context;
+
+fn parse_server(text: &str) -> Main {
+    let directives = parse_directives_in(text, Context::Server).unwrap();
+    Main { directives, end_comments: Vec::new() }
+}
 
 fn list(value: &str) -> Vec<String> {
-    let ast = parse_main(&value).unwrap();
+    let ast = parse_server(&value);
     ast.all_directives().map(|s| s.to_string()).collect()
 }
 
@@ -13,7 +20,7 @@ fn replace_vars<'a, F, S>(text: &str, f: F)
     where F: FnMut(&str) -> Option<S>,
           S: AsRef<str> + Into<String> + 'a,
 {
-    let mut ast = parse_main(&text).unwrap();
+    let mut ast = parse_server(&text);
     visitors::replace_vars(&mut ast.directives, f);
     ast.to_string()
 }
@@ -32,6 +39,18 @@ fn iterlocation() {
     ]);
 }
 
+#[test]
+fn iterunknown() {
+    assert_eq!(list(r#"
+        types {
+            text/html html htm;
+        }
+    "#), vec![
+        "types {\n    text/html html htm;\n}\n",
+        "text/html html htm;\n",
+    ]);
+}
+
 #[test]
 fn test_replace_vars() {
     assert_eq!(replace_vars(r#"